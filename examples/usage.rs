@@ -1,36 +1,60 @@
 extern crate baal;
 
+use baal::prelude::*;
+
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
 fn main() {
-    let setting = baal::Setting {
+    let setting = Setting {
         effect_dir: "assets/effects".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.5,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
-        distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        distance_model: DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
 
-        music_transition: baal::music::MusicTransition::Instant,
+        music_transition: MusicTransition::Instant,
 
         short_effects: vec!("explosion.ogg".into(),"stereo_explosion.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!("electro_fly_from_xonotic_game.ogg".into()),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!("village.ogg".into()),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
     baal::init(&setting).unwrap();
-    baal::music::play(0);
+    let fly = setting.persistent_effect_id("electro_fly_from_xonotic_game.ogg").unwrap();
+    let explosion = setting.short_effect_id("explosion.ogg").unwrap();
+    baal::music::play(setting.music_id("village.ogg").unwrap());
 
     baal::effect::set_listener([1.,1.,1.]);
 
-    baal::effect::persistent::add_position(0,[0.0,0.0,0.0]);
-    baal::effect::persistent::add_position(0,[0.0,0.0,10.0]);
+    baal::effect::persistent::add_position(fly,[0.0,0.0,0.0]);
+    baal::effect::persistent::add_position(fly,[0.0,0.0,10.0]);
     baal::effect::persistent::update_volume_for_all();
 
-    baal::effect::short::play(0,[0.,0.,0.]);
+    baal::effect::short::play(explosion,[0.,0.,0.]);
 
     thread::sleep(Duration::from_secs(40));
 
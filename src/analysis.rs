@@ -0,0 +1,43 @@
+//! this module allow to analyse audio files without playing them
+//!
+//! it doesn't need the audio player to be initialized
+
+use rodio::decoder::Decoder;
+use rodio::Sample;
+
+use std::fs::File;
+use std::path::Path;
+
+use super::InitError;
+
+/// decode the file and return the peak of the absolute value of its samples for each
+/// of the buckets
+///
+/// the samples are split in `buckets` parts of the same length, it is meant to
+/// render waveforms
+pub fn peaks<P: AsRef<Path>>(path: P, buckets: usize) -> Result<Vec<f32>,InitError> {
+    let path = path.as_ref();
+    let file = try!(File::open(path).map_err(|e| InitError::FileOpenError(path.to_path_buf(), e)));
+    let decoder = try!(Decoder::new(file).map_err(|e| InitError::DecodeError(path.to_path_buf(), e)));
+    let samples = decoder.map(|sample| sample.to_f32()).collect::<Vec<_>>();
+    Ok(envelope(&samples, buckets))
+}
+
+fn envelope(samples: &[f32], buckets: usize) -> Vec<f32> {
+    (0..buckets)
+        .map(|i| {
+            let start = i * samples.len() / buckets;
+            let end = (i + 1) * samples.len() / buckets;
+            samples[start..end].iter().fold(0., |peak, sample| sample.abs().max(peak))
+        })
+        .collect()
+}
+
+#[test]
+fn test_envelope() {
+    let samples = [0., 0.5, -1., 0.25, 0., -0.1];
+    assert_eq!(envelope(&samples, 3), vec!(0.5, 1., 0.1));
+    assert_eq!(envelope(&samples, 1), vec!(1.));
+    assert_eq!(envelope(&samples, 0), Vec::<f32>::new());
+    assert_eq!(envelope(&[], 2), vec!(0., 0.));
+}
@@ -0,0 +1,243 @@
+//! this module allow to choose the audio output of baal
+//!
+//! the rodio backend plays sounds on an endpoint, the null backend discards
+//! them, it is meant for tests and headless servers, the render backend mixes
+//! them on demand with `baal::render`, it is meant for tests
+
+use rodio;
+use rodio::Sample;
+use rodio::Source;
+use rodio::source::UniformSourceIterator;
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+
+/// an audio output able to create sinks
+#[doc(hidden)]
+pub trait AudioBackend {
+    /// the sinks of the backend
+    type Sink: AudioSink;
+
+    /// create a new sink
+    fn new_sink(&self) -> Self::Sink;
+}
+
+/// a queue of sources played one after the other
+///
+/// sources are stopped when the sink is dropped unless it is detached
+#[doc(hidden)]
+pub trait AudioSink {
+    /// append the source to the queue
+    fn append<S>(&self, source: S) where S: Source + Send + 'static, S::Item: Sample + Send;
+
+    /// keep playing the sources after the sink is dropped
+    fn detach(self);
+}
+
+impl AudioBackend for rodio::Endpoint {
+    type Sink = rodio::Sink;
+
+    fn new_sink(&self) -> rodio::Sink {
+        rodio::Sink::new(self)
+    }
+}
+
+impl AudioSink for rodio::Sink {
+    fn append<S>(&self, source: S) where S: Source + Send + 'static, S::Item: Sample + Send {
+        rodio::Sink::append(self, source);
+    }
+
+    fn detach(self) {
+        rodio::Sink::detach(self);
+    }
+}
+
+/// a backend discarding all sounds
+#[doc(hidden)]
+pub struct NullBackend;
+
+/// a sink discarding all sources, they are dropped immediately
+#[doc(hidden)]
+pub struct NullSink;
+
+impl AudioBackend for NullBackend {
+    type Sink = NullSink;
+
+    fn new_sink(&self) -> NullSink {
+        NullSink
+    }
+}
+
+impl AudioSink for NullSink {
+    fn append<S>(&self, _source: S) where S: Source + Send + 'static, S::Item: Sample + Send {
+    }
+
+    fn detach(self) {
+    }
+}
+
+/// the samples rate of the render backend, it renders mono samples
+pub const RENDER_SAMPLES_RATE: u32 = 44_100;
+
+type Queue = Arc<Mutex<VecDeque<Box<Iterator<Item = f32> + Send>>>>;
+
+/// a backend mixing its sinks on demand
+#[derive(Default)]
+pub struct RenderBackend {
+    queues: Mutex<Vec<(Queue, Arc<AtomicBool>)>>,
+    /// the number of mixed samples beyond full scale
+    clipped: AtomicUsize,
+}
+
+/// a sink of the render backend
+#[doc(hidden)]
+pub struct RenderSink {
+    queue: Queue,
+    stopped: Arc<AtomicBool>,
+    detached: bool,
+}
+
+impl RenderBackend {
+    /// create a render backend
+    pub fn new() -> RenderBackend {
+        RenderBackend::default()
+    }
+
+    /// mix the next samples of all the sinks
+    #[doc(hidden)]
+    pub fn render(&self, samples: usize) -> Vec<f32> {
+        let mut queues = self.queues.lock().unwrap();
+        queues.retain(|&(_, ref stopped)| !stopped.load(Relaxed));
+
+        let mut output = vec![0f32; samples];
+        for &(ref queue, _) in queues.iter() {
+            let mut queue = queue.lock().unwrap();
+            for sample in output.iter_mut() {
+                loop {
+                    let next = match queue.front_mut() {
+                        Some(source) => source.next(),
+                        None => break,
+                    };
+                    match next {
+                        Some(value) => {
+                            *sample += value;
+                            break;
+                        },
+                        None => {
+                            queue.pop_front();
+                        },
+                    }
+                }
+            }
+        }
+        let clipped = output.iter().filter(|sample| sample.abs() > 1.).count();
+        self.clipped.fetch_add(clipped, Relaxed);
+        output
+    }
+
+    /// return the number of mixed samples beyond full scale and reset it
+    #[doc(hidden)]
+    pub fn take_clipped(&self) -> usize {
+        self.clipped.swap(0, Relaxed)
+    }
+}
+
+impl AudioBackend for RenderBackend {
+    type Sink = RenderSink;
+
+    fn new_sink(&self) -> RenderSink {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        self.queues.lock().unwrap().push((queue.clone(), stopped.clone()));
+        RenderSink {
+            queue: queue,
+            stopped: stopped,
+            detached: false,
+        }
+    }
+}
+
+impl AudioSink for RenderSink {
+    fn append<S>(&self, source: S) where S: Source + Send + 'static, S::Item: Sample + Send {
+        let source = UniformSourceIterator::<S, f32>::new(source, 1, RENDER_SAMPLES_RATE);
+        self.queue.lock().unwrap().push_back(Box::new(source));
+    }
+
+    fn detach(mut self) {
+        self.detached = true;
+    }
+}
+
+impl Drop for RenderSink {
+    fn drop(&mut self) {
+        if !self.detached {
+            self.stopped.store(true, Relaxed);
+            self.queue.lock().unwrap().clear();
+        }
+    }
+}
+
+/// the audio output of baal
+pub enum Backend {
+    /// play sounds on the rodio endpoint
+    Rodio(rodio::Endpoint),
+    /// discard all sounds
+    Null,
+    /// mix sounds when `baal::render` is called
+    Render(RenderBackend),
+}
+
+impl Backend {
+    /// return the number of mixed samples beyond full scale and reset it
+    ///
+    /// none if the mix isn't observable: the rodio endpoint sums the sinks in
+    /// the audio device and the null backend doesn't mix them
+    #[doc(hidden)]
+    pub fn take_clipped(&self) -> Option<usize> {
+        match *self {
+            Backend::Render(ref backend) => Some(backend.take_clipped()),
+            Backend::Rodio(_) | Backend::Null => None,
+        }
+    }
+}
+
+/// a sink of the backend
+#[doc(hidden)]
+pub enum Sink {
+    Rodio(rodio::Sink),
+    Null(NullSink),
+    Render(RenderSink),
+}
+
+impl AudioBackend for Backend {
+    type Sink = Sink;
+
+    fn new_sink(&self) -> Sink {
+        match *self {
+            Backend::Rodio(ref endpoint) => Sink::Rodio(endpoint.new_sink()),
+            Backend::Null => Sink::Null(NullBackend.new_sink()),
+            Backend::Render(ref backend) => Sink::Render(backend.new_sink()),
+        }
+    }
+}
+
+impl AudioSink for Sink {
+    fn append<S>(&self, source: S) where S: Source + Send + 'static, S::Item: Sample + Send {
+        match *self {
+            Sink::Rodio(ref sink) => sink.append(source),
+            Sink::Null(ref sink) => sink.append(source),
+            Sink::Render(ref sink) => sink.append(source),
+        }
+    }
+
+    fn detach(self) {
+        match self {
+            Sink::Rodio(sink) => sink.detach(),
+            Sink::Null(sink) => sink.detach(),
+            Sink::Render(sink) => sink.detach(),
+        }
+    }
+}
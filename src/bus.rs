@@ -0,0 +1,1053 @@
+//! this module allow to control buses
+//!
+//! a bus is a set of controls shared by all the sounds routed to it:
+//!
+//! * `music`: the musics
+//! * `effect`: the short and persistent effects
+//! * `voice`: the voice lines
+//! * `ui`: the user interface effects, they are not paused with the effect bus
+//!
+//! the actual volume of a bus is `bus_volume * global_volume`, it can also be
+//! ducked temporarily by events
+//!
+//! buses can follow the time scale set with `baal::set_time_scale`, their sounds
+//! are then played faster or slower, only the effect bus follows it by default
+//!
+//! buses can be muted or soloed to isolate them while tuning the mix, a soloed
+//! bus silences all the buses that are not soloed
+//!
+//! mixer snapshots are sets of bus volumes and low pass filters defined in the
+//! setting, `baal::transition_to_snapshot` blends the current mix toward a snapshot
+//! during `baal::update` calls
+//!
+//! `baal::enter_pause_preset` is a built-in transition for pause menus: the
+//! gameplay buses are attenuated and low-passed while the ui bus is untouched,
+//! `baal::exit_pause_preset` removes it, the preset is applied on top of the mix
+//! so that the volumes and snapshots changed meanwhile are kept
+//!
+//! user filters are inserted in a bus with `bus::insert`, each sound of the bus
+//! is processed by its own instance of the filter as sounds are mixed by the
+//! audio device, `bus::Equalizer` is a built-in three bands equalizer, cutting
+//! the low and high bands gives a radio or telephone voice
+//!
+//! return buses are reverbs shared by the effects sent to them, each effect sets
+//! its send levels in its setting, returns are played through the effect bus
+
+use rodio::Sample;
+use rodio::Source;
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::InitError;
+use super::RAW_STATE;
+use super::Setting;
+use super::source;
+use super::event;
+use super::event::AudioEvent;
+use super::source::{AmplifyCtrl, CatchPanic, Insert, LowPassCtrl, Meter, MeterLevels, PlayPauseCtrl, SpeedCtrl};
+
+/// the name of the music bus
+pub const MUSIC: &'static str = "music";
+
+/// the name of the effect bus
+pub const EFFECT: &'static str = "effect";
+
+/// the name of the voice bus
+pub const VOICE: &'static str = "voice";
+
+/// the name of the user interface bus
+pub const UI: &'static str = "ui";
+
+/// the cutoff frequency at which low pass filters are disabled
+pub const NO_LOW_PASS: f32 = 24_000.;
+
+/// the buses affected by the pause preset
+pub const PAUSE_BUSES: [&'static str; 3] = [MUSIC, EFFECT, VOICE];
+
+/// the factor applied to the volume of the buses affected by the pause preset
+pub const PAUSE_ATTENUATION: f32 = 0.5;
+
+/// the cutoff frequency in Hz of the low pass filter of the pause preset
+pub const PAUSE_LOW_PASS: f32 = 800.;
+
+/// the duration in milliseconds of the fades of the pause preset
+pub const PAUSE_FADE_MILLIS: u64 = 300;
+
+/// a filter processing blocks of samples, inserted in a bus with `bus::insert`
+pub trait AudioFilter: Send + Sync {
+    /// process the interleaved samples in place, the block is made of whole frames
+    /// except at the end of the sound
+    fn process(&mut self, samples: &mut [f32], channels: u16, samples_rate: u32);
+
+    /// return a new filter with the same parameters, each sound is processed by
+    /// its own instance
+    fn instance(&self) -> Box<AudioFilter>;
+}
+
+/// the crossover frequency in Hz between the low and mid bands of the equalizer
+pub const EQ_LOW_FREQUENCY: f32 = 250.;
+
+/// the crossover frequency in Hz between the mid and high bands of the equalizer
+pub const EQ_HIGH_FREQUENCY: f32 = 4_000.;
+
+/// a three bands equalizer to insert in a bus
+///
+/// clones share their gains so they can be changed after the insert:
+///
+/// ```ignore
+/// let eq = baal::bus::Equalizer::new();
+/// baal::bus::insert(baal::bus::EFFECT, Box::new(eq.clone()));
+/// eq.set_low_db(-3.);
+/// ```
+#[derive(Clone,Debug)]
+pub struct Equalizer {
+    /// the gains of the low, mid and high bands * 10_000
+    gains: Arc<[AtomicUsize; 3]>,
+    /// the low passes at the crossover frequencies by channel
+    low: Vec<f32>,
+    high: Vec<f32>,
+    channel: usize,
+}
+
+impl Equalizer {
+    /// create an equalizer with all the bands at 0 dB
+    pub fn new() -> Equalizer {
+        Equalizer {
+            gains: Arc::new([AtomicUsize::new(10_000), AtomicUsize::new(10_000), AtomicUsize::new(10_000)]),
+            low: vec!(),
+            high: vec!(),
+            channel: 0,
+        }
+    }
+
+    /// set the gain in decibels of the frequencies under `EQ_LOW_FREQUENCY`
+    pub fn set_low_db(&self, db: f32) {
+        self.set_gain_db(0, db);
+    }
+
+    /// set the gain in decibels of the frequencies between the crossovers
+    pub fn set_mid_db(&self, db: f32) {
+        self.set_gain_db(1, db);
+    }
+
+    /// set the gain in decibels of the frequencies over `EQ_HIGH_FREQUENCY`
+    pub fn set_high_db(&self, db: f32) {
+        self.set_gain_db(2, db);
+    }
+
+    /// return the gains in decibels of the low, mid and high bands
+    pub fn gains_db(&self) -> [f32; 3] {
+        let db = |band: usize| gain_to_db(self.gains[band].load(Relaxed) as f32 / 10_000.);
+        [db(0), db(1), db(2)]
+    }
+
+    fn set_gain_db(&self, band: usize, db: f32) {
+        self.gains[band].store((db_to_gain(db) * 10_000.) as usize, Relaxed);
+    }
+}
+
+impl Default for Equalizer {
+    fn default() -> Equalizer {
+        Equalizer::new()
+    }
+}
+
+impl AudioFilter for Equalizer {
+    fn process(&mut self, samples: &mut [f32], channels: u16, samples_rate: u32) {
+        let channels = channels as usize;
+        if self.low.len() != channels {
+            self.low = vec!(0.; channels);
+            self.high = vec!(0.; channels);
+            self.channel = 0;
+        }
+        let factor = |frequency: f32| 1. - (-2. * ::std::f32::consts::PI * frequency / samples_rate as f32).exp();
+        let low_factor = factor(EQ_LOW_FREQUENCY);
+        let high_factor = factor(EQ_HIGH_FREQUENCY);
+        let gain = |band: usize| self.gains[band].load(Relaxed) as f32 / 10_000.;
+        let (low_gain, mid_gain, high_gain) = (gain(0), gain(1), gain(2));
+
+        for sample in samples {
+            let c = self.channel;
+            self.low[c] += (*sample - self.low[c]) * low_factor;
+            self.high[c] += (*sample - self.high[c]) * high_factor;
+            let low = self.low[c];
+            let high = *sample - self.high[c];
+            let mid = *sample - low - high;
+            *sample = low * low_gain + mid * mid_gain + high * high_gain;
+            self.channel = (c + 1) % channels;
+        }
+    }
+
+    fn instance(&self) -> Box<AudioFilter> {
+        Box::new(Equalizer {
+            gains: self.gains.clone(),
+            low: vec!(),
+            high: vec!(),
+            channel: 0,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct Bus {
+    curve: VolumeCurve,
+    max_gain: f32,
+    /// the master pre-gain, see `Setting::master_pre_gain_db`
+    pre_gain: f32,
+    volume: f32,
+    /// the factors of the ducks of the events in progress, overlapping ducks
+    /// are multiplied
+    ducks: Vec<f32>,
+    /// the factor applied while a voice line is played, see `voice`
+    voice_duck: f32,
+    /// the compensation of the number of sounds played, see `Setting::auto_gain_db`
+    auto_gain: f32,
+    low_pass: f32,
+    /// the progress of the pause preset, from 0 not applied to 1 fully applied
+    pause_preset: f32,
+    final_volume: Arc<AtomicUsize>,
+    final_low_pass: Arc<AtomicUsize>,
+    pause: Arc<AtomicBool>,
+    /// paused by the application independently of the pause of the bus, see `focus`
+    suspend: Arc<AtomicBool>,
+    speed: Arc<AtomicUsize>,
+    levels: Arc<MeterLevels>,
+    inserts: Vec<Box<AudioFilter>>,
+    /// the panics of the sounds of the bus
+    errors: Arc<Mutex<Vec<String>>>,
+    time_scaled: bool,
+    muted: bool,
+    soloed: bool,
+    silenced: bool,
+    focus_muted: bool,
+}
+
+impl Bus {
+    #[doc(hidden)]
+    pub fn new(volume: f32, setting: &Setting) -> Bus {
+        let bus = Bus {
+            curve: setting.volume_curve,
+            max_gain: setting.max_gain,
+            pre_gain: db_to_gain(setting.master_pre_gain_db),
+            volume: sanitize_volume(volume),
+            ducks: vec!(),
+            voice_duck: 1.,
+            auto_gain: 1.,
+            low_pass: NO_LOW_PASS,
+            pause_preset: 0.,
+            final_volume: Arc::new(AtomicUsize::new(0)),
+            final_low_pass: Arc::new(AtomicUsize::new(0)),
+            pause: Arc::new(AtomicBool::new(false)),
+            suspend: Arc::new(AtomicBool::new(false)),
+            speed: Arc::new(AtomicUsize::new(10_000)),
+            levels: Arc::new(MeterLevels::default()),
+            inserts: vec!(),
+            errors: Arc::new(Mutex::new(vec!())),
+            time_scaled: false,
+            muted: false,
+            soloed: false,
+            silenced: false,
+            focus_muted: false,
+        };
+        bus.update(sanitize_volume(setting.global_volume));
+        bus
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn update(&self, global_volume: f32) {
+        let audible = if self.muted || self.silenced || self.focus_muted { 0. } else { 1. };
+        let duck = self.ducks.iter().fold(self.voice_duck, |duck, factor| duck * factor);
+        let pause = 1. + (PAUSE_ATTENUATION - 1.) * self.pause_preset;
+        let volume = self.curve.gain(self.volume) * self.curve.gain(global_volume) * duck * pause * self.auto_gain * audible;
+        self.final_volume.store((volume.min(self.max_gain) * self.pre_gain * 10_000f32) as usize, Relaxed);
+        let pause_low_pass = NO_LOW_PASS + (PAUSE_LOW_PASS - NO_LOW_PASS) * self.pause_preset;
+        self.final_low_pass.store(self.low_pass.min(pause_low_pass) as usize, Relaxed);
+    }
+
+    /// route the source through the bus
+    #[doc(hidden)]
+    #[inline]
+    pub fn apply<I>(&self, input: I) -> Meter<PlayPauseCtrl<PlayPauseCtrl<AmplifyCtrl<CatchPanic<Insert<LowPassCtrl<SpeedCtrl<I>>>>>>>>
+        where I: Source, I::Item: Sample
+    {
+        let source = source::speed_ctrl(input, self.speed.clone());
+        let source = source::low_pass_ctrl(source, self.final_low_pass.clone());
+        let source = source::insert(source, self.inserts.iter().map(|filter| filter.instance()).collect());
+        // a panicking sound or insert ends without killing the audio thread
+        let source = source::catch_panic(source, self.errors.clone());
+        let source = source::amplify_ctrl(source, self.final_volume.clone());
+        let source = source::play_pause_ctrl(source, self.pause.clone());
+        let source = source::play_pause_ctrl(source, self.suspend.clone());
+        source::meter(source, self.levels.clone())
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_volume(&mut self, volume: f32, global_volume: f32) {
+        self.volume = sanitize_volume(volume);
+        self.update(global_volume);
+    }
+
+    /// start a duck of the bus, the factor is applied to its volume until
+    /// `end_duck` is called with the same factor
+    #[doc(hidden)]
+    #[inline]
+    pub fn start_duck(&mut self, factor: f32, global_volume: f32) {
+        self.ducks.push(factor);
+        self.update(global_volume);
+    }
+
+    /// end a duck started by `start_duck`, the other ducks are kept
+    #[doc(hidden)]
+    #[inline]
+    pub fn end_duck(&mut self, factor: f32, global_volume: f32) {
+        if let Some(index) = self.ducks.iter().position(|&duck| duck == factor) {
+            self.ducks.swap_remove(index);
+        }
+        self.update(global_volume);
+    }
+
+    /// set the factor applied to the volume of the bus while a voice line is
+    /// played, it is multiplied by the ducks of the events
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_voice_duck(&mut self, duck: f32, global_volume: f32) {
+        self.voice_duck = duck;
+        self.update(global_volume);
+    }
+
+    /// set the factor compensating the number of sounds played on the bus
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_auto_gain(&mut self, auto_gain: f32, global_volume: f32) {
+        if self.auto_gain != auto_gain {
+            self.auto_gain = auto_gain;
+            self.update(global_volume);
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.pause.load(Relaxed)
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_paused(&self, pause: bool) {
+        self.pause.store(pause, Relaxed);
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_suspended(&self, suspend: bool) {
+        self.suspend.store(suspend, Relaxed);
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_focus_muted(&mut self, muted: bool, global_volume: f32) {
+        self.focus_muted = muted;
+        self.update(global_volume);
+    }
+
+    /// set whether the bus follows the time scale
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_time_scaled(&mut self, time_scaled: bool, time_scale: f32) {
+        self.time_scaled = time_scaled;
+        self.set_time_scale(time_scale);
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_time_scale(&self, time_scale: f32) {
+        let speed = if self.time_scaled { time_scale } else { 1. };
+        self.speed.store((speed * 10_000.) as usize, Relaxed);
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set_low_pass(&mut self, cutoff: Option<f32>, global_volume: f32) {
+        self.low_pass = cutoff.unwrap_or(NO_LOW_PASS).min(NO_LOW_PASS);
+        self.update(global_volume);
+    }
+
+    fn mix(&self) -> BusMix {
+        BusMix {
+            volume: self.volume,
+            low_pass: if self.low_pass >= NO_LOW_PASS { None } else { Some(self.low_pass) },
+        }
+    }
+}
+
+/// how volumes in [0,1], like the values of sliders, are mapped to gains
+///
+/// it applies to the global volume and the volumes of buses
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum VolumeCurve {
+    /// the gain is the volume
+    Linear,
+    /// the gain is the volume to the power
+    Power(f32),
+    /// the volume is mapped linearly to decibels, 1 is 0dB and 0 is minus the
+    /// dynamic range in dB, below the gain is 0
+    Decibel(f32),
+}
+
+impl Default for VolumeCurve {
+    fn default() -> VolumeCurve {
+        VolumeCurve::Linear
+    }
+}
+
+impl VolumeCurve {
+    /// return the gain of the volume
+    pub fn gain(&self, volume: f32) -> f32 {
+        match *self {
+            VolumeCurve::Linear => volume,
+            VolumeCurve::Power(n) => volume.max(0.).powf(n),
+            VolumeCurve::Decibel(range) => if volume <= 0. {
+                0.
+            } else {
+                db_to_gain((volume - 1.) * range)
+            },
+        }
+    }
+
+    /// return the volume of the gain
+    pub fn volume(&self, gain: f32) -> f32 {
+        match *self {
+            VolumeCurve::Linear => gain,
+            VolumeCurve::Power(n) => gain.max(0.).powf(1. / n),
+            VolumeCurve::Decibel(range) => if gain <= 0. {
+                0.
+            } else {
+                (1. + gain_to_db(gain) / range).max(0.)
+            },
+        }
+    }
+}
+
+/// the greatest volume accepted by setters, greater volumes are clamped
+pub const MAX_VOLUME: f32 = 10.;
+
+/// return the volume clamped in [0,MAX_VOLUME], NaN is 0
+#[doc(hidden)]
+#[inline]
+pub fn sanitize_volume(volume: f32) -> f32 {
+    if volume.is_nan() || volume < 0. {
+        0.
+    } else {
+        volume.min(MAX_VOLUME)
+    }
+}
+
+/// return the gain compensating the number of sounds played at once, it is
+/// attenuated by `db_per_doubling` each time the number doubles
+pub fn auto_gain(db_per_doubling: f32, sounds: usize) -> f32 {
+    if sounds <= 1 || db_per_doubling <= 0. {
+        1.
+    } else {
+        db_to_gain(-db_per_doubling * (sounds as f32).log2())
+    }
+}
+
+/// convert decibels to a gain
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+/// convert a gain to decibels
+pub fn gain_to_db(gain: f32) -> f32 {
+    20. * gain.log10()
+}
+
+/// the names of all the buses
+#[doc(hidden)]
+pub const BUSES: [&'static str; 4] = [MUSIC, EFFECT, VOICE, UI];
+
+#[doc(hidden)]
+#[inline]
+pub fn get<'a>(name: &str, state: &'a super::State) -> Option<&'a Bus> {
+    match name {
+        MUSIC => Some(&state.music.bus),
+        EFFECT => Some(&state.effect.bus),
+        VOICE => Some(&state.voice.bus),
+        UI => Some(&state.effect.ui_bus),
+        _ => None,
+    }
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn get_mut<'a>(name: &str, state: &'a mut super::State) -> Option<&'a mut Bus> {
+    match name {
+        MUSIC => Some(&mut state.music.bus),
+        EFFECT => Some(&mut state.effect.bus),
+        VOICE => Some(&mut state.voice.bus),
+        UI => Some(&mut state.effect.ui_bus),
+        _ => None,
+    }
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn update_volume(state: &mut super::State) {
+    let global_volume = state.global_volume;
+    for name in &BUSES {
+        get(name, state).unwrap().update(global_volume);
+    }
+}
+
+#[doc(hidden)]
+pub fn update_pre_gain(state: &mut super::State) {
+    let pre_gain = db_to_gain(state.master_pre_gain_db);
+    for name in &BUSES {
+        get_mut(name, state).unwrap().pre_gain = pre_gain;
+    }
+    update_volume(state);
+}
+
+/// insert the filter at the end of the filters of the bus, before its volume
+///
+/// it applies to the sounds played after this call, panic if the bus doesn't exist
+pub fn insert(name: &str, filter: Box<AudioFilter>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    get_mut(name, &mut state).expect("unknown bus").inserts.push(filter);
+}
+
+/// remove the filters inserted in the bus
+///
+/// it applies to the sounds played after this call, panic if the bus doesn't exist
+pub fn clear_inserts(name: &str) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    get_mut(name, &mut state).expect("unknown bus").inserts.clear();
+}
+
+/// take the filters inserted in the buses so they can be kept on reset
+#[doc(hidden)]
+pub fn take_inserts(state: &mut super::State) -> Vec<Vec<Box<AudioFilter>>> {
+    BUSES.iter()
+        .map(|name| ::std::mem::replace(&mut get_mut(name, state).unwrap().inserts, vec!()))
+        .collect()
+}
+
+#[doc(hidden)]
+pub fn restore_inserts(inserts: Vec<Vec<Box<AudioFilter>>>, state: &mut super::State) {
+    for (name, inserts) in BUSES.iter().zip(inserts) {
+        get_mut(name, state).unwrap().inserts = inserts;
+    }
+}
+
+/// set the volume of the bus
+///
+/// panic if the bus doesn't exist
+pub fn set_volume(name: &str, v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    get_mut(name, &mut state).expect("unknown bus").set_volume(v, global_volume);
+}
+
+/// return the volume of the bus
+///
+/// panic if the bus doesn't exist
+pub fn volume(name: &str) -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").volume
+}
+
+/// set the cutoff frequency in Hz of the low pass filter of the bus, none disables it
+///
+/// panic if the bus doesn't exist
+pub fn set_low_pass(name: &str, cutoff: Option<f32>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    get_mut(name, &mut state).expect("unknown bus").set_low_pass(cutoff, global_volume);
+}
+
+/// return the cutoff frequency of the low pass filter of the bus if any
+///
+/// panic if the bus doesn't exist
+pub fn low_pass(name: &str) -> Option<f32> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").mix().low_pass
+}
+
+/// set whether the bus follows the time scale
+///
+/// panic if the bus doesn't exist
+pub fn set_time_scaled(name: &str, time_scaled: bool) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let time_scale = state.time_scale;
+    get_mut(name, &mut state).expect("unknown bus").set_time_scaled(time_scaled, time_scale);
+}
+
+/// return whether the bus follows the time scale
+///
+/// panic if the bus doesn't exist
+pub fn is_time_scaled(name: &str) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").time_scaled
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn update_time_scale(state: &super::State) {
+    for name in &BUSES {
+        get(name, state).unwrap().set_time_scale(state.time_scale);
+    }
+}
+
+/// pause the bus, its sounds resume where they were
+///
+/// panic if the bus doesn't exist
+pub fn pause(name: &str) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").set_paused(true);
+}
+
+/// resume the bus
+///
+/// panic if the bus doesn't exist
+pub fn resume(name: &str) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").set_paused(false);
+}
+
+/// return whether the bus is paused
+///
+/// panic if the bus doesn't exist
+pub fn is_paused(name: &str) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").is_paused()
+}
+
+#[doc(hidden)]
+pub fn pause_all_except_inner(excepted: &[&str], state: &super::State) {
+    if let Some(name) = excepted.iter().find(|name| !exists(name)) {
+        panic!("unknown bus {}", name);
+    }
+    for name in BUSES.iter().filter(|name| !excepted.contains(name)) {
+        get(name, state).unwrap().set_paused(true);
+    }
+}
+
+#[doc(hidden)]
+pub fn resume_all_inner(state: &super::State) {
+    for name in &BUSES {
+        get(name, state).unwrap().set_paused(false);
+    }
+}
+
+/// mute or unmute the bus
+///
+/// panic if the bus doesn't exist
+pub fn mute(name: &str, muted: bool) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    let bus = get_mut(name, &mut state).expect("unknown bus");
+    bus.muted = muted;
+    bus.update(global_volume);
+}
+
+/// return whether the bus is muted
+///
+/// panic if the bus doesn't exist
+pub fn is_muted(name: &str) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").muted
+}
+
+/// solo or unsolo the bus, while any bus is soloed the others are silent
+///
+/// panic if the bus doesn't exist
+pub fn solo(name: &str, soloed: bool) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    get_mut(name, &mut state).expect("unknown bus").soloed = soloed;
+    update_solo(&mut state);
+}
+
+/// return whether the bus is soloed
+///
+/// panic if the bus doesn't exist
+pub fn is_soloed(name: &str) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    get(name, &state).expect("unknown bus").soloed
+}
+
+#[inline]
+fn update_solo(state: &mut super::State) {
+    let global_volume = state.global_volume;
+    let any_soloed = BUSES.iter().any(|name| get(name, state).unwrap().soloed);
+    for name in &BUSES {
+        let bus = get_mut(name, state).unwrap();
+        bus.silenced = any_soloed && !bus.soloed;
+        bus.update(global_volume);
+    }
+}
+
+/// the levels of the output since the last call to `baal::meter`
+#[derive(Clone,Debug,PartialEq)]
+pub struct LevelMeter {
+    /// the sum of the peaks of the buses, it is an upper bound of the peak of the output
+    pub peak: f32,
+    /// the root mean square of the output
+    pub rms: f32,
+    /// the levels of each bus by name
+    pub per_bus: HashMap<String, BusLevel>,
+}
+
+/// the levels of a bus since the last call to `baal::meter`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct BusLevel {
+    /// the greatest absolute value of the samples of the bus
+    pub peak: f32,
+    /// the root mean square of the bus
+    pub rms: f32,
+}
+
+#[doc(hidden)]
+pub fn meter_inner(elapsed: Duration, state: &super::State) -> LevelMeter {
+    let elapsed = as_secs_f32(elapsed);
+    let rms = |energy: f32| if elapsed > 0. { (energy / elapsed).sqrt() } else { 0. };
+
+    let mut meter = LevelMeter {
+        peak: 0.,
+        rms: 0.,
+        per_bus: HashMap::new(),
+    };
+    let mut energy = 0.;
+    for name in &BUSES {
+        let (bus_peak, bus_energy) = get(name, state).unwrap().levels.take();
+        meter.peak += bus_peak;
+        energy += bus_energy;
+        meter.per_bus.insert(name.to_string(), BusLevel {
+            peak: bus_peak,
+            rms: rms(bus_energy),
+        });
+    }
+    meter.rms = rms(energy);
+    meter
+}
+
+/// a set of bus volumes and low pass filters
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MixerSnapshot {
+    /// the global volume, it is not affected if none
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub global_volume: Option<f32>,
+
+    /// the mix of buses by name, buses not listed are not affected
+    pub buses: HashMap<String, BusMix>,
+}
+
+/// the volume and low pass filter of a bus
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct BusMix {
+    /// the volume of the bus
+    pub volume: f32,
+
+    /// the cutoff frequency in Hz of the low pass filter, none disables it
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub low_pass: Option<f32>,
+}
+
+/// a reverb shared by the effects sent to it
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ReturnBus {
+    /// the volume of the return in [0,1]
+    pub volume: f32,
+
+    /// the feedback of the reverb in [0,1), the higher the longer the tail
+    pub decay: f32,
+}
+
+struct Fade {
+    global_volume: Option<(f32, f32)>,
+    buses: Vec<(String, BusMix, BusMix)>,
+    elapsed: Duration,
+    duration: Duration,
+}
+
+#[doc(hidden)]
+pub struct Mixer {
+    snapshots: HashMap<String, MixerSnapshot>,
+    fade: Option<Fade>,
+    /// whether the pause preset is entered, it is faded in or out by `update`
+    pause: bool,
+    /// the clipped samples counted since the start of the second
+    clipped: usize,
+    clip_elapsed: Duration,
+    clipped_per_second: Option<usize>,
+}
+
+impl Mixer {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<Mixer,InitError> {
+        for snapshot in setting.mixer_snapshots.values() {
+            if let Some(name) = snapshot.buses.keys().find(|name| !exists(name)) {
+                return Err(InitError::UnknownBus(name.clone()));
+            }
+        }
+
+        Ok(Mixer {
+            snapshots: setting.mixer_snapshots.clone(),
+            fade: None,
+            pause: false,
+            clipped: 0,
+            clip_elapsed: Duration::new(0, 0),
+            clipped_per_second: None,
+        })
+    }
+}
+
+/// return whether the bus exists
+#[doc(hidden)]
+#[inline]
+pub fn exists(name: &str) -> bool {
+    BUSES.contains(&name)
+}
+
+#[doc(hidden)]
+pub fn transition_to_snapshot_inner(name: &str, duration: Duration, state: &mut super::State) {
+    let fade = {
+        let state = &*state;
+        let snapshot = &state.mixer.snapshots[name];
+        Fade {
+            global_volume: snapshot.global_volume.map(|v| (state.global_volume, v)),
+            buses: snapshot.buses.iter()
+                .map(|(name, mix)| (name.clone(), get(name, state).unwrap().mix(), *mix))
+                .collect(),
+            elapsed: Duration::new(0, 0),
+            duration: duration,
+        }
+    };
+    state.mixer.fade = Some(fade);
+    update(Duration::new(0, 0), state);
+}
+
+#[doc(hidden)]
+pub fn enter_pause_preset_inner(state: &mut super::State) {
+    state.mixer.pause = true;
+}
+
+#[doc(hidden)]
+pub fn exit_pause_preset_inner(state: &mut super::State) {
+    state.mixer.pause = false;
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn is_pause_preset_inner(state: &super::State) -> bool {
+    state.mixer.pause
+}
+
+/// step the fade of the pause preset toward whether it is entered
+fn update_pause_preset(dt: Duration, state: &mut super::State) {
+    let target = if state.mixer.pause { 1. } else { 0. };
+    let step = as_secs_f32(dt) * 1000. / PAUSE_FADE_MILLIS as f32;
+    let global_volume = state.global_volume;
+    for name in &PAUSE_BUSES {
+        let bus = get_mut(name, state).unwrap();
+        if bus.pause_preset != target {
+            bus.pause_preset = if bus.pause_preset < target {
+                (bus.pause_preset + step).min(target)
+            } else {
+                (bus.pause_preset - step).max(target)
+            };
+            bus.update(global_volume);
+        }
+    }
+}
+
+/// step the current transition between snapshots
+#[doc(hidden)]
+pub fn update(dt: Duration, state: &mut super::State) {
+    update_clipping(dt, state);
+    update_errors(state);
+    update_pause_preset(dt, state);
+
+    let mut fade = match state.mixer.fade.take() {
+        Some(fade) => fade,
+        None => return,
+    };
+
+    fade.elapsed += dt;
+    let t = if fade.elapsed >= fade.duration {
+        1.
+    } else {
+        as_secs_f32(fade.elapsed) / as_secs_f32(fade.duration)
+    };
+
+    if let Some((from, to)) = fade.global_volume {
+        state.global_volume = from + (to - from) * t;
+    }
+    for &(ref name, from, to) in &fade.buses {
+        let from_low_pass = from.low_pass.unwrap_or(NO_LOW_PASS);
+        let to_low_pass = to.low_pass.unwrap_or(NO_LOW_PASS);
+
+        let bus = get_mut(name, state).unwrap();
+        bus.volume = from.volume + (to.volume - from.volume) * t;
+        bus.low_pass = from_low_pass + (to_low_pass - from_low_pass) * t;
+    }
+    update_volume(state);
+
+    if t < 1. {
+        state.mixer.fade = Some(fade);
+    }
+}
+
+/// count the samples clipped by the mix of the backend and emit
+/// `AudioEvent::Clipping` each second with clipped samples
+///
+/// the sounds are summed after the buses so a mix can clip while every sound is
+/// below full scale, it is only measured on the mixed output
+fn update_clipping(dt: Duration, state: &mut super::State) {
+    let clipped = match state.backend.take_clipped() {
+        Some(clipped) => clipped,
+        None => return,
+    };
+    state.mixer.clipped += clipped;
+    state.mixer.clip_elapsed += dt;
+
+    if state.mixer.clip_elapsed >= Duration::from_secs(1) {
+        let per_second = (state.mixer.clipped as f32 / as_secs_f32(state.mixer.clip_elapsed)) as usize;
+        state.mixer.clipped = 0;
+        state.mixer.clip_elapsed = Duration::new(0, 0);
+        state.mixer.clipped_per_second = Some(per_second);
+        if per_second > 0 {
+            event::emit(AudioEvent::Clipping(per_second), state);
+        }
+    }
+}
+
+/// emit `AudioEvent::VoiceError` for the sounds that panicked
+fn update_errors(state: &mut super::State) {
+    let mut errors = vec!();
+    for name in &BUSES {
+        if let Ok(mut bus_errors) = get(name, state).unwrap().errors.lock() {
+            errors.extend(bus_errors.drain(..));
+        }
+    }
+    for error in errors {
+        warn!("baal: a sound panicked and has been dropped: {}", error);
+        event::emit(AudioEvent::VoiceError(error), state);
+    }
+}
+
+/// return the number of samples clipped per second during the last second
+#[doc(hidden)]
+pub fn clipped_per_second_inner(state: &super::State) -> Option<usize> {
+    state.mixer.clipped_per_second
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn as_secs_f32(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.
+}
+
+#[test]
+fn test_equalizer() {
+    let eq = Equalizer::new();
+    let mut dc = vec!(1f32; 10_000);
+    eq.instance().process(&mut dc, 1, 44_100);
+    assert!((dc[9_999] - 1.).abs() < 1e-3);
+
+    eq.set_low_db(-6.);
+    assert!((eq.gains_db()[0] + 6.).abs() < 1e-2);
+    let mut dc = vec!(1f32; 10_000);
+    eq.instance().process(&mut dc, 1, 44_100);
+    assert!((dc[9_999] - db_to_gain(-6.)).abs() < 1e-2);
+}
+
+#[test]
+fn test_auto_gain() {
+    assert_eq!(auto_gain(1., 0), 1.);
+    assert_eq!(auto_gain(1., 1), 1.);
+    assert_eq!(auto_gain(0., 30), 1.);
+    assert!((gain_to_db(auto_gain(1., 2)) + 1.).abs() < 1e-4);
+    assert!((gain_to_db(auto_gain(1., 32)) + 5.).abs() < 1e-4);
+}
+
+#[test]
+fn test_volume_curve() {
+    let curves = [VolumeCurve::Linear, VolumeCurve::Power(2.), VolumeCurve::Decibel(60.)];
+    for curve in &curves {
+        assert_eq!(curve.gain(0.), 0.);
+        assert!((curve.gain(1.) - 1.).abs() < 1e-6);
+        for &volume in &[0.25, 0.5, 0.75] {
+            assert!((curve.volume(curve.gain(volume)) - volume).abs() < 1e-4);
+        }
+    }
+    assert!((VolumeCurve::Power(2.).gain(0.5) - 0.25).abs() < 1e-6);
+    assert!((VolumeCurve::Decibel(60.).gain(0.5) - db_to_gain(-30.)).abs() < 1e-6);
+}
+
+#[test]
+fn test_duck() {
+    let setting = Setting {
+        volume_curve: VolumeCurve::Linear,
+        global_volume: 1.,
+        .. Default::default()
+    };
+    let mut bus = Bus::new(1., &setting);
+    let volume = |bus: &Bus| bus.final_volume.load(Relaxed) as f32 / 10_000. / bus.pre_gain;
+
+    bus.start_duck(0.5, 1.);
+    bus.start_duck(0.5, 1.);
+    bus.set_voice_duck(0.5, 1.);
+    assert!((volume(&bus) - 0.125).abs() < 1e-3);
+
+    // the end of a duck keeps the others
+    bus.end_duck(0.5, 1.);
+    assert!((volume(&bus) - 0.25).abs() < 1e-3);
+    bus.set_voice_duck(1., 1.);
+    assert!((volume(&bus) - 0.5).abs() < 1e-3);
+    bus.end_duck(0.5, 1.);
+    assert!((volume(&bus) - 1.).abs() < 1e-3);
+}
+
+#[test]
+fn test_pause_preset() {
+    let setting = Setting {
+        volume_curve: VolumeCurve::Linear,
+        global_volume: 1.,
+        .. Default::default()
+    };
+    let mut bus = Bus::new(0.5, &setting);
+    let volume = |bus: &Bus| bus.final_volume.load(Relaxed) as f32 / 10_000. / bus.pre_gain;
+
+    bus.pause_preset = 1.;
+    bus.update(1.);
+    assert!((volume(&bus) - 0.5 * PAUSE_ATTENUATION).abs() < 1e-3);
+    assert_eq!(bus.final_low_pass.load(Relaxed), PAUSE_LOW_PASS as usize);
+    // the mix is unchanged
+    assert_eq!(bus.mix(), BusMix { volume: 0.5, low_pass: None });
+
+    bus.set_low_pass(Some(400.), 1.);
+    assert_eq!(bus.final_low_pass.load(Relaxed), 400);
+    bus.pause_preset = 0.;
+    bus.set_volume(0.25, 1.);
+    assert!((volume(&bus) - 0.25).abs() < 1e-3);
+    assert_eq!(bus.final_low_pass.load(Relaxed), 400);
+}
@@ -0,0 +1,124 @@
+//! this module allow to load the setting from configuration files
+//!
+//! `Setting::from_yaml` is available with the `config-yaml` feature and
+//! `Setting::from_toml` with the `config-toml` feature
+//!
+//! errors give the line and column of the faulty value, the path of its key and the type
+//! that was expected, for example:
+//! `line 12 column 7: short_effects[2].tags: invalid type: string "shoot.ogg", expected a sequence`
+
+use std::error;
+use std::fmt;
+
+use serde_path_to_error;
+#[cfg(feature = "config-yaml")]
+use serde_yaml;
+#[cfg(feature = "config-toml")]
+use toml;
+
+use super::Setting;
+
+/// error possible when loading a setting from a configuration file
+#[derive(Clone,Debug,PartialEq)]
+pub struct ConfigError {
+    /// the line of the error starting at 1, if known
+    pub line: Option<usize>,
+    /// the column of the error starting at 1, if known
+    pub column: Option<usize>,
+    /// the path of the faulty key, for example `short_effects[2].tags`, if the
+    /// error is not at the root of the document
+    pub path: Option<String>,
+    /// what went wrong, usually the type that was found and the one that was expected
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let path = self.path.as_ref().map(|path| format!("{}: ", path)).unwrap_or_default();
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(fmt, "line {} column {}: {}{}", line, column, path, self.message),
+            (Some(line), None) => write!(fmt, "line {}: {}{}", line, path, self.message),
+            _ => write!(fmt, "{}{}", path, self.message),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn description(&self) -> &str {
+        "invalid configuration"
+    }
+}
+
+impl Setting {
+    /// load the setting from a YAML document
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Setting,ConfigError> {
+        serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(yaml)).map_err(|e| {
+            let path = key_path(e.path());
+            let e = e.into_inner();
+            let location = e.location();
+            ConfigError {
+                line: location.as_ref().map(|location| location.line()),
+                column: location.as_ref().map(|location| location.column()),
+                path: path,
+                message: strip_location(e.to_string()),
+            }
+        })
+    }
+
+    /// load the setting from a TOML document
+    #[cfg(feature = "config-toml")]
+    pub fn from_toml(toml: &str) -> Result<Setting,ConfigError> {
+        let mut deserializer = toml::de::Deserializer::new(toml);
+        let setting = try!(serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            let path = key_path(e.path());
+            toml_error(e.into_inner(), path)
+        }));
+        // the content after the setting is checked like in `toml::from_str`
+        try!(deserializer.end().map_err(|e| toml_error(e, None)));
+        Ok(setting)
+    }
+}
+
+#[cfg(feature = "config-toml")]
+fn toml_error(error: toml::de::Error, path: Option<String>) -> ConfigError {
+    // toml lines and columns start at 0
+    let line_col = error.line_col();
+    ConfigError {
+        line: line_col.map(|(line, _)| line + 1),
+        column: line_col.map(|(_, column)| column + 1),
+        path: path,
+        message: strip_location(error.to_string()),
+    }
+}
+
+/// the path of the key, none at the root of the document
+fn key_path(path: &serde_path_to_error::Path) -> Option<String> {
+    match path.to_string() {
+        ref root if root == "." => None,
+        path => Some(path),
+    }
+}
+
+/// remove the location appended to the message by the parser as it is given separately
+fn strip_location(message: String) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+#[test]
+fn test_strip_location() {
+    assert_eq!(strip_location("invalid type: string \"a\", expected a sequence at line 3 column 5".into()),
+               "invalid type: string \"a\", expected a sequence");
+    assert_eq!(strip_location("missing field `musics`".into()), "missing field `musics`");
+}
+
+#[cfg(feature = "config-yaml")]
+#[test]
+fn test_key_path() {
+    let error = Setting::from_yaml("short_effects:\n  - path: shoot.ogg\n  - path: [hit.ogg]\n").unwrap_err();
+    assert_eq!(error.path, Some("short_effects[1].path".into()));
+    assert!(error.to_string().contains("short_effects[1].path: invalid type"));
+}
@@ -0,0 +1,135 @@
+//! this module allow to control the audio from a developer console
+//!
+//! commands are:
+//!
+//! * `play effect <index>`: play the short effect on the listener
+//! * `play music <index>`
+//! * `stop music`
+//! * `stop effects`: stop all short effects
+//! * `vol <bus|global> <volume>`
+//! * `mute bus <bus>`, `unmute bus <bus>`
+//! * `solo bus <bus>`, `unsolo bus <bus>`
+//! * `status`, `stats`
+//! * `help`
+
+use super::bus;
+use super::effect;
+#[cfg(feature = "music")]
+use super::music;
+
+const HELP: &'static str = "commands: play effect <index>, play music <index>, stop music, stop effects, \
+                            vol <bus|global> <volume>, mute bus <bus>, unmute bus <bus>, \
+                            solo bus <bus>, unsolo bus <bus>, status, stats, help";
+
+#[derive(Clone,Debug,PartialEq)]
+enum Command {
+    PlayEffect(usize),
+    PlayMusic(usize),
+    StopMusic,
+    StopEffects,
+    GlobalVolume(f32),
+    Volume(String, f32),
+    Mute(String, bool),
+    Solo(String, bool),
+    Status,
+    Stats,
+    Help,
+}
+
+/// execute the command and return its output, errors are returned as output too
+pub fn execute(cmd: &str) -> String {
+    let command = match parse(cmd) {
+        Ok(command) => command,
+        Err(error) => return error,
+    };
+
+    match command {
+        Command::PlayEffect(index) => {
+            if index >= effect::short::count() {
+                return format!("no effect {}", index);
+            }
+            effect::short::play_on_listener(effect::ShortId(index));
+            format!("play effect {}", index)
+        },
+        #[cfg(feature = "music")]
+        Command::PlayMusic(index) => {
+            if index >= music::count() {
+                return format!("no music {}", index);
+            }
+            music::play(music::MusicId(index));
+            format!("play music {}", index)
+        },
+        #[cfg(feature = "music")]
+        Command::StopMusic => {
+            music::stop();
+            "music stopped".into()
+        },
+        #[cfg(not(feature = "music"))]
+        Command::PlayMusic(_) | Command::StopMusic => "musics are disabled".into(),
+        Command::StopEffects => {
+            effect::short::stop_all();
+            "effects stopped".into()
+        },
+        Command::GlobalVolume(volume) => {
+            super::set_global_volume(volume);
+            format!("global volume {}", super::global_volume())
+        },
+        Command::Volume(name, volume) => {
+            bus::set_volume(&name, volume);
+            format!("{} volume {}", name, bus::volume(&name))
+        },
+        Command::Mute(name, muted) => {
+            bus::mute(&name, muted);
+            format!("{} {}", name, if muted { "muted" } else { "unmuted" })
+        },
+        Command::Solo(name, soloed) => {
+            bus::solo(&name, soloed);
+            format!("{} {}", name, if soloed { "soloed" } else { "unsoloed" })
+        },
+        Command::Status => format!("{:?}", super::status()),
+        Command::Stats => format!("{:?}", super::stats()),
+        Command::Help => HELP.into(),
+    }
+}
+
+fn parse(cmd: &str) -> Result<Command, String> {
+    let words = cmd.split_whitespace().collect::<Vec<_>>();
+    let index = |word: &str| word.parse::<usize>().map_err(|_| format!("invalid index {}", word));
+    let volume = |word: &str| word.parse::<f32>().map_err(|_| format!("invalid volume {}", word));
+    let bus_name = |word: &str| if bus::exists(word) {
+        Ok(word.to_string())
+    } else {
+        Err(format!("unknown bus {}", word))
+    };
+
+    let word = |i: usize| words.get(i).cloned();
+    match (word(0), word(1), word(2), words.len()) {
+        (Some("play"), Some("effect"), Some(i), 3) => index(i).map(Command::PlayEffect),
+        (Some("play"), Some("music"), Some(i), 3) => index(i).map(Command::PlayMusic),
+        (Some("stop"), Some("music"), None, 2) => Ok(Command::StopMusic),
+        (Some("stop"), Some("effects"), None, 2) => Ok(Command::StopEffects),
+        (Some("vol"), Some("global"), Some(v), 3) => volume(v).map(Command::GlobalVolume),
+        (Some("vol"), Some(name), Some(v), 3) => Ok(Command::Volume(try!(bus_name(name)), try!(volume(v)))),
+        (Some("mute"), Some("bus"), Some(name), 3) => bus_name(name).map(|name| Command::Mute(name, true)),
+        (Some("unmute"), Some("bus"), Some(name), 3) => bus_name(name).map(|name| Command::Mute(name, false)),
+        (Some("solo"), Some("bus"), Some(name), 3) => bus_name(name).map(|name| Command::Solo(name, true)),
+        (Some("unsolo"), Some("bus"), Some(name), 3) => bus_name(name).map(|name| Command::Solo(name, false)),
+        (Some("status"), None, None, 1) => Ok(Command::Status),
+        (Some("stats"), None, None, 1) => Ok(Command::Stats),
+        (Some("help"), None, None, 1) => Ok(Command::Help),
+        _ => Err(format!("unknown command: {}, try help", cmd.trim())),
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse("play effect 3"), Ok(Command::PlayEffect(3)));
+    assert_eq!(parse("  play   music 0 "), Ok(Command::PlayMusic(0)));
+    assert_eq!(parse("vol music 0.2"), Ok(Command::Volume("music".into(), 0.2)));
+    assert_eq!(parse("vol global 1"), Ok(Command::GlobalVolume(1.)));
+    assert_eq!(parse("mute bus effect"), Ok(Command::Mute("effect".into(), true)));
+    assert_eq!(parse("unsolo bus voice"), Ok(Command::Solo("voice".into(), false)));
+    assert!(parse("mute bus sfx").is_err());
+    assert!(parse("play effect x").is_err());
+    assert!(parse("dance").is_err());
+}
@@ -0,0 +1,76 @@
+//! this module allow to inspect the spatial state of baal, it is meant to be
+//! drawn by the debug renderer of the game to tune distance models
+//!
+//! baal doesn't pan sounds so only positions and gains are reported
+
+use super::RAW_STATE;
+use super::effect;
+use super::effect::{PersistentId, ShortId};
+use super::spatial;
+use super::spatial::RoomId;
+
+/// the spatial state of baal, see `spatial_state`
+#[derive(Clone,Debug,PartialEq)]
+pub struct SpatialState {
+    /// the position of the listener
+    pub listener: [f32;3],
+    /// the room of the listener, see `baal::spatial`
+    pub listener_room: Option<RoomId>,
+    /// the emitters alive
+    pub emitters: Vec<EmitterInfo>,
+    /// the short effects played or waiting to be played
+    pub short_effects: Vec<ShortInfo>,
+    /// the persistent effects with at least one position
+    pub persistent_effects: Vec<PersistentInfo>,
+}
+
+/// an emitter, see `effect::Emitter`
+#[derive(Clone,Debug,PartialEq)]
+pub struct EmitterInfo {
+    /// the position of the emitter
+    pub position: [f32;3],
+    /// the velocity of the emitter
+    pub velocity: [f32;3],
+    /// the room of the emitter
+    pub room: Option<RoomId>,
+    /// the gain of the distance model and the occlusion at the position of the emitter
+    pub gain: f32,
+    /// the persistent effects attached to the emitter
+    pub persistent: Vec<PersistentId>,
+}
+
+/// a short effect
+#[derive(Clone,Debug,PartialEq)]
+pub struct ShortInfo {
+    /// the effect, none for raw samples
+    pub effect: Option<ShortId>,
+    /// the position the effect is played at
+    pub position: [f32;3],
+    /// the gain computed when the effect started
+    pub gain: f32,
+}
+
+/// a persistent effect
+#[derive(Clone,Debug,PartialEq)]
+pub struct PersistentInfo {
+    /// the effect
+    pub effect: PersistentId,
+    /// the positions of the effect, without the emitters it is attached to
+    pub positions: Vec<[f32;3]>,
+    /// the gain computed at the last update of its volume
+    pub gain: f32,
+}
+
+/// return the spatial state of baal
+pub fn spatial_state() -> SpatialState {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let mut spatial_state = SpatialState {
+        listener: [0.;3],
+        listener_room: spatial::listener_room_inner(&state),
+        emitters: vec!(),
+        short_effects: vec!(),
+        persistent_effects: vec!(),
+    };
+    effect::spatial_state_inner(&state, &mut spatial_state);
+    spatial_state
+}
@@ -0,0 +1,100 @@
+//! this module provide components and a system to use baal with [specs](https://crates.io/crates/specs)
+//!
+//! it is only available with the `specs` feature
+//!
+//! the `BaalSystem` must be run once per frame after entities have moved, then
+//! `baal::update` can be called
+
+use specs::Component;
+use specs::HashMapStorage;
+use specs::Join;
+use specs::ReadStorage;
+use specs::System;
+use specs::VecStorage;
+use specs::WriteStorage;
+
+use effect;
+use effect::{PersistentId, ShortId};
+
+/// the position of the listener
+///
+/// only one entity is supposed to have this component
+#[derive(Clone,Debug,PartialEq)]
+pub struct Listener {
+    /// the position of the entity
+    pub position: [f32;3],
+}
+
+impl Component for Listener {
+    type Storage = HashMapStorage<Self>;
+}
+
+/// an entity that emits sound effects
+#[derive(Clone,Debug,PartialEq)]
+pub struct AudioEmitter {
+    /// the position of the entity
+    pub position: [f32;3],
+
+    /// the persistent effects emitted at the position of the entity
+    pub persistent_effects: Vec<PersistentId>,
+
+    /// the short effects to play at the next run of the system
+    pub queued_effects: Vec<ShortId>,
+}
+
+impl AudioEmitter {
+    /// create an emitter with no effects
+    pub fn new(position: [f32;3]) -> AudioEmitter {
+        AudioEmitter {
+            position: position,
+            persistent_effects: vec!(),
+            queued_effects: vec!(),
+        }
+    }
+
+    /// queue the short effect to be played at the next run of the system
+    pub fn play(&mut self, effect: ShortId) {
+        self.queued_effects.push(effect);
+    }
+}
+
+impl Component for AudioEmitter {
+    type Storage = VecStorage<Self>;
+}
+
+/// synchronize listener and emitters with baal
+///
+/// * set the position of the listener
+/// * set the positions of all persistent effects and update their volume
+/// * play the queued short effects
+pub struct BaalSystem;
+
+impl<'a> System<'a> for BaalSystem {
+    type SystemData = (ReadStorage<'a, Listener>, WriteStorage<'a, AudioEmitter>);
+
+    fn run(&mut self, (listeners, mut emitters): Self::SystemData) {
+        if let Some(listener) = (&listeners).join().next() {
+            effect::set_listener(listener.position);
+        }
+
+        #[cfg(feature = "persistent")]
+        {
+            let mut positions = vec!();
+            for emitter in (&emitters).join() {
+                for &effect in &emitter.persistent_effects {
+                    positions.push((effect, vec!(emitter.position)));
+                }
+            }
+            effect::persistent::set_positions_for_all(positions);
+            effect::persistent::update_volume_for_all();
+        }
+
+        let mut shorts = vec!();
+        for emitter in (&mut emitters).join() {
+            for effect in emitter.queued_effects.drain(..) {
+                shorts.push((effect, emitter.position));
+            }
+        }
+        effect::short::play_many(&shorts);
+    }
+}
@@ -0,0 +1,68 @@
+//! this module allow to define blend containers
+//!
+//! a blend container is a set of persistent effects, called layers, crossfaded
+//! according to a parameter set with `effect::set_parameter`
+//!
+//! each layer is active in a range of the parameter with a fade in and a fade out:
+//!
+//! ```text
+//!   1 |      ________
+//!     |     /        \
+//!   0 |____/          \____
+//!         r0 r1    r2 r3
+//! ```
+//!
+//! the gain of a layer is multiplied to the volume of the persistent effect
+//! computed from its positions
+
+/// a set of layers crossfaded according to a parameter
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct BlendContainer {
+    /// the name of the parameter
+    pub parameter: String,
+
+    /// the layers of the container
+    pub layers: Vec<BlendLayer>,
+}
+
+/// a persistent effect in a blend container
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct BlendLayer {
+    /// the index of the persistent effect
+    pub effect: usize,
+
+    /// the range of the parameter: `[fade in start, fade in end, fade out start, fade out end]`
+    pub range: [f32;4],
+}
+
+impl BlendLayer {
+    /// the gain of the layer for the value of the parameter
+    pub fn gain(&self, value: f32) -> f32 {
+        let r = self.range;
+        if value <= r[0] || value >= r[3] {
+            0.
+        } else if value < r[1] {
+            (value - r[0]) / (r[1] - r[0])
+        } else if value <= r[2] {
+            1.
+        } else {
+            (r[3] - value) / (r[3] - r[2])
+        }
+    }
+}
+
+#[test]
+fn test_gain() {
+    let layer = BlendLayer {
+        effect: 0,
+        range: [0.2, 0.4, 0.6, 1.0],
+    };
+    assert_eq!(layer.gain(0.), 0.);
+    assert_eq!(layer.gain(0.2), 0.);
+    assert!((layer.gain(0.3) - 0.5).abs() < 0.00001);
+    assert_eq!(layer.gain(0.5), 1.);
+    assert!((layer.gain(0.8) - 0.5).abs() < 0.00001);
+    assert_eq!(layer.gain(1.0), 0.);
+}
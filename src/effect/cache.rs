@@ -0,0 +1,130 @@
+//! the decoded effects loaded in memory, they are kept across `baal::reset` so
+//! that a file is decoded again only if it has been modified
+
+use rodio::Source;
+use rodio::decoder::Decoder;
+use rodio::source::Buffered;
+
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::super::InitError;
+use super::super::Setting;
+use super::super::{AssetMemory, MemoryReport};
+
+struct Entry {
+    modified: SystemTime,
+    len: u64,
+    source: Buffered<Decoder<File>>,
+    duration: Option<Duration>,
+    samples: usize,
+    used: bool,
+}
+
+/// the decoded sources by path, an entry is valid while the modification time
+/// and the length of its file are unchanged
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<PathBuf, Entry>,
+}
+
+impl Cache {
+    /// return the decoded source of the file, its duration and its number of samples
+    pub fn load(&mut self, setting: &Setting, source: &PathBuf) -> Result<(Buffered<Decoder<File>>, Option<Duration>, usize),InitError> {
+        let path = setting.effect_dir.join(source);
+        // the file is decoded at each load if its modification time is unknown
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified().map(|modified| (modified, metadata.len())))
+            .ok();
+
+        if let Some(entry) = self.entries.get_mut(&path) {
+            if Some((entry.modified, entry.len)) == modified {
+                entry.used = true;
+                return Ok((entry.source.clone(), entry.duration, entry.samples));
+            }
+        }
+
+        let decoder = try!(super::decode(setting, source));
+        let decoded = decoder.buffered();
+        // decode the whole source upfront, clones share the decoded frames
+        // so that the first play doesn't pay the decoding
+        let samples = decoded.clone().count();
+        let duration = super::duration(&decoded, samples);
+
+        match modified {
+            Some((modified, len)) => {
+                self.entries.insert(path, Entry {
+                    modified: modified,
+                    len: len,
+                    source: decoded.clone(),
+                    duration: duration,
+                    samples: samples,
+                    used: true,
+                });
+            },
+            None => {
+                self.entries.remove(&path);
+            },
+        }
+        Ok((decoded, duration, samples))
+    }
+
+    /// remove the entry of the file so that its next load decodes it
+    ///
+    /// the modification time may not change when the file is written twice in
+    /// a short time, it is used by explicit reloads
+    pub fn invalidate(&mut self, setting: &Setting, source: &PathBuf) {
+        self.entries.remove(&setting.effect_dir.join(source));
+    }
+
+    /// add the memory used by each file to the report
+    pub fn memory_report(&self, report: &mut MemoryReport) {
+        for (path, entry) in &self.entries {
+            report.assets.push(AssetMemory {
+                path: path.clone(),
+                bytes: entry.samples * ::std::mem::size_of::<i16>(),
+            });
+        }
+    }
+
+    /// drop the entries not loaded since the last call
+    pub fn clear_unused(&mut self) {
+        let unused = self.entries.iter()
+            .filter(|&(_, entry)| !entry.used)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        for path in unused {
+            self.entries.remove(&path);
+        }
+        for entry in self.entries.values_mut() {
+            entry.used = false;
+        }
+    }
+}
+
+#[test]
+fn test_cache() {
+    let setting = Setting {
+        effect_dir: "assets/effects".into(),
+        .. Default::default()
+    };
+    let mut cache = Cache::default();
+    let (_, _, samples) = cache.load(&setting, &"hit.ogg".into()).unwrap();
+    let (_, _, cached_samples) = cache.load(&setting, &"hit.ogg".into()).unwrap();
+    assert_eq!(samples, cached_samples);
+    assert_eq!(cache.entries.len(), 1);
+    assert!(cache.load(&setting, &"missing.ogg".into()).is_err());
+
+    cache.invalidate(&setting, &"hit.ogg".into());
+    assert_eq!(cache.entries.len(), 0);
+    cache.load(&setting, &"hit.ogg".into()).unwrap();
+
+    // the entry is kept while it is loaded between two calls
+    cache.clear_unused();
+    assert_eq!(cache.entries.len(), 1);
+    cache.clear_unused();
+    assert_eq!(cache.entries.len(), 0);
+}
@@ -0,0 +1,198 @@
+//! this module allow to handle a source of sound effects as an object
+//!
+//! the emitter keeps track of its position and velocity, the short effects it
+//! plays are affected by doppler effect and the persistent effects attached to it
+//! follow it until it is dropped
+//!
+//! the emitters are removed by `baal::close`, the calls on an emitter removed
+//! do nothing
+
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
+use std::sync::atomic::Ordering::Relaxed;
+
+use super::{PersistentId, ShortId};
+use super::super::RAW_STATE;
+use super::super::spatial::RoomId;
+
+/// speed of sound in distance unit per second used for doppler effect
+pub const SPEED_OF_SOUND: f32 = 343.;
+
+/// the generation of the next emitter, it is kept across `baal::close` so that
+/// an emitter never matches the slot of an emitter created after it
+static NEXT_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+#[doc(hidden)]
+pub struct EmitterState {
+    pub position: [f32;3],
+    pub velocity: [f32;3],
+    pub persistent: Vec<usize>,
+    pub room: Option<RoomId>,
+    generation: usize,
+}
+
+/// a source of sound effects, it is removed from baal when dropped
+///
+/// persistent effects attached to it are taken into account by
+/// `persistent::update_volume` like other positions
+#[derive(Debug)]
+pub struct Emitter {
+    id: usize,
+    generation: usize,
+}
+
+impl Emitter {
+    /// create a new emitter at position with no velocity
+    pub fn new(position: [f32;3]) -> Emitter {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        let emitter = EmitterState {
+            position: position,
+            velocity: [0.;3],
+            persistent: vec!(),
+            room: None,
+            generation: NEXT_GENERATION.fetch_add(1, Relaxed),
+        };
+        let generation = emitter.generation;
+
+        let emitters = &mut state.effect.emitters;
+        let id = match emitters.iter().position(|e| e.is_none()) {
+            Some(id) => {
+                emitters[id] = Some(emitter);
+                id
+            }
+            None => {
+                emitters.push(Some(emitter));
+                emitters.len() - 1
+            }
+        };
+
+        Emitter {
+            id: id,
+            generation: generation,
+        }
+    }
+
+    /// the state of the emitter, none if it has been removed
+    fn get<'a>(&self, state: &'a super::super::State) -> Option<&'a EmitterState> {
+        match state.effect.emitters.get(self.id) {
+            Some(&Some(ref emitter)) if emitter.generation == self.generation => Some(emitter),
+            _ => None,
+        }
+    }
+
+    fn get_mut<'a>(&self, state: &'a mut super::super::State) -> Option<&'a mut EmitterState> {
+        match state.effect.emitters.get_mut(self.id) {
+            Some(&mut Some(ref mut emitter)) if emitter.generation == self.generation => Some(emitter),
+            _ => None,
+        }
+    }
+
+    /// set the position of the emitter
+    pub fn set_position(&self, position: [f32;3]) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if let Some(emitter) = self.get_mut(&mut state) {
+            emitter.position = position;
+        }
+    }
+
+    /// return the position of the emitter, the origin if it has been removed
+    pub fn position(&self) -> [f32;3] {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        self.get(&state).map_or([0.;3], |emitter| emitter.position)
+    }
+
+    /// set the velocity of the emitter in distance unit per second
+    pub fn set_velocity(&self, velocity: [f32;3]) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if let Some(emitter) = self.get_mut(&mut state) {
+            emitter.velocity = velocity;
+        }
+    }
+
+    /// return the velocity of the emitter, zero if it has been removed
+    pub fn velocity(&self) -> [f32;3] {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        self.get(&state).map_or([0.;3], |emitter| emitter.velocity)
+    }
+
+    /// set the room of the emitter, its effects are occluded by the portals
+    /// to the listener room, see `baal::spatial`
+    pub fn set_room(&self, room: Option<RoomId>) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if let Some(emitter) = self.get_mut(&mut state) {
+            emitter.room = room;
+        }
+    }
+
+    /// return the room of the emitter
+    pub fn room(&self) -> Option<RoomId> {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        self.get(&state).and_then(|emitter| emitter.room)
+    }
+
+    /// play the short effect at the position of the emitter
+    ///
+    /// the effect is pitched according to the velocity of the emitter relative to the listener
+    pub fn play(&self, effect: ShortId) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        let (position, speed, room) = match self.get(&state) {
+            Some(emitter) => (emitter.position, doppler(emitter.position, emitter.velocity, state.effect.listener), emitter.room),
+            None => return,
+        };
+        super::short::play_from_emitter(effect.0, position, speed, room, &mut state);
+    }
+
+    /// attach the persistent effect to the emitter
+    pub fn attach_persistent(&self, effect: PersistentId) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if let Some(emitter) = self.get_mut(&mut state) {
+            if !emitter.persistent.contains(&effect.0) {
+                emitter.persistent.push(effect.0);
+            }
+        }
+    }
+
+    /// detach the persistent effect from the emitter
+    pub fn detach_persistent(&self, effect: PersistentId) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if let Some(emitter) = self.get_mut(&mut state) {
+            emitter.persistent.retain(|&e| e != effect.0);
+        }
+    }
+}
+
+impl Drop for Emitter {
+    fn drop(&mut self) {
+        unsafe {
+            if RAW_STATE.is_null() {
+                return;
+            }
+            let mut state = (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner());
+            if self.get(&state).is_some() {
+                state.effect.emitters[self.id] = None;
+            }
+        }
+    }
+}
+
+/// speed factor of the doppler effect
+#[inline]
+fn doppler(position: [f32;3], velocity: [f32;3], listener: [f32;3]) -> f32 {
+    let d = super::length(position, listener);
+    if d == 0. {
+        return 1.;
+    }
+    let radial_velocity = (0..3)
+        .fold(0., |acc, i| acc + velocity[i] * (listener[i] - position[i]) / d)
+        .min(SPEED_OF_SOUND * 0.9);
+
+    SPEED_OF_SOUND / (SPEED_OF_SOUND - radial_velocity)
+}
+
+#[test]
+fn test_doppler() {
+    let origin = [0.,0.,0.];
+    assert_eq!(doppler([10.,0.,0.], [0.,0.,0.], origin), 1.);
+    assert_eq!(doppler([10.,0.,0.], [0.,5.,0.], origin), 1.);
+    assert!(doppler([10.,0.,0.], [-10.,0.,0.], origin) > 1.);
+    assert!(doppler([10.,0.,0.], [10.,0.,0.], origin) < 1.);
+}
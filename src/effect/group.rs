@@ -0,0 +1,129 @@
+//! this module allow to control groups of effects
+//!
+//! effects are put in groups by the tags of their setting, a group is a middle
+//! ground between the control of one effect and the control of the effect bus
+//!
+//! ```lua
+//! volume = effect_volume * product(group_volume)
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering::Relaxed;
+
+use super::EffectVolume;
+use super::short;
+use super::super::RAW_STATE;
+use super::super::bus;
+use super::super::Setting;
+
+#[doc(hidden)]
+pub struct GroupState {
+    volume: f32,
+    paused: bool,
+}
+
+#[doc(hidden)]
+pub fn init(setting: &Setting) -> HashMap<String, GroupState> {
+    setting.short_effects.iter()
+        .chain(setting.persistent_effects.iter())
+        .flat_map(|effect| effect.tags.iter())
+        .map(|tag| (tag.clone(), GroupState { volume: 1., paused: false }))
+        .collect()
+}
+
+/// add the groups of the tags of a registered effect and apply them to its volume
+#[doc(hidden)]
+pub fn register(volume: &mut EffectVolume, groups: &mut HashMap<String, GroupState>) {
+    for tag in &volume.tags {
+        groups.entry(tag.clone()).or_insert(GroupState { volume: 1., paused: false });
+    }
+    volume.group_volume = volume.tags.iter().map(|tag| groups[tag].volume).product();
+    volume.paused.store(volume.tags.iter().any(|tag| groups[tag].paused), Relaxed);
+    volume.update();
+}
+
+/// return the group of effects with this tag
+///
+/// panic if no effect has this tag
+pub fn group(name: &str) -> Group {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    assert!(state.effect.groups.contains_key(name), "unknown group");
+    Group {
+        name: name.into(),
+    }
+}
+
+/// a group of short and persistent effects sharing a tag
+#[derive(Clone,Debug,PartialEq)]
+pub struct Group {
+    name: String,
+}
+
+impl Group {
+    /// return the name of the group
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// set the volume of the group, it affects current and future sounds of the effects
+    pub fn set_volume(&self, v: f32) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        state.effect.groups.get_mut(&self.name).unwrap().volume = bus::sanitize_volume(v);
+        update(&self.name, &mut state.effect);
+    }
+
+    /// return the volume of the group
+    pub fn volume(&self) -> f32 {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        state.effect.groups[&self.name].volume
+    }
+
+    /// pause the effects of the group
+    pub fn pause(&self) {
+        self.set_paused(true);
+    }
+
+    /// resume the effects of the group
+    pub fn resume(&self) {
+        self.set_paused(false);
+    }
+
+    /// return whereas the group is paused
+    pub fn is_paused(&self) -> bool {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        state.effect.groups[&self.name].paused
+    }
+
+    /// stop the short effects and remove the sources of the persistent effects of the group
+    pub fn stop(&self) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        let tagged = state.effect.short_volumes.iter()
+            .map(|volume| volume.tags.contains(&self.name))
+            .collect::<Vec<_>>();
+        short::stop_all_matching_inner(|effect| effect.map_or(false, |effect| tagged[effect]), &mut state);
+
+        let effect = &mut state.effect;
+        for (positions, volume) in effect.persistent_positions.iter_mut().zip(effect.persistent_volumes.iter()) {
+            if volume.tags.contains(&self.name) {
+                positions.clear();
+            }
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        state.effect.groups.get_mut(&self.name).unwrap().paused = paused;
+        update(&self.name, &mut state.effect);
+    }
+}
+
+#[inline]
+fn update(name: &str, state: &mut super::State) {
+    let groups = &state.groups;
+    let volumes = state.short_volumes.iter_mut().chain(state.persistent_volumes.iter_mut());
+    for volume in volumes.filter(|volume| volume.tags.iter().any(|tag| tag == name)) {
+        volume.group_volume = volume.tags.iter().map(|tag| groups[tag].volume).product();
+        volume.paused.store(volume.tags.iter().any(|tag| groups[tag].paused), Relaxed);
+        volume.update();
+    }
+}
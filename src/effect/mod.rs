@@ -3,161 +3,1117 @@
 //! be careful that `set_volume`, `set_listener`, `set_distance_model`
 //! only affect future short sound effects
 
+#[cfg(feature = "persistent")]
 pub mod persistent;
 pub mod short;
+pub mod ui;
+pub mod group;
+
+pub use self::group::{group, Group};
+use self::cache::Cache;
+pub mod emitter;
+pub mod blend;
+mod cache;
 
 use rodio::decoder::Decoder;
-use rodio::Sink;
-use rodio::Endpoint;
 use rodio::Source;
 use rodio::source::Buffered;
+#[cfg(feature = "persistent")]
+use rodio::source::Repeat;
+#[cfg(feature = "persistent")]
+use rand::Rng;
+#[cfg(feature = "persistent")]
+use rand::SeedableRng;
+#[cfg(feature = "persistent")]
+use rand::XorShiftRng;
 
+use std::collections::HashMap;
 use std::fs::File;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT};
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::sync::Mutex;
+use std::time::Duration;
 
+use super::AudioSnapshot;
+use super::AudioStatus;
+use super::backend::{AudioBackend, AudioSink, Backend, Sink};
+use super::Stats;
+use super::MemoryReport;
+use super::bus;
+use super::bus::Bus;
+use super::event;
+use super::event::AudioEvent;
 use super::InitError;
 use super::RAW_STATE;
 use super::Setting;
+use super::parameter;
 use super::source;
+use super::debug;
+use super::spatial;
+use super::source::{Either, PoolSources, SendBuffer, Sends};
+
+/// the format the detached short effects are mixed in, see `short::play_detached`
+const DETACHED_CHANNELS: u16 = 2;
+const DETACHED_SAMPLES_RATE: u32 = 44_100;
+
+/// the id of the next short effect played, it is kept across `baal::close` so
+/// that a handle never matches an effect played after it
+static NEXT_SHORT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// the id of a short effect: its index in the short effects of the setting,
+/// see `Setting::short_effect_id` and `short::register`
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ShortId(pub(crate) usize);
+
+impl ShortId {
+    /// the index of the effect in the short effects of the setting
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// the id of a persistent effect: its index in the persistent effects of the
+/// setting, see `Setting::persistent_effect_id` and `persistent::register`
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct PersistentId(pub(crate) usize);
+
+impl PersistentId {
+    /// the index of the effect in the persistent effects of the setting
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// a short or persistent effect entry of the setting
+///
+/// it can be created from a path: `"shoot.ogg".into()`
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct EffectSetting {
+    /// the path of the file relative to the effect directory
+    pub path: PathBuf,
+
+    /// the caption of the effect, audio events are emitted when it starts and ends
+    ///
+    /// only used for short effects and voice lines
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub caption: Option<String>,
+
+    /// the groups of the effect, see `effect::group`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub tags: Vec<String>,
+
+    /// how the effect is loaded
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub load: Load,
+
+    /// the bus the effect is routed to, `bus::EFFECT` if none
+    ///
+    /// only `bus::EFFECT` and `bus::UI` are allowed, voice lines are always
+    /// routed to `bus::VOICE`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub bus: Option<String>,
+
+    /// the send levels of the effect to return buses by name, see `Setting::return_buses`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub sends: HashMap<String, f32>,
+
+    /// whether the loop starts at a random position, so that persistent effects
+    /// of the same file don't play in phase
+    ///
+    /// only used for persistent effects, a new position is chosen on each reset
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub random_start: bool,
+
+    /// the distance from the listener beyond which the effect isn't played,
+    /// none if it is only limited by the distance model
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub max_distance: Option<f32>,
+}
+
+/// a tier of distance for the automatic update of persistent effects
+///
+/// `baal::update` updates the volume of a persistent effect whose nearest
+/// source was at least at `distance` from the listener once every `interval`
+/// calls, the tier of the greatest distance applies
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct LodTier {
+    /// the minimal distance of the tier
+    pub distance: f32,
+    /// the number of calls to `update` between two updates of the volume
+    pub interval: usize,
+}
+
+/// how an effect is loaded
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum Load {
+    /// the effect is decoded entirely at init and kept in memory,
+    /// playing it doesn't decode it again
+    Memory,
+    /// the effect is decoded from the disk each time it is played,
+    /// it is meant for long effects
+    Stream,
+}
+
+impl Default for Load {
+    fn default() -> Load {
+        Load::Memory
+    }
+}
+
+/// the source of a short effect
+enum ShortSource {
+    Memory(Buffered<Decoder<File>>),
+    Stream {
+        path: PathBuf,
+        channels: u16,
+        samples_rate: u32,
+    },
+}
+
+impl ShortSource {
+    /// return the source of a new sound, the streamed file may have been
+    /// removed or replaced since it has been loaded
+    fn open(&self) -> Result<Either<Buffered<Decoder<File>>, Decoder<File>>,InitError> {
+        match *self {
+            ShortSource::Memory(ref source) => Ok(Either::Left(source.clone())),
+            ShortSource::Stream { ref path, .. } => {
+                let file = try!(File::open(path).map_err(|e| InitError::FileOpenError(path.clone(), e)));
+                let decoder = try!(Decoder::new(file).map_err(|e| InitError::DecodeError(path.clone(), e)));
+                Ok(Either::Right(decoder))
+            },
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match *self {
+            ShortSource::Memory(ref source) => source.get_channels(),
+            ShortSource::Stream { channels, .. } => channels,
+        }
+    }
+
+    fn samples_rate(&self) -> u32 {
+        match *self {
+            ShortSource::Memory(ref source) => source.get_samples_rate(),
+            ShortSource::Stream { samples_rate, .. } => samples_rate,
+        }
+    }
+}
+
+impl From<PathBuf> for EffectSetting {
+    fn from(path: PathBuf) -> EffectSetting {
+        EffectSetting {
+            path: path,
+            caption: None,
+            tags: vec!(),
+            load: Load::Memory,
+            bus: None,
+            sends: HashMap::new(),
+            random_start: false,
+            max_distance: None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for EffectSetting {
+    fn from(path: &'a str) -> EffectSetting {
+        PathBuf::from(path).into()
+    }
+}
+
+impl From<String> for EffectSetting {
+    fn from(path: String) -> EffectSetting {
+        PathBuf::from(path).into()
+    }
+}
+
+/// the volume of one effect, it affects current and future sounds of the effect
+struct EffectVolume {
+    volume: f32,
+    muted: bool,
+    tags: Vec<String>,
+    group_volume: f32,
+    final_volume: Arc<AtomicUsize>,
+    paused: Arc<AtomicBool>,
+}
+
+impl EffectVolume {
+    fn new(tags: Vec<String>) -> EffectVolume {
+        EffectVolume {
+            volume: 1.,
+            muted: false,
+            tags: tags,
+            group_volume: 1.,
+            final_volume: Arc::new(AtomicUsize::new(10_000)),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn update(&self) {
+        let volume = if self.muted { 0. } else { self.volume * self.group_volume };
+        self.final_volume.store((volume * 10_000.) as usize, Relaxed);
+    }
+}
+
+/// the bus an effect is routed to
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Route {
+    Effect,
+    Ui,
+}
+
+impl Route {
+    fn new(setting_source: &EffectSetting) -> Result<Route,InitError> {
+        match setting_source.bus.as_ref().map(|name| name.as_str()) {
+            None | Some(bus::EFFECT) => Ok(Route::Effect),
+            Some(bus::UI) => Ok(Route::Ui),
+            Some(name) => Err(InitError::UnknownBus(name.into())),
+        }
+    }
+}
+
+/// the returns the effect is sent to with their levels
+fn sends(setting_source: &EffectSetting, returns: &HashMap<String, Arc<Mutex<SendBuffer>>>) -> Result<Sends,InitError> {
+    let mut sends = vec!();
+    for (name, &level) in &setting_source.sends {
+        let buffer = try!(returns.get(name).ok_or_else(|| InitError::UnknownBus(name.clone())));
+        sends.push((buffer.clone(), level));
+    }
+    Ok(sends)
+}
+
+/// the sink and the controls of a short effect, they are kept once the effect
+/// ended so that playing the next one doesn't allocate them
+struct ShortSlot {
+    sink: Sink,
+    stop: Arc<AtomicUsize>,
+    ended: Arc<AtomicBool>,
+    played: Arc<AtomicUsize>,
+    cutoff: Arc<AtomicUsize>,
+    speed: Arc<AtomicUsize>,
+}
+
+struct Short {
+    /// see `short::ShortHandle`
+    id: usize,
+    slot: ShortSlot,
+    /// none for raw samples, see `short::play_pcm`
+    effect: Option<usize>,
+    /// the bus the effect is played through
+    route: Route,
+    /// the number of samples played per second
+    rate: u64,
+    position: [f32;3],
+    gain: f32,
+    /// shared with the effect so playing it doesn't allocate
+    caption: Option<Arc<String>>,
+    caption_started: bool,
+}
 
 #[doc(hidden)]
 pub struct State {
     listener: [f32;3],
+    listener_target: Option<([f32;3], Duration)>,
     distance_model: DistanceModel,
-    volume: f32,
-    final_volume: Arc<AtomicUsize>,
-    pause: Arc<AtomicBool>,
+    air_absorption: f32,
+    near_field: f32,
+    auto_gain_db: f32,
+    /// the ceiling of the gain of each sound, see `Setting::max_gain`
+    max_gain: f32,
+    #[doc(hidden)]
+    pub bus: Bus,
+    #[doc(hidden)]
+    pub ui_bus: Bus,
     persistent_positions: Vec<Vec<[f32;3]>>,
     persistent_final_volumes: Vec<Arc<AtomicUsize>>,
+    persistent_cutoffs: Vec<Arc<AtomicUsize>>,
+    persistent_blend_gains: Vec<Arc<AtomicUsize>>,
+    persistent_pitches: Vec<Arc<AtomicUsize>>,
+    persistent_volumes: Vec<EffectVolume>,
+    persistent_max_distances: Vec<Option<f32>>,
+    /// the distance of the nearest source at the last update of the volume
+    persistent_nearest: Vec<f32>,
+    lod_tiers: Vec<LodTier>,
+    persistent_fade: Duration,
+    lod_count: usize,
+    persistent_durations: Vec<Option<Duration>>,
+    persistent_samples: Vec<usize>,
+    persistent_routes: Vec<Route>,
+    persistent_sends: Vec<Sends>,
+    #[cfg(feature = "persistent")]
     _persistent_sinks: Vec<Sink>,
-    short_sinks: Vec<Sink>,
-    short_sources: Vec<Buffered<Decoder<File>>>,
+    short_sinks: Vec<Short>,
+    /// the slots of the ended short effects
+    short_slots: Vec<ShortSlot>,
+    max_short_effects: Option<usize>,
+    /// the sources waiting to be mixed with the detached short effects, it is
+    /// dangling if the backend dropped the pool
+    detached: Weak<PoolSources>,
+    _detached_sink: Sink,
+    short_sources: Vec<ShortSource>,
+    short_pitches: Vec<Arc<AtomicUsize>>,
+    short_volumes: Vec<EffectVolume>,
+    short_durations: Vec<Option<Duration>>,
+    short_samples: Vec<usize>,
+    short_routes: Vec<Route>,
+    short_sends: Vec<Sends>,
+    short_captions: Vec<Option<Arc<String>>>,
+    short_max_distances: Vec<Option<f32>>,
+    emitters: Vec<Option<emitter::EmitterState>>,
+    groups: HashMap<String, group::GroupState>,
+    blend_containers: Vec<blend::BlendContainer>,
+    returns: HashMap<String, Arc<Mutex<SendBuffer>>>,
+    _return_sinks: Vec<Sink>,
+    cache: Cache,
 }
 impl State {
     #[doc(hidden)]
-    pub fn init(setting: &Setting, endpoint: &Endpoint) -> Result<State,InitError> {
-        let pause = Arc::new(AtomicBool::new(false));
-        let final_volume = Arc::new(AtomicUsize::new((setting.effect_volume * setting.global_volume * 10_000.) as usize));
+    pub fn init(setting: &Setting, backend: &Backend) -> Result<State,InitError> {
+        State::init_with_cache(setting, backend, Cache::default())
+    }
+    /// init the state loading the effects from the cache
+    fn init_with_cache(setting: &Setting, backend: &Backend, mut cache: Cache) -> Result<State,InitError> {
+        for layer in setting.blend_containers.iter().flat_map(|container| container.layers.iter()) {
+            if layer.effect >= setting.persistent_effects.len() {
+                return Err(InitError::UnknownPersistentEffect(layer.effect));
+            }
+        }
+
+        let mut bus = Bus::new(setting.effect_volume, setting);
+        bus.set_time_scaled(true, 1.);
+
+        let returns = setting.return_buses.keys()
+            .map(|name| (name.clone(), Arc::new(Mutex::new(SendBuffer::default()))))
+            .collect::<HashMap<_, _>>();
 
         let mut persistent_final_volumes = vec!();
+        let mut persistent_cutoffs = vec!();
+        let mut persistent_blend_gains = vec!();
+        let mut persistent_pitches = vec!();
+        let mut persistent_max_distances = vec!();
         let mut persistent_positions = vec!();
-        let mut persistent_sinks = vec!();
+        let mut persistent_volumes = vec!();
+        #[cfg(feature = "persistent")]
+        let mut persistent_sources = vec!();
+        let mut persistent_durations = vec!();
+        let mut persistent_samples = vec!();
+        let mut persistent_routes = vec!();
 
-        for source in &setting.persistent_effects {
-            let p_final_volume = Arc::new(AtomicUsize::new(0));
+        let mut lod_tiers = setting.lod_tiers.clone();
+        lod_tiers.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(::std::cmp::Ordering::Equal));
+        let mut persistent_sends = vec!();
 
-            let path = setting.effect_dir.join(source);
-            let file = try!(File::open(path.clone()).map_err(|e| InitError::FileOpenError(source.clone(), e)));
-            let source = try!(Decoder::new(file).map_err(|e| InitError::DecodeError(source.clone(), e)));
-            let source = source.buffered();
-            let source = source.repeat_infinite();
-            let source = source::amplify_ctrl(source, p_final_volume.clone());
-            let source = source::amplify_ctrl(source, final_volume.clone());
-            let source = source::play_pause_ctrl(source, pause.clone());
+        #[cfg(not(feature = "persistent"))]
+        {
+            if !setting.persistent_effects.is_empty() {
+                warn!("baal: persistent effects are silent without the persistent feature");
+            }
+        }
 
-            let sink = Sink::new(endpoint);
-            sink.append(source);
+        for setting_source in &setting.persistent_effects {
+            persistent_routes.push(try!(Route::new(setting_source)));
+            persistent_sends.push(try!(sends(setting_source, &returns)));
+            #[cfg(feature = "persistent")]
+            {
+                let (source, duration, samples) = try!(load_persistent(setting, setting_source, &mut cache));
+                persistent_sources.push(source);
+                persistent_durations.push(duration);
+                persistent_samples.push(samples);
+            }
+            #[cfg(not(feature = "persistent"))]
+            {
+                persistent_durations.push(None);
+                persistent_samples.push(0);
+            }
 
             persistent_positions.push(vec!());
-            persistent_final_volumes.push(p_final_volume);
-            persistent_sinks.push(sink);
+            persistent_final_volumes.push(Arc::new(AtomicUsize::new(0)));
+            persistent_cutoffs.push(Arc::new(AtomicUsize::new(::std::usize::MAX)));
+            persistent_blend_gains.push(Arc::new(AtomicUsize::new(10_000)));
+            persistent_pitches.push(Arc::new(AtomicUsize::new(10_000)));
+            persistent_volumes.push(EffectVolume::new(setting_source.tags.clone()));
+            persistent_max_distances.push(setting_source.max_distance);
         }
 
         let mut short_sources = vec!();
+        let mut short_pitches = vec!();
+        let mut short_volumes = vec!();
+        let mut short_durations = vec!();
+        let mut short_samples = vec!();
+        let mut short_routes = vec!();
+        let mut short_sends = vec!();
+        let mut short_captions = vec!();
+        let mut short_max_distances = vec!();
 
-        for source in &setting.short_effects {
-            let path = setting.effect_dir.join(source);
-            let file = try!(File::open(path.clone()).map_err(|e| InitError::FileOpenError(source.clone(), e)));
-            let source = try!(Decoder::new(file).map_err(|e| InitError::DecodeError(source.clone(), e)));
-            let source = source.buffered();
+        for setting_source in &setting.short_effects {
+            let (source, duration, samples) = try!(load_short(setting, setting_source, &mut cache));
 
             short_sources.push(source);
+            short_durations.push(duration);
+            short_samples.push(samples);
+            short_routes.push(try!(Route::new(setting_source)));
+            short_sends.push(try!(sends(setting_source, &returns)));
+            short_pitches.push(Arc::new(AtomicUsize::new(10_000)));
+            short_volumes.push(EffectVolume::new(setting_source.tags.clone()));
+            short_captions.push(setting_source.caption.clone().map(Arc::new));
+            short_max_distances.push(setting_source.max_distance);
         }
 
-        Ok(State {
+        // the detached short effects are mixed in one long-lived sink
+        let detached = Arc::new(PoolSources::default());
+        let detached_sink = backend.new_sink();
+        detached_sink.append(source::pool(DETACHED_CHANNELS, DETACHED_SAMPLES_RATE, detached.clone()));
+
+        let mut state = State {
             listener: [0f32;3],
+            listener_target: None,
             distance_model: setting.distance_model.clone(),
-            pause: pause,
-            final_volume: final_volume,
-            volume: setting.effect_volume,
+            air_absorption: setting.air_absorption,
+            near_field: setting.near_field,
+            auto_gain_db: setting.auto_gain_db,
+            max_gain: setting.max_gain,
+            bus: bus,
+            ui_bus: Bus::new(setting.effect_volume, setting),
 
             persistent_positions: persistent_positions,
             persistent_final_volumes: persistent_final_volumes,
-            _persistent_sinks: persistent_sinks,
+            persistent_cutoffs: persistent_cutoffs,
+            persistent_blend_gains: persistent_blend_gains,
+            persistent_pitches: persistent_pitches,
+            persistent_max_distances: persistent_max_distances,
+            persistent_nearest: vec!(0.; setting.persistent_effects.len()),
+            lod_tiers: lod_tiers,
+            persistent_fade: setting.persistent_fade,
+            lod_count: 0,
+            persistent_volumes: persistent_volumes,
+            persistent_durations: persistent_durations,
+            persistent_samples: persistent_samples,
+            persistent_routes: persistent_routes,
+            persistent_sends: persistent_sends,
+            #[cfg(feature = "persistent")]
+            _persistent_sinks: vec!(),
 
-            short_sinks: vec!(),
-            short_sources: short_sources
-        })
+            // playing doesn't grow the vectors when the number of short effects is limited
+            short_sinks: Vec::with_capacity(setting.max_short_effects.unwrap_or(0)),
+            short_slots: Vec::with_capacity(setting.max_short_effects.unwrap_or(0)),
+            max_short_effects: setting.max_short_effects,
+            detached: Arc::downgrade(&detached),
+            _detached_sink: detached_sink,
+            short_sources: short_sources,
+            short_pitches: short_pitches,
+            short_volumes: short_volumes,
+            short_durations: short_durations,
+            short_samples: short_samples,
+            short_routes: short_routes,
+            short_sends: short_sends,
+            short_captions: short_captions,
+            short_max_distances: short_max_distances,
+            emitters: vec!(),
+            groups: group::init(setting),
+            blend_containers: setting.blend_containers.clone(),
+            returns: returns,
+            _return_sinks: vec!(),
+            cache: cache,
+        };
+        // the files no longer in the setting are released
+        state.cache.clear_unused();
+
+        for (name, return_bus) in &setting.return_buses {
+            let source = source::reverb(state.returns[name].clone(), return_bus.decay.max(0.).min(0.99));
+            let source = source.amplify(return_bus.volume);
+            let sink = backend.new_sink();
+            sink.append(state.bus.apply(source));
+            state._return_sinks.push(sink);
+        }
+
+        #[cfg(feature = "persistent")]
+        let mut rng = match setting.deterministic_seed {
+            Some(seed) => XorShiftRng::from_seed([seed, 0x2f6b3a1d, 0x5c84e2b7, 0x91d07f43]),
+            None => ::rand::weak_rng(),
+        };
+        #[cfg(feature = "persistent")]
+        for (effect, source) in persistent_sources.into_iter().enumerate() {
+            let start = match (setting.persistent_effects[effect].random_start, state.persistent_durations[effect]) {
+                (true, Some(duration)) if duration > Duration::new(0, 0) => {
+                    let nanos = duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64;
+                    let start = rng.gen_range(0, nanos);
+                    Duration::new(start / 1_000_000_000, (start % 1_000_000_000) as u32)
+                },
+                _ => Duration::new(0, 0),
+            };
+            let sink = persistent_sink(source, start, effect, &state, backend);
+            state._persistent_sinks.push(sink);
+        }
+
+        for container in &state.blend_containers {
+            update_blend_container(container, 0., &state.persistent_blend_gains);
+        }
+
+        Ok(state)
+    }
+    /// the gain of the distance model with the near field
+    #[inline]
+    fn distance_gain(&self, pos: [f32;3], listener: [f32;3]) -> f32 {
+        self.distance_model.gain_at(length(pos, listener).max(self.near_field))
+    }
+    /// whether the position is within the maximum distance from the listener
+    #[inline]
+    fn in_range(&self, max_distance: Option<f32>, pos: [f32;3]) -> bool {
+        max_distance.map_or(true, |max| length(pos, self.listener) <= max)
+    }
+    /// clamp the gain of a sound so that with the volume of its effect it
+    /// doesn't exceed the ceiling
+    #[inline]
+    fn ceil_gain(&self, gain: f32, volume: &EffectVolume) -> f32 {
+        let volume = volume.final_volume.load(Relaxed) as f32 / 10_000.;
+        if volume > 0. { gain.min(self.max_gain / volume) } else { gain }
+    }
+    fn route_bus(&self, route: Route) -> &Bus {
+        match route {
+            Route::Effect => &self.bus,
+            Route::Ui => &self.ui_bus,
+        }
     }
     #[doc(hidden)]
-    pub fn reset(&mut self, setting: &Setting, endpoint: &Endpoint) -> Result<(),InitError> {
-        *self = try!(State::init(setting, endpoint));
+    pub fn reset(&mut self, setting: &Setting, backend: &Backend) -> Result<(),InitError> {
+        let emitters = ::std::mem::replace(&mut self.emitters, vec!());
+        let cache = ::std::mem::replace(&mut self.cache, Cache::default());
+        *self = try!(State::init_with_cache(setting, backend, cache));
+        self.emitters = emitters;
         Ok(())
     }
 }
 
-/// set the volume of sound effects
-/// take effect for future sounds effects only
-pub fn set_volume(v: f32) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.effect.volume = v;
-    update_volume(&mut *state);
+#[doc(hidden)]
+#[inline]
+pub fn update(dt: Duration, state: &mut super::State) {
+    if let Some((target, smoothing)) = state.effect.listener_target {
+        let t = if smoothing == Duration::new(0, 0) {
+            1.
+        } else {
+            1. - (-bus::as_secs_f32(dt) / bus::as_secs_f32(smoothing)).exp()
+        };
+        for (l, t_l) in state.effect.listener.iter_mut().zip(&target) {
+            *l += (t_l - *l) * t;
+        }
+    }
+
+    #[cfg(feature = "persistent")]
+    persistent::update_volume_lod_inner(state);
+
+    reap_shorts(state);
+    update_auto_gain(state);
+}
+
+/// emit the captions of the short effects that started and free the ones that
+/// have ended, their slots are kept for the next ones
+fn reap_shorts(state: &mut super::State) {
+    // the shorts are removed in place so that the vector keeps its allocation
+    let mut i = 0;
+    while i < state.effect.short_sinks.len() {
+        // loaded once so the caption end can't be missed
+        let ended = state.effect.short_sinks[i].slot.ended.load(Relaxed);
+        let started = {
+            let short = &mut state.effect.short_sinks[i];
+            let started = !short.caption_started && short.slot.played.load(Relaxed) > 0;
+            short.caption_started |= started;
+            if started { short.caption.clone() } else { None }
+        };
+        if let Some(caption) = started {
+            event::emit(AudioEvent::CaptionStart(caption), state);
+        }
+        if ended {
+            let short = state.effect.short_sinks.remove(i);
+            if let (true, Some(caption)) = (short.caption_started, short.caption) {
+                event::emit(AudioEvent::CaptionEnd(caption), state);
+            }
+            // its sources ended so the sink is empty
+            state.effect.short_slots.push(short.slot);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// return a slot for a new short effect, the slot of an ended one is reused if any
+fn short_slot(cutoff: usize, speed: f32, state: &mut super::State) -> ShortSlot {
+    match state.effect.short_slots.pop() {
+        Some(slot) => {
+            slot.stop.store(source::PLAYING, Relaxed);
+            slot.ended.store(false, Relaxed);
+            slot.played.store(0, Relaxed);
+            slot.cutoff.store(cutoff, Relaxed);
+            slot.speed.store((speed * 10_000.) as usize, Relaxed);
+            slot
+        },
+        None => ShortSlot {
+            sink: state.backend.new_sink(),
+            stop: Arc::new(AtomicUsize::new(source::PLAYING)),
+            ended: Arc::new(AtomicBool::new(false)),
+            played: Arc::new(AtomicUsize::new(0)),
+            cutoff: Arc::new(AtomicUsize::new(cutoff)),
+            speed: Arc::new(AtomicUsize::new((speed * 10_000.) as usize)),
+        },
+    }
+}
+
+/// compensate the number of short effects heard on the effect and ui buses,
+/// see `Setting::auto_gain_db`
+fn update_auto_gain(state: &mut super::State) {
+    if state.effect.auto_gain_db <= 0. {
+        return;
+    }
+    let mut effects = 0;
+    let mut uis = 0;
+    // effects waiting for their delay aren't heard yet
+    for short in state.effect.short_sinks.iter().filter(|short| short.slot.played.load(Relaxed) > 0) {
+        match short.effect.map_or(Route::Effect, |effect| state.effect.short_routes[effect]) {
+            Route::Effect => effects += 1,
+            Route::Ui => uis += 1,
+        }
+    }
+    let global_volume = state.global_volume;
+    let db = state.effect.auto_gain_db;
+    state.effect.bus.set_auto_gain(bus::auto_gain(db, effects), global_volume);
+    state.effect.ui_bus.set_auto_gain(bus::auto_gain(db, uis), global_volume);
+}
+
+/// stop short effects so that a new one can be played without exceeding the
+/// maximum number of short effects, return false if no short effect can be played
+///
+/// the ended effects are freed first so that they aren't counted nor stolen
+fn reserve_short(state: &mut super::State) -> bool {
+    let max = match state.effect.max_short_effects {
+        Some(0) => return false,
+        Some(max) => max,
+        None => return true,
+    };
+    reap_shorts(state);
+    while state.effect.short_sinks.len() >= max {
+        // shorts are ordered from the oldest so the first quietest one is the oldest
+        let mut quietest = 0;
+        for (i, short) in state.effect.short_sinks.iter().enumerate() {
+            if short.gain < state.effect.short_sinks[quietest].gain {
+                quietest = i;
+            }
+        }
+        let short = state.effect.short_sinks.remove(quietest);
+        debug!("baal: short effect {:?} stolen", short.effect);
+        if let (true, Some(caption)) = (short.caption_started, short.caption) {
+            event::emit(AudioEvent::CaptionEnd(caption), state);
+        }
+    }
+    true
 }
 
 #[doc(hidden)]
-#[inline]
-pub fn update_volume(state: &mut super::State) {
-    state.effect.final_volume.store((state.effect.volume * state.global_volume * 10_000f32) as usize, Relaxed);
+pub fn report_inner(state: &super::State, status: &mut AudioStatus) {
+    status.effect_volume = state.effect.bus.volume();
+    status.effect_paused = state.effect.bus.is_paused();
+    status.listener = state.effect.listener;
+    status.distance_model = state.effect.distance_model.clone();
+}
+
+#[doc(hidden)]
+pub fn stats_inner(state: &super::State, stats: &mut Stats) {
+    let audible = state.effect.persistent_final_volumes.iter()
+        .zip(&state.effect.persistent_blend_gains)
+        .filter(|&(volume, gain)| volume.load(Relaxed) > 0 && gain.load(Relaxed) > 0)
+        .count();
+    stats.short_effects = state.effect.short_sinks.len();
+    stats.persistent_audible = audible;
+    stats.persistent_virtual = state.effect.persistent_final_volumes.len() - audible;
+    let buffered_samples = state.effect.persistent_samples.iter()
+        .chain(&state.effect.short_samples)
+        .fold(0, |sum, samples| sum + samples);
+    stats.buffered_bytes = buffered_samples * ::std::mem::size_of::<i16>();
+    // the shorts, their kept slots and the sink of the detached shorts
+    stats.sinks += state.effect.short_sinks.len() + state.effect.short_slots.len() + 1;
+    #[cfg(feature = "persistent")]
+    {
+        stats.sinks += state.effect._persistent_sinks.len();
+    }
+}
+
+#[doc(hidden)]
+pub fn memory_report_inner(state: &super::State, report: &mut MemoryReport) {
+    state.effect.cache.memory_report(report);
+}
+
+#[doc(hidden)]
+pub fn spatial_state_inner(state: &super::State, spatial_state: &mut debug::SpatialState) {
+    let listener = state.effect.listener;
+    spatial_state.listener = listener;
+    spatial_state.emitters = state.effect.emitters.iter()
+        .filter_map(|emitter| emitter.as_ref())
+        .map(|emitter| debug::EmitterInfo {
+            position: emitter.position,
+            velocity: emitter.velocity,
+            room: emitter.room,
+            gain: state.effect.distance_gain(emitter.position, listener)
+                * spatial::occlusion_inner(emitter.room, state).gain,
+            persistent: emitter.persistent.iter().cloned().map(PersistentId).collect(),
+        })
+        .collect();
+    spatial_state.short_effects = state.effect.short_sinks.iter()
+        .map(|short| debug::ShortInfo {
+            effect: short.effect.map(ShortId),
+            position: short.position,
+            gain: short.gain,
+        })
+        .collect();
+    spatial_state.persistent_effects = state.effect.persistent_positions.iter()
+        .enumerate()
+        .filter(|&(_, positions)| !positions.is_empty())
+        .map(|(effect, positions)| debug::PersistentInfo {
+            effect: PersistentId(effect),
+            positions: positions.clone(),
+            gain: state.effect.persistent_final_volumes[effect].load(Relaxed) as f32 / 10_000.,
+        })
+        .collect();
+}
+
+#[doc(hidden)]
+pub fn snapshot_inner(state: &super::State, snapshot: &mut AudioSnapshot) {
+    snapshot.effect_volume = state.effect.bus.volume();
+    snapshot.effect_paused = state.effect.bus.is_paused();
+    snapshot.listener = state.effect.listener;
+    snapshot.persistent_positions = state.effect.persistent_positions.clone();
+}
+
+#[doc(hidden)]
+pub fn restore_inner(snapshot: &AudioSnapshot, state: &mut super::State) {
+    let global_volume = state.global_volume;
+    state.effect.bus.set_volume(snapshot.effect_volume, global_volume);
+    state.effect.bus.set_paused(snapshot.effect_paused);
+    state.effect.listener = snapshot.listener;
+    state.effect.listener_target = None;
+    for (positions, saved) in state.effect.persistent_positions.iter_mut().zip(&snapshot.persistent_positions) {
+        *positions = saved.clone();
+    }
+    #[cfg(feature = "persistent")]
+    persistent::update_volume_for_all_inner(state);
+}
+
+/// set the volume of sound effects
+/// take effect for future sounds effects only
+pub fn set_volume(v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    state.effect.bus.set_volume(v, global_volume);
 }
 
 
 /// return the volume of sound effects
 pub fn volume() -> f32 {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
-    state.effect.volume
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.bus.volume()
 }
 
 /// pause all effects
 pub fn pause() {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
-    state.effect.pause.store(true,Relaxed);
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.bus.set_paused(true);
 }
 
 /// resume all effects
 pub fn resume() {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
-    state.effect.pause.store(false,Relaxed);
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.bus.set_paused(false);
 }
 
 /// return whereas effects are paused
 pub fn is_paused() -> bool {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
-    state.effect.pause.load(Relaxed)
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.bus.is_paused()
 }
 
 /// set the position of the listener
+///
+/// it cancels the target set by `set_listener_target`
 pub fn set_listener(pos: [f32;3]) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
     state.effect.listener = pos;
+    state.effect.listener_target = None;
+}
+
+/// set the target of the listener, the listener glides toward it during `baal::update`
+///
+/// the smoothing time is the time constant of the glide: after it the listener
+/// has done about 63% of the way
+pub fn set_listener_target(pos: [f32;3], smoothing_time: Duration) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.listener_target = Some((pos, smoothing_time));
 }
 
 /// return the position of the listener
 pub fn listener() -> [f32;3] {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
     state.effect.listener
 }
 
 /// set the distance model
 pub fn set_distance_model(d: DistanceModel) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
     state.effect.distance_model = d;
 }
 
+/// set the value of the parameter, equivalent to `baal::set_parameter`
+pub fn set_parameter(name: &str, value: f32) {
+    parameter::set_parameter(name, value);
+}
+
+/// return the value of the parameter if it has been set, equivalent to `baal::parameter`
+pub fn parameter(name: &str) -> Option<f32> {
+    parameter::parameter(name)
+}
+
+#[doc(hidden)]
+pub fn update_blend_containers(name: &str, value: f32, state: &super::State) {
+    for container in state.effect.blend_containers.iter().filter(|c| c.parameter == name) {
+        update_blend_container(container, value, &state.effect.persistent_blend_gains);
+    }
+}
+
+#[inline]
+fn update_blend_container(container: &blend::BlendContainer, value: f32, gains: &[Arc<AtomicUsize>]) {
+    for layer in &container.layers {
+        gains[layer.effect].store((layer.gain(value) * 10_000.) as usize, Relaxed);
+    }
+}
+
+/// set the air absorption coefficient
+///
+/// effects are low-passed with a cutoff frequency of `20000 / (1 + air_absorption * d)` Hz
+/// where d is the distance to the listener, 0 disables it
+pub fn set_air_absorption(a: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.air_absorption = a;
+}
+
+/// return the air absorption coefficient
+pub fn air_absorption() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.air_absorption
+}
+
+/// set the near field distance, effects nearer are as loud as at this distance
+pub fn set_near_field(d: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.near_field = d;
+}
+
+/// return the near field distance
+pub fn near_field() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.near_field
+}
+
+#[inline]
+fn air_absorption_cutoff(air_absorption: f32, d: f32) -> usize {
+    if air_absorption > 0. {
+        (20_000. / (1. + air_absorption * d)) as usize
+    } else {
+        ::std::usize::MAX
+    }
+}
+
+/// the cutoff of the air absorption lowered by the low pass of the occlusion
+#[inline]
+fn occluded_cutoff(cutoff: usize, occlusion: spatial::Occlusion) -> usize {
+    match occlusion.low_pass {
+        Some(low_pass) => cutoff.min(low_pass.max(0.) as usize),
+        None => cutoff,
+    }
+}
+
+#[cfg(feature = "persistent")]
+type PersistentSource = Either<Repeat<Buffered<Decoder<File>>>, source::StreamLoop>;
+
+/// open and decode the file of the effect
+fn decode(setting: &Setting, source: &PathBuf) -> Result<Decoder<File>,InitError> {
+    let path = setting.effect_dir.join(source);
+    let file = try!(File::open(path).map_err(|e| InitError::FileOpenError(source.clone(), e)));
+    Decoder::new(file).map_err(|e| InitError::DecodeError(source.clone(), e))
+}
+
+/// load the persistent effect, return its looping source, its duration
+/// and the number of samples kept in memory
+#[cfg(feature = "persistent")]
+fn load_persistent(setting: &Setting, setting_source: &EffectSetting, cache: &mut Cache) -> Result<(PersistentSource, Option<Duration>, usize),InitError> {
+    Ok(match setting_source.load {
+        Load::Memory => {
+            let (source, duration, samples) = try!(cache.load(setting, &setting_source.path));
+            (Either::Left(source.repeat_infinite()), duration, samples)
+        },
+        Load::Stream => {
+            let duration = try!(decode(setting, &setting_source.path)).get_total_duration();
+            let path = setting.effect_dir.join(&setting_source.path);
+            (Either::Right(try!(source::stream_loop(path))), duration, 0)
+        },
+    })
+}
+
+/// play the source of the persistent effect from start in a new sink controlled by the effect
+#[cfg(feature = "persistent")]
+fn persistent_sink(source: PersistentSource, start: Duration, effect: usize, state: &State, backend: &Backend) -> Sink {
+    let source = source::trim(source, start, None);
+    let source = source::speed_ctrl(source, state.persistent_pitches[effect].clone());
+    let source = source::low_pass_ctrl(source, state.persistent_cutoffs[effect].clone());
+    let source = source::ramp_ctrl(source, state.persistent_final_volumes[effect].clone(), state.persistent_fade);
+    let source = source::amplify_ctrl(source, state.persistent_blend_gains[effect].clone());
+    let source = source::amplify_ctrl(source, state.persistent_volumes[effect].final_volume.clone());
+    let source = source::play_pause_ctrl(source, state.persistent_volumes[effect].paused.clone());
+    let source = source::tap(source, state.persistent_sends[effect].clone());
+    let source = state.route_bus(state.persistent_routes[effect]).apply(source);
+
+    let sink = backend.new_sink();
+    sink.append(source);
+    sink
+}
+
+/// load the short effect, return its source, its duration
+/// and the number of samples kept in memory
+fn load_short(setting: &Setting, setting_source: &EffectSetting, cache: &mut Cache) -> Result<(ShortSource, Option<Duration>, usize),InitError> {
+    Ok(match setting_source.load {
+        Load::Memory => {
+            let (source, duration, samples) = try!(cache.load(setting, &setting_source.path));
+            (ShortSource::Memory(source), duration, samples)
+        },
+        Load::Stream => {
+            let source = try!(decode(setting, &setting_source.path));
+            let duration = source.get_total_duration();
+            let source = ShortSource::Stream {
+                channels: source.get_channels(),
+                samples_rate: source.get_samples_rate(),
+                path: setting.effect_dir.join(&setting_source.path),
+            };
+            (source, duration, 0)
+        },
+    })
+}
+
+#[doc(hidden)]
+#[cfg(feature = "persistent")]
+pub fn reload_persistent_inner(effect: usize, state: &mut super::State) -> Result<(),InitError> {
+    state.effect.cache.invalidate(&state.setting, &state.setting.persistent_effects[effect].path);
+    let (source, duration, samples) = try!(load_persistent(&state.setting, &state.setting.persistent_effects[effect], &mut state.effect.cache));
+    // the reloaded file may be shorter, it starts from the beginning
+    let sink = persistent_sink(source, Duration::new(0, 0), effect, &state.effect, &state.backend);
+    // the previous sink is dropped and thus stopped
+    state.effect._persistent_sinks[effect] = sink;
+    state.effect.persistent_durations[effect] = duration;
+    state.effect.persistent_samples[effect] = samples;
+    debug!("baal: reload persistent effect {}", effect);
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn reload_short_inner(effect: usize, state: &mut super::State) -> Result<(),InitError> {
+    state.effect.cache.invalidate(&state.setting, &state.setting.short_effects[effect].path);
+    let (source, duration, samples) = try!(load_short(&state.setting, &state.setting.short_effects[effect], &mut state.effect.cache));
+    // playing sounds keep the previous source until they end
+    state.effect.short_sources[effect] = source;
+    state.effect.short_durations[effect] = duration;
+    state.effect.short_samples[effect] = samples;
+    debug!("baal: reload short effect {}", effect);
+    Ok(())
+}
+
+#[doc(hidden)]
+#[cfg(feature = "persistent")]
+pub fn register_persistent_inner(setting_source: EffectSetting, state: &mut super::State) -> Result<PersistentId,InitError> {
+    let route = try!(Route::new(&setting_source));
+    let sends = try!(sends(&setting_source, &state.effect.returns));
+    let (source, duration, samples) = try!(load_persistent(&state.setting, &setting_source, &mut state.effect.cache));
+
+    let effect = state.effect.persistent_volumes.len();
+    {
+        let effect_state = &mut state.effect;
+        let mut volume = EffectVolume::new(setting_source.tags.clone());
+        group::register(&mut volume, &mut effect_state.groups);
+        effect_state.persistent_positions.push(vec!());
+        effect_state.persistent_final_volumes.push(Arc::new(AtomicUsize::new(0)));
+        effect_state.persistent_cutoffs.push(Arc::new(AtomicUsize::new(::std::usize::MAX)));
+        effect_state.persistent_blend_gains.push(Arc::new(AtomicUsize::new(10_000)));
+        effect_state.persistent_pitches.push(Arc::new(AtomicUsize::new(10_000)));
+        effect_state.persistent_volumes.push(volume);
+        effect_state.persistent_max_distances.push(setting_source.max_distance);
+        effect_state.persistent_nearest.push(0.);
+        effect_state.persistent_durations.push(duration);
+        effect_state.persistent_samples.push(samples);
+        effect_state.persistent_routes.push(route);
+        effect_state.persistent_sends.push(sends);
+    }
+    let sink = persistent_sink(source, Duration::new(0, 0), effect, &state.effect, &state.backend);
+    state.effect._persistent_sinks.push(sink);
+    // kept so that reload and reset find the effect
+    state.setting.persistent_effects.push(setting_source);
+    debug!("baal: register persistent effect {}", effect);
+    Ok(PersistentId(effect))
+}
+
+#[doc(hidden)]
+pub fn register_short_inner(setting_source: EffectSetting, state: &mut super::State) -> Result<ShortId,InitError> {
+    let route = try!(Route::new(&setting_source));
+    let sends = try!(sends(&setting_source, &state.effect.returns));
+    let (source, duration, samples) = try!(load_short(&state.setting, &setting_source, &mut state.effect.cache));
+
+    let effect = state.effect.short_sources.len();
+    {
+        let effect_state = &mut state.effect;
+        let mut volume = EffectVolume::new(setting_source.tags.clone());
+        group::register(&mut volume, &mut effect_state.groups);
+        effect_state.short_sources.push(source);
+        effect_state.short_durations.push(duration);
+        effect_state.short_samples.push(samples);
+        effect_state.short_routes.push(route);
+        effect_state.short_sends.push(sends);
+        effect_state.short_pitches.push(Arc::new(AtomicUsize::new(10_000)));
+        effect_state.short_volumes.push(volume);
+        effect_state.short_captions.push(setting_source.caption.clone().map(Arc::new));
+        effect_state.short_max_distances.push(setting_source.max_distance);
+    }
+    // kept so that reload and reset find the effect
+    state.setting.short_effects.push(setting_source);
+    debug!("baal: register short effect {}", effect);
+    Ok(ShortId(effect))
+}
+
+/// return the duration of the buffered source of this number of samples,
+/// it is computed from the samples if the decoder doesn't know it
+fn duration(source: &Buffered<Decoder<File>>, samples: usize) -> Option<Duration> {
+    source.get_total_duration().or_else(|| {
+        let rate = source.get_samples_rate() as u64 * source.get_channels() as u64;
+        if rate == 0 {
+            return None;
+        }
+        let samples = samples as u64;
+        Some(Duration::new(samples / rate, ((samples % rate) * 1_000_000_000 / rate) as u32))
+    })
+}
+
+#[inline]
+fn length(pos: [f32;3], listener: [f32;3]) -> f32 {
+    pos.iter()
+        .zip(&listener)
+        .map(|(a,b)| (a-b).powi(2))
+        .fold(0.,|sum,i| sum+i)
+        .sqrt()
+}
+
 /// distance model, used to compute sound effects volumes.
 #[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
 pub enum DistanceModel {
     /// if d <= a then 1
     ///
@@ -171,16 +1127,28 @@ pub enum DistanceModel {
     ///
     /// if d >= b then 0
     Pow2(f32,f32),
+    /// piecewise linear curve of (distance, volume) points sorted by distance
+    ///
+    /// if d <= first distance then first volume
+    ///
+    /// if d >= last distance then last volume
+    ///
+    /// else volume is interpolated between the two surrounding points
+    ///
+    /// if there is no points then 1
+    Curve(Vec<(f32,f32)>),
 }
 
 impl DistanceModel {
+    #[cfg(test)]
     fn distance(&self, pos: [f32;3], listener: [f32;3]) -> f32 {
-        let d = pos.iter()
-            .zip(&listener)
-            .map(|(a,b)| (a-b).powi(2))
-            .fold(0.,|sum,i| sum+i)
-            .sqrt();
+        self.gain_at(length(pos, listener))
+    }
 
+    /// return the gain of an effect at the distance from the listener
+    ///
+    /// linear and pow2 models never exceed 1, see `Setting::near_field`
+    pub fn gain_at(&self, d: f32) -> f32 {
         match *self {
             DistanceModel::Linear(a,b) => {
                 if d <= a {
@@ -200,6 +1168,7 @@ impl DistanceModel {
                     0.
                 }
             }
+            DistanceModel::Curve(ref points) => parameter::curve(points, d),
         }
     }
 }
@@ -213,4 +1182,17 @@ fn test_distance() {
     assert_eq!(d.distance(origin,[60.,0.,0.]), 0.5);
     assert!(d.distance(origin,[100.,0.,0.]) - 0.1 < 0.00001);
     assert_eq!(d.distance(origin,[150.,0.,0.]), 0.);
+    assert_eq!(d.gain_at(60.), 0.5);
+}
+
+#[test]
+fn test_curve() {
+    let origin = [0.,0.,0.];
+    let d = DistanceModel::Curve(vec!((0.,1.),(10.,1.),(50.,0.4),(120.,0.)));
+    assert_eq!(d.distance(origin,origin), 1.);
+    assert_eq!(d.distance(origin,[10.,0.,0.]), 1.);
+    assert!((d.distance(origin,[30.,0.,0.]) - 0.7).abs() < 0.00001);
+    assert_eq!(d.distance(origin,[50.,0.,0.]), 0.4);
+    assert_eq!(d.distance(origin,[150.,0.,0.]), 0.);
+    assert_eq!(DistanceModel::Curve(vec!()).distance(origin,[150.,0.,0.]), 1.);
 }
@@ -10,39 +10,65 @@
 //!
 //! also if its volume is zero then the sound is not played at all
 
+use super::PersistentId;
+use super::EffectSetting;
 use super::super::RAW_STATE;
+use super::super::bus;
+use super::super::InitError;
+use super::super::spatial;
 
+use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
 
 /// add a new source of the effect
-pub fn add_position(effect: usize, pos: [f32;3]) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.effect.persistent_positions[effect].push(pos);
+pub fn add_position(effect: PersistentId, pos: [f32;3]) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_positions[effect.0].push(pos);
 }
 
 /// add a vec of new sources of the effect
-pub fn add_positions(effect: usize, mut pos: Vec<[f32;3]>) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.effect.persistent_positions[effect].append(&mut pos);
+pub fn add_positions(effect: PersistentId, mut pos: Vec<[f32;3]>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_positions[effect.0].append(&mut pos);
 }
 
 /// add a vec of new sources of the effects
-pub fn add_positions_for_all(all: Vec<(usize,Vec<[f32;3]>)>) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
+pub fn add_positions_for_all(all: Vec<(PersistentId,Vec<[f32;3]>)>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
     for (effect,mut pos) in all {
-        state.effect.persistent_positions[effect].append(&mut pos);
+        state.effect.persistent_positions[effect.0].append(&mut pos);
+    }
+}
+
+/// replace the sources of the effect
+pub fn set_positions(effect: PersistentId, pos: Vec<[f32;3]>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_positions[effect.0] = pos;
+}
+
+/// replace the sources of all effects
+///
+/// effects not in the vec have their sources removed
+pub fn set_positions_for_all(all: Vec<(PersistentId,Vec<[f32;3]>)>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    for p in &mut state.effect.persistent_positions {
+        p.clear()
+    }
+    for (effect,mut pos) in all {
+        state.effect.persistent_positions[effect.0].append(&mut pos);
     }
 }
 
 /// remove all sources of the effect
-pub fn clear_positions(effect: usize) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.effect.persistent_positions[effect].clear()
+pub fn clear_positions(effect: PersistentId) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_positions[effect.0].clear()
 }
 
 /// remove all sources of all effects
 pub fn clear_positions_for_all() {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
     for p in &mut state.effect.persistent_positions {
         p.clear()
     }
@@ -50,22 +76,186 @@ pub fn clear_positions_for_all() {
 
 /// update the volume of effect computed from sources position and listener position at the
 /// moment of this call
-pub fn update_volume(effect: usize) {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
-    let volume = state.effect.persistent_positions[effect].iter()
-        .fold(0f32, |acc, &pos| acc + state.effect.distance_model.distance(pos,state.effect.listener));
+pub fn update_volume(effect: PersistentId) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    update_volume_inner(effect.0, &state);
+}
+
+/// update the volume and return the distance of the nearest source
+#[inline]
+fn update_volume_inner(effect: usize, state: &super::super::State) -> f32 {
+    let listener = state.effect.listener;
+    let attached = state.effect.emitters.iter()
+        .filter_map(|emitter| emitter.as_ref())
+        .filter(|emitter| emitter.persistent.contains(&effect))
+        .map(|emitter| (emitter.position, emitter.room));
 
+    let mut volume = 0f32;
+    let mut nearest = ::std::f32::INFINITY;
+    // the cutoff of the least filtered source
+    let mut cutoff = None;
+    let positions = state.effect.persistent_positions[effect].iter().map(|&pos| (pos, None));
+    let max_distance = state.effect.persistent_max_distances[effect];
+    for (pos, room) in positions.chain(attached) {
+        let d = super::length(pos,listener);
+        nearest = nearest.min(d);
+        if max_distance.map_or(false, |max| d > max) {
+            continue;
+        }
+        let occlusion = spatial::occlusion_inner(room, state);
+        volume += state.effect.distance_gain(pos,listener) * occlusion.gain;
+        let c = super::occluded_cutoff(super::air_absorption_cutoff(state.effect.air_absorption, d), occlusion);
+        cutoff = Some(cutoff.map_or(c, |cutoff: usize| cutoff.max(c)));
+    }
+    let cutoff = cutoff.unwrap_or_else(|| super::air_absorption_cutoff(state.effect.air_absorption, nearest));
+
+    let volume = state.effect.ceil_gain(volume, &state.effect.persistent_volumes[effect]);
     state.effect.persistent_final_volumes[effect].store((volume * 10_000f32) as usize, Relaxed);
+    state.effect.persistent_cutoffs[effect].store(cutoff, Relaxed);
+    nearest
 }
 
 /// update the volume of all effect
 pub fn update_volume_for_all() {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    update_volume_for_all_inner(&state);
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn update_volume_for_all_inner(state: &super::super::State) {
+    for effect in 0..state.effect.persistent_positions.len() {
+        update_volume_inner(effect, state);
+    }
+}
+
+/// update the volumes of the effects whose tier is due, see `LodTier`
+#[doc(hidden)]
+pub fn update_volume_lod_inner(state: &mut super::super::State) {
+    let count = state.effect.lod_count;
+    state.effect.lod_count = count.wrapping_add(1);
+    for effect in 0..state.effect.persistent_positions.len() {
+        let nearest = state.effect.persistent_nearest[effect];
+        let interval = state.effect.lod_tiers.iter()
+            .take_while(|tier| tier.distance <= nearest)
+            .last()
+            .map_or(1, |tier| tier.interval.max(1));
+        // the effects of a tier are spread over its interval
+        if (count + effect) % interval == 0 {
+            state.effect.persistent_nearest[effect] = update_volume_inner(effect, state);
+        }
+    }
+}
+
+/// set the pitch of the effect
+///
+/// the pitch is a factor of the playback speed, 1 is the normal speed
+pub fn set_pitch(effect: PersistentId, pitch: f32) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    set_pitch_inner(effect.0, pitch, &state);
+}
 
-    for (positions,final_volume) in state.effect.persistent_positions.iter().zip(state.effect.persistent_final_volumes.iter()) {
-        let volume = positions.iter()
-            .fold(0f32, |acc, &pos| acc + state.effect.distance_model.distance(pos,state.effect.listener));
+#[doc(hidden)]
+#[inline]
+pub fn set_pitch_inner(effect: usize, pitch: f32, state: &super::super::State) {
+    state.effect.persistent_pitches[effect].store((pitch * 10_000.) as usize, Relaxed);
+}
+
+/// return the pitch of the effect
+pub fn pitch(effect: PersistentId) -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_pitches[effect.0].load(Relaxed) as f32 / 10_000.
+}
+
+/// set the volume of the effect in [0,1], it affects current and future sounds of the effect
+pub fn set_effect_volume(effect: PersistentId, v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let volume = &mut state.effect.persistent_volumes[effect.0];
+    volume.volume = bus::sanitize_volume(v);
+    volume.update();
+}
+
+/// return the volume of the effect
+pub fn effect_volume(effect: PersistentId) -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_volumes[effect.0].volume
+}
+
+/// mute or unmute the effect, it affects current and future sounds of the effect
+pub fn mute_effect(effect: PersistentId, muted: bool) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let volume = &mut state.effect.persistent_volumes[effect.0];
+    volume.muted = muted;
+    volume.update();
+}
+
+/// return whether the effect is muted
+pub fn is_effect_muted(effect: PersistentId) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_volumes[effect.0].muted
+}
+
+/// return the duration of the effect if known
+pub fn duration(effect: PersistentId) -> Option<Duration> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_durations[effect.0]
+}
+
+/// return the distance from the listener beyond which the positions of the
+/// effect aren't heard, see `EffectSetting::max_distance`
+pub fn max_distance(effect: PersistentId) -> Option<f32> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.persistent_max_distances[effect.0]
+}
+
+/// reload the effect from the disk, the effect restarts from its beginning
+///
+/// its sources, volume and pitch are kept, it is meant to iterate on the file
+/// while the game is running
+pub fn reload(effect: PersistentId) -> Result<(),InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    super::reload_persistent_inner(effect.0, &mut state)
+}
+
+/// load a new persistent effect from the effect directory and return its id,
+/// it has no position
+///
+/// it is meant for prototypes and editors that start the audio player before
+/// the list of effects is known, see `baal::init_default`
+pub fn register(setting: EffectSetting) -> Result<PersistentId,InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    super::register_persistent_inner(setting, &mut state)
+}
+
+/// the status of a persistent effect, see `status`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct PersistentStatus {
+    /// the number of sources of the effect, emitters included
+    pub position_count: usize,
+    /// the volume of the effect before the effect bus:
+    /// `effect_volume * blend_gain * sum(distance(position,listener_position))`
+    pub current_volume: f32,
+    /// whether the effect is muted
+    pub muted: bool,
+    /// whether the effect is paused by one of its groups or by the effect bus
+    pub paused: bool,
+}
 
-        final_volume.store((volume * 10_000f32) as usize, Relaxed);
+/// return the status of the effect
+pub fn status(effect: PersistentId) -> PersistentStatus {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let emitters = state.effect.emitters.iter()
+        .filter_map(|emitter| emitter.as_ref())
+        .filter(|emitter| emitter.persistent.contains(&effect.0))
+        .count();
+    let volume = &state.effect.persistent_volumes[effect.0];
+    let gain = |factor: &AtomicUsize| factor.load(Relaxed) as f32 / 10_000.;
+    PersistentStatus {
+        position_count: state.effect.persistent_positions[effect.0].len() + emitters,
+        current_volume: gain(&state.effect.persistent_final_volumes[effect.0])
+            * gain(&state.effect.persistent_blend_gains[effect.0])
+            * gain(&volume.final_volume),
+        muted: volume.muted,
+        paused: volume.paused.load(Relaxed) || state.effect.bus.is_paused(),
     }
 }
@@ -8,37 +8,545 @@
 //!
 //! this can lead to weird effects for not so short sound effects and with moving source
 
-use rodio::Sink;
 use rodio::Source;
+use rodio::buffer::SamplesBuffer;
+use rodio::source::Empty;
+use rodio::source::UniformSourceIterator;
 
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::super::backend::AudioSink;
+use super::ShortId;
+use super::EffectSetting;
+use super::Route;
+use super::Short;
 use super::super::RAW_STATE;
+use super::super::bus;
+use super::super::bus::{AudioFilter, Equalizer};
 use super::super::source;
+use super::super::event;
+use super::super::event::AudioEvent;
+use super::super::InitError;
+use super::super::spatial;
+use super::super::spatial::RoomId;
 
 /// play the sound effect at the volume: `global_volume * effect_volume *
 /// distance(position, listener_position)`
-pub fn play(effect: usize, pos: [f32;3]) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    let distance_volume = state.effect.distance_model.distance(pos,state.effect.listener);
+///
+/// the effect is tracked until it ends, the handle allows to stop it
+pub fn play(effect: ShortId, pos: [f32;3]) -> ShortHandle {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    ShortHandle {
+        id: play_inner(effect.0, pos, 1, Duration::new(0, 0), 1., &mut state),
+    }
+}
+
+/// a short effect played with `play`
+///
+/// the handle does nothing once the effect has ended or has been stopped, or if
+/// it hasn't been played because it is out of range or over `max_short_effects`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct ShortHandle {
+    id: Option<usize>,
+}
+
+impl ShortHandle {
+    /// stop the effect, its caption end event is emitted if its caption started
+    pub fn stop(&self) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        let id = self.id;
+        if let Some(i) = state.effect.short_sinks.iter().position(|short| Some(short.id) == id) {
+            let short = state.effect.short_sinks.remove(i);
+            if let (true, Some(caption)) = (short.caption_started, short.caption) {
+                event::emit(AudioEvent::CaptionEnd(caption), &mut state);
+            }
+        }
+    }
+
+    /// return whether the effect is playing or waiting for its delay
+    pub fn is_playing(&self) -> bool {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        state.effect.short_sinks.iter()
+            .any(|short| Some(short.id) == self.id && !short.slot.ended.load(Relaxed))
+    }
+}
+
+/// play the sound effect like `play` without keeping track of it, it is the
+/// cheapest way to play an effect: it is added to the sink shared by the
+/// detached effects
+///
+/// the effect has no caption, isn't counted by `stats` and `playing` and can't
+/// be stopped, its volume, pitch and pause still apply
+///
+/// it isn't limited by `max_short_effects` either, the number of detached effects
+/// heard at once is up to the caller
+///
+/// the detached effects are stopped when the audio player is reset
+pub fn play_detached(effect: ShortId, pos: [f32;3]) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let effect = effect.0;
+    if !state.effect.in_range(state.effect.short_max_distances[effect], pos) {
+        return;
+    }
+    // the null backend drops the pool of the detached effects
+    let detached = match state.effect.detached.upgrade() {
+        Some(detached) => detached,
+        None => return,
+    };
+    let distance_volume = state.effect.distance_gain(pos,state.effect.listener);
     if distance_volume > 0. {
-        let source = state.effect.short_sources[effect].clone().amplify(distance_volume);
-        let source = source::amplify_ctrl(source, state.effect.final_volume.clone());
-        let source = source::play_pause_ctrl(source, state.effect.pause.clone());
+        let cutoff = super::air_absorption_cutoff(state.effect.air_absorption, super::length(pos,state.effect.listener));
+        let source = match state.effect.short_sources[effect].open() {
+            Ok(source) => source.amplify(state.effect.ceil_gain(distance_volume, &state.effect.short_volumes[effect])),
+            Err(e) => {
+                warn!("baal: short effect {} cannot be played: {}", effect, e);
+                return;
+            },
+        };
+        // the position doesn't change so the filter needs a control only with air absorption
+        let source = if cutoff == ::std::usize::MAX {
+            source::Either::Left(source)
+        } else {
+            source::Either::Right(source::low_pass_ctrl(source, Arc::new(AtomicUsize::new(cutoff))))
+        };
+        let source = source::speed_ctrl(source, state.effect.short_pitches[effect].clone());
+        let source = source::amplify_ctrl(source, state.effect.short_volumes[effect].final_volume.clone());
+        let source = source::play_pause_ctrl(source, state.effect.short_volumes[effect].paused.clone());
+        let source = source::tap(source, state.effect.short_sends[effect].clone());
+        let source = state.effect.route_bus(state.effect.short_routes[effect]).apply(source);
+        let source = UniformSourceIterator::<_, f32>::new(source, super::DETACHED_CHANNELS, super::DETACHED_SAMPLES_RATE);
+        detached.lock().unwrap_or_else(|e| e.into_inner()).push(Box::new(source));
+    }
+}
+
+/// a filter applied to a single short effect, see `PlayParams::filter`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum FilterPreset {
+    /// heard through a wall
+    Muffled,
+    /// heard through a radio or a telephone: no low and few high frequencies
+    Radio,
+    /// heard under water
+    Underwater,
+}
+
+impl FilterPreset {
+    /// the cutoff frequency in Hz of the low pass filter of the preset
+    fn low_pass(&self) -> Option<f32> {
+        match *self {
+            FilterPreset::Muffled => Some(800.),
+            FilterPreset::Radio => None,
+            FilterPreset::Underwater => Some(400.),
+        }
+    }
+
+    /// the filters inserted for the preset
+    fn filters(&self) -> Vec<Box<AudioFilter>> {
+        let eq = Equalizer::new();
+        match *self {
+            FilterPreset::Muffled => return vec!(),
+            FilterPreset::Radio => {
+                eq.set_low_db(-30.);
+                eq.set_mid_db(3.);
+                eq.set_high_db(-12.);
+            }
+            FilterPreset::Underwater => {
+                eq.set_low_db(3.);
+                eq.set_mid_db(-6.);
+            }
+        }
+        vec!(Box::new(eq))
+    }
+}
+
+/// the part of the file played by `play_with`, the room and the filter it is played with
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct PlayParams {
+    /// the position in the file the effect starts at
+    pub start: Duration,
+    /// the duration played, until the end of the file if none
+    pub take: Option<Duration>,
+    /// the room of the effect, it is occluded by the portals to the listener room,
+    /// see `baal::spatial`
+    pub room: Option<RoomId>,
+    /// the filter applied to this effect only
+    pub filter: Option<FilterPreset>,
+}
+
+impl Default for PlayParams {
+    fn default() -> PlayParams {
+        PlayParams {
+            start: Duration::new(0, 0),
+            take: None,
+            room: None,
+            filter: None,
+        }
+    }
+}
+
+/// play the part of the sound effect like `play`
+///
+/// it allows a single long recording to provide several effects
+pub fn play_with(effect: ShortId, pos: [f32;3], params: PlayParams) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    if !state.effect.in_range(state.effect.short_max_distances[effect.0], pos) {
+        return;
+    }
+    play_sequence_inner(::std::iter::once((effect.0, Duration::new(0, 0))), pos, 1., params, &mut state);
+}
+
+/// play the sound effect when the audio clock reaches `time`
+///
+/// if the time is already passed the effect is played immediately, see `baal::clock`
+pub fn play_at(effect: ShortId, pos: [f32;3], time: Duration) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let delay = time.checked_sub(super::super::clock_inner(&state)).unwrap_or(Duration::new(0, 0));
+    play_inner(effect.0, pos, 1, delay, 1., &mut state);
+}
+
+/// play the sound effect `n` times back-to-back
+///
+/// the volume is computed once like for `play`
+pub fn play_repeated(effect: ShortId, pos: [f32;3], n: usize) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    play_inner(effect.0, pos, n, Duration::new(0, 0), 1., &mut state);
+}
+
+/// play the sound effects one after the other in the same sink
+///
+/// each element is the index of the effect and the silence played before it,
+/// the relative timing of the effects is exact, the volume is computed once
+/// like for `play`
+pub fn play_sequence(effects: &[(ShortId, Duration)], pos: [f32;3]) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    // played if one of them is within its maximum distance
+    if !effects.iter().any(|&(effect, _)| state.effect.in_range(state.effect.short_max_distances[effect.0], pos)) {
+        return;
+    }
+    let effects = effects.iter().map(|&(effect, gap)| (effect.0, gap));
+    play_sequence_inner(effects, pos, 1., PlayParams::default(), &mut state);
+}
+
+/// play many sound effects at once, the state is locked only once
+///
+/// each element is the index of the effect and its position
+pub fn play_many(effects: &[(ShortId, [f32;3])]) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    for &(effect, pos) in effects {
+        play_inner(effect.0, pos, 1, Duration::new(0, 0), 1., &mut state);
+    }
+}
+
+/// play raw samples at the volume: `global_volume * effect_volume *
+/// distance(position, listener_position)`
+///
+/// samples are interleaved by channel, it allows to play procedurally generated
+/// or received audio, the effect volumes, pitches and groups don't apply to them
+///
+/// nothing is played if the number of channels or the samples rate is zero
+pub fn play_pcm(samples: Vec<f32>, channels: u16, samples_rate: u32, pos: [f32;3]) {
+    if channels == 0 || samples_rate == 0 {
+        warn!("baal: raw samples with {} channels at {} Hz cannot be played", channels, samples_rate);
+        return;
+    }
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let distance_volume = state.effect.distance_gain(pos,state.effect.listener);
+    let cutoff = super::air_absorption_cutoff(state.effect.air_absorption, super::length(pos,state.effect.listener));
+    if distance_volume > 0. && !samples.is_empty() && super::reserve_short(&mut state) {
+        let slot = super::short_slot(cutoff, 1., &mut state);
+
+        let source = SamplesBuffer::new(channels, samples_rate, samples).amplify(distance_volume.min(state.effect.max_gain));
+        let source = source::low_pass_ctrl(source, slot.cutoff.clone());
+        let source = source::counter(source, slot.played.clone());
+        let source = source::stop_ctrl(source, slot.stop.clone(), state.timing());
+        let source = state.effect.bus.apply(source);
+        slot.sink.append(source::end_signal(source, slot.ended.clone()));
+
+        state.effect.short_sinks.push(Short {
+            id: super::NEXT_SHORT_ID.fetch_add(1, Relaxed),
+            slot: slot,
+            effect: None,
+            route: Route::Effect,
+            rate: samples_rate as u64 * channels as u64,
+            position: pos,
+            gain: distance_volume,
+            caption: None,
+            caption_started: false,
+        });
+    }
+}
+
+#[inline]
+fn play_inner(effect: usize, pos: [f32;3], repeat: usize, delay: Duration, speed: f32, state: &mut super::super::State) -> Option<usize> {
+    if !state.effect.in_range(state.effect.short_max_distances[effect], pos) {
+        return None;
+    }
+    let effects = (0..repeat).map(|i| (effect, if i == 0 { delay } else { Duration::new(0, 0) }));
+    play_sequence_inner(effects, pos, speed, PlayParams::default(), state)
+}
+
+/// play the effects one after the other in one sink, each one after its delay
+///
+/// the first effect gives its caption to the whole sequence, the parameters
+/// apply to each effect, return the id of the short played if any
+fn play_sequence_inner<I>(effects: I, pos: [f32;3], speed: f32, params: PlayParams, state: &mut super::super::State) -> Option<usize>
+    where I: ExactSizeIterator<Item = (usize, Duration)>
+{
+    let occlusion = spatial::occlusion_inner(params.room, state);
+    let distance_volume = state.effect.distance_gain(pos,state.effect.listener) * occlusion.gain;
+    let cutoff = super::air_absorption_cutoff(state.effect.air_absorption, super::length(pos,state.effect.listener));
+    let cutoff = super::occluded_cutoff(cutoff, occlusion);
+    let cutoff = match params.filter.and_then(|filter| filter.low_pass()) {
+        Some(low_pass) => cutoff.min(low_pass as usize),
+        None => cutoff,
+    };
+    let len = effects.len();
+    if distance_volume > 0. && len > 0 && super::reserve_short(state) {
+        // controls are shared by the effects of the sequence
+        let slot = super::short_slot(cutoff, speed, state);
+        let mut first = None;
+        for (i, (effect, delay)) in effects.enumerate() {
+            first = first.or(Some(effect));
+            let source = match state.effect.short_sources[effect].open() {
+                Ok(source) => source,
+                Err(e) => {
+                    warn!("baal: short effect {} cannot be played: {}", effect, e);
+                    if i + 1 == len {
+                        // the end of the sequence is still signaled
+                        slot.sink.append(source::end_signal(Empty::<f32>::new(), slot.ended.clone()));
+                    }
+                    continue;
+                },
+            };
+            let gain = state.effect.ceil_gain(distance_volume, &state.effect.short_volumes[effect]);
+            let source = source::trim(source, params.start, params.take).amplify(gain);
+            let source = source::low_pass_ctrl(source, slot.cutoff.clone());
+            let source = source::insert(source, params.filter.map_or(vec!(), |filter| filter.filters()));
+            let source = source::speed_ctrl(source, slot.speed.clone());
+            let source = source::speed_ctrl(source, state.effect.short_pitches[effect].clone());
+            let source = source::amplify_ctrl(source, state.effect.short_volumes[effect].final_volume.clone());
+            let source = source::play_pause_ctrl(source, state.effect.short_volumes[effect].paused.clone());
+            let source = source::tap(source, state.effect.short_sends[effect].clone());
+            // counted inside the wait so the caption starts with the sound
+            let source = source::counter(source, slot.played.clone());
+            // the delay of the first effect is measured from the play, the others
+            // from the end of the previous one
+            let timing = if i == 0 { state.timing().starting_now() } else { state.timing() };
+            let source = source::wait(source, delay, timing);
+            let source = source::stop_ctrl(source, slot.stop.clone(), state.timing());
+            let source = state.effect.route_bus(state.effect.short_routes[effect]).apply(source);
+            // only the end of the last one matters
+            if i + 1 == len {
+                slot.sink.append(source::end_signal(source, slot.ended.clone()));
+            } else {
+                slot.sink.append(source);
+            }
+        }
+
+        // the iterator is not empty
+        let effect = first.unwrap();
+        let source = &state.effect.short_sources[effect];
+        let rate = source.samples_rate() as u64 * source.channels() as u64;
+        let caption = state.effect.short_captions[effect].clone();
+        let id = super::NEXT_SHORT_ID.fetch_add(1, Relaxed);
+        state.effect.short_sinks.push(Short {
+            id: id,
+            slot: slot,
+            effect: Some(effect),
+            route: state.effect.short_routes[effect],
+            rate: rate,
+            position: pos,
+            gain: distance_volume,
+            caption: caption,
+            caption_started: false,
+        });
+        Some(id)
+    } else {
+        None
+    }
+}
 
-        let sink = Sink::new(&state.endpoint);
-        sink.append(source);
+#[doc(hidden)]
+pub fn play_scheduled(effect: usize, pos: [f32;3], repeat: usize, delay: Duration, state: &mut super::super::State) {
+    play_inner(effect, pos, repeat, delay, 1., state);
+}
 
-        state.effect.short_sinks.push(sink);
+#[doc(hidden)]
+pub fn play_from_emitter(effect: usize, pos: [f32;3], speed: f32, room: Option<RoomId>, state: &mut super::super::State) {
+    if !state.effect.in_range(state.effect.short_max_distances[effect], pos) {
+        return;
     }
+    let params = PlayParams {
+        room: room,
+        ..PlayParams::default()
+    };
+    play_sequence_inner(::std::iter::once((effect, Duration::new(0, 0))), pos, speed, params, state);
+}
+
+/// return the number of short effects of the setting
+pub fn count() -> usize {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_sources.len()
 }
 
 /// play the sound effect at the position of the listener
 /// i.e. volume is `global_volume * effect_volume`
-pub fn play_on_listener(effect: usize) {
+pub fn play_on_listener(effect: ShortId) {
     play(effect,super::listener());
 }
 
+/// set the pitch of the sound effect, it affects current and future sounds effects
+///
+/// the pitch is a factor of the playback speed, 1 is the normal speed
+pub fn set_pitch(effect: ShortId, pitch: f32) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    set_pitch_inner(effect.0, pitch, &state);
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn set_pitch_inner(effect: usize, pitch: f32, state: &super::super::State) {
+    state.effect.short_pitches[effect].store((pitch * 10_000.) as usize, Relaxed);
+}
+
+/// return the pitch of the sound effect
+pub fn pitch(effect: ShortId) -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_pitches[effect.0].load(Relaxed) as f32 / 10_000.
+}
+
+/// a short effect played, see `playing`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct PlayingInfo {
+    /// the id of the effect, none for raw samples played with `play_pcm`
+    pub effect: Option<ShortId>,
+    /// the position the effect is played at
+    pub position: [f32;3],
+    /// the time elapsed since the effect started to be heard, zero while it is delayed
+    pub elapsed: Duration,
+}
+
+/// return the short effects played or waiting to be played
+///
+/// effects played by `effect::ui` are at the position of the listener
+pub fn playing() -> Vec<PlayingInfo> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_sinks.iter()
+        .map(|short| {
+            let rate = short.rate;
+            let samples = short.slot.played.load(Relaxed) as u64;
+            PlayingInfo {
+                effect: short.effect.map(ShortId),
+                position: short.position,
+                elapsed: Duration::new(samples / rate, ((samples % rate) * 1_000_000_000 / rate) as u32),
+            }
+        })
+        .collect()
+}
+
 /// stop all short sound effects
+///
+/// caption end events are emitted for the captions started
 pub fn stop_all() {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.effect.short_sinks.clear();
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    stop_all_matching_inner(|_| true, &mut state);
+}
+
+/// stop all short sound effects for which the predicate on their effect id is true
+///
+/// caption end events are emitted for the captions started, raw samples are not stopped
+pub fn stop_all_matching<F>(mut predicate: F) where F: FnMut(ShortId) -> bool {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    stop_all_matching_inner(|effect| effect.map_or(false, |effect| predicate(ShortId(effect))), &mut state);
+}
+
+#[doc(hidden)]
+pub fn stop_all_matching_inner<F>(mut predicate: F, state: &mut super::super::State) where F: FnMut(Option<usize>) -> bool {
+    // the shorts are removed in place so that the vector keeps its allocation
+    let mut i = 0;
+    while i < state.effect.short_sinks.len() {
+        if predicate(state.effect.short_sinks[i].effect) {
+            let short = state.effect.short_sinks.remove(i);
+            if let (true, Some(caption)) = (short.caption_started, short.caption) {
+                event::emit(AudioEvent::CaptionEnd(caption), state);
+            }
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// fade out all short sound effects during the duration
+///
+/// they are removed and their caption end events are emitted at the end of the
+/// fade by `baal::update`, the effects routed to the `ui` bus are not stopped
+pub fn stop_all_with_fade(duration: Duration) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let millis = duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1_000_000;
+    // the control is in milliseconds so that it fits in an usize on 32 bits targets
+    let millis = millis.min(source::PLAYING as u64 - 1) as usize;
+    for short in state.effect.short_sinks.iter().filter(|short| short.route != Route::Ui) {
+        short.slot.stop.store(millis, Relaxed);
+    }
+}
+
+/// set the volume of the effect in [0,1], it affects current and future sounds of the effect
+pub fn set_effect_volume(effect: ShortId, v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let volume = &mut state.effect.short_volumes[effect.0];
+    volume.volume = bus::sanitize_volume(v);
+    volume.update();
+}
+
+/// return the volume of the effect
+pub fn effect_volume(effect: ShortId) -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_volumes[effect.0].volume
+}
+
+/// mute or unmute the effect, it affects current and future sounds of the effect
+pub fn mute_effect(effect: ShortId, muted: bool) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let volume = &mut state.effect.short_volumes[effect.0];
+    volume.muted = muted;
+    volume.update();
+}
+
+/// return whether the effect is muted
+pub fn is_effect_muted(effect: ShortId) -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_volumes[effect.0].muted
+}
+
+/// return the duration of the effect if known
+pub fn duration(effect: ShortId) -> Option<Duration> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_durations[effect.0]
+}
+
+/// return the distance from the listener beyond which the effect isn't played,
+/// see `EffectSetting::max_distance`
+pub fn max_distance(effect: ShortId) -> Option<f32> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.effect.short_max_distances[effect.0]
+}
+
+/// reload the effect from the disk, the sounds already playing are not affected
+///
+/// it is meant to iterate on the file while the game is running
+pub fn reload(effect: ShortId) -> Result<(),InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    super::reload_short_inner(effect.0, &mut state)
+}
+
+/// load a new short effect from the effect directory and return its id
+///
+/// it is meant for prototypes and editors that start the audio player before
+/// the list of effects is known, see `baal::init_default`
+pub fn register(setting: EffectSetting) -> Result<ShortId,InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    super::register_short_inner(setting, &mut state)
 }
@@ -0,0 +1,66 @@
+//! this module allow to play user interface sound effects like menu clicks
+//!
+//! ```lua
+//! volume = global_volume * ui_volume
+//! ```
+//!
+//! they use the short effects of the setting but are routed through the `ui` bus,
+//! they ignore the listener and are not paused by `effect::pause`
+//!
+//! the volume of the `ui` bus is initialized to the effect volume, it can be
+//! changed with `bus::set_volume`
+
+use rodio::Source;
+
+use std::sync::atomic::Ordering::Relaxed;
+
+use super::super::backend::AudioSink;
+use super::ShortId;
+use super::Route;
+use super::Short;
+use super::super::RAW_STATE;
+use super::super::source;
+
+/// play the sound effect at the volume: `global_volume * ui_volume`
+pub fn play(effect: ShortId) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let source = match state.effect.short_sources[effect.0].open() {
+        Ok(source) => source,
+        Err(e) => {
+            warn!("baal: short effect {} cannot be played: {}", effect.0, e);
+            return;
+        },
+    };
+    if !super::reserve_short(&mut state) {
+        return;
+    }
+    let listener = state.effect.listener;
+
+    // the ui effects aren't filtered
+    let slot = super::short_slot(::std::usize::MAX, 1., &mut state);
+
+    let rate = state.effect.short_sources[effect.0].samples_rate() as u64
+        * state.effect.short_sources[effect.0].channels() as u64;
+    let source = source.amplify(state.effect.ceil_gain(1., &state.effect.short_volumes[effect.0]));
+    let source = source::speed_ctrl(source, state.effect.short_pitches[effect.0].clone());
+    let source = source::amplify_ctrl(source, state.effect.short_volumes[effect.0].final_volume.clone());
+    let source = source::play_pause_ctrl(source, state.effect.short_volumes[effect.0].paused.clone());
+    let source = source::counter(source, slot.played.clone());
+    let source = source::stop_ctrl(source, slot.stop.clone(), state.timing());
+    let source = state.effect.ui_bus.apply(source);
+    let source = source::end_signal(source, slot.ended.clone());
+    slot.sink.append(source);
+
+    let caption = state.effect.short_captions[effect.0].clone();
+    state.effect.short_sinks.push(Short {
+        id: super::NEXT_SHORT_ID.fetch_add(1, Relaxed),
+        slot: slot,
+        effect: Some(effect.0),
+        route: Route::Ui,
+        rate: rate,
+        position: listener,
+        gain: 1.,
+        caption: caption,
+        caption_started: false,
+    });
+}
@@ -0,0 +1,249 @@
+//! this module allow to post events defined in the setting and to receive
+//! audio events emitted by baal
+//!
+//! an event is a list of actions executed in order, a `Delay` action delays all
+//! the following actions on the audio clock
+//!
+//! short effects are played at the position given to `post_event`
+//!
+//! audio events are given to the callback set with `set_callback` during
+//! `baal::update` calls, the state is not locked while the callback is executed
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand::XorShiftRng;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::InitError;
+use super::RAW_STATE;
+use super::Setting;
+use super::bus;
+use super::effect;
+
+/// an action of an event
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum EventAction {
+    /// play the short effect
+    Play(usize),
+    /// play one of the short effects chosen randomly
+    PlayRandom(Vec<usize>),
+    /// play the short effect n times back-to-back
+    Loop(usize, usize),
+    /// set the volume of the bus
+    SetBusVolume(String, f32),
+    /// multiply the volume of the bus by a factor during a duration
+    Duck(String, f32, Duration),
+    /// delay the following actions
+    Delay(Duration),
+}
+
+#[derive(Clone,Debug)]
+enum Pending {
+    SetBusVolume(String, f32),
+    DuckStart(String, f32),
+    DuckEnd(String, f32),
+}
+
+/// an event emitted by baal
+#[derive(Clone,Debug,PartialEq)]
+pub enum AudioEvent {
+    /// the voice line with this id started
+    VoiceLineStart(usize),
+    /// the voice line with this id ended
+    VoiceLineEnd(usize),
+    /// the voice line with this id has been stopped or removed from the queue
+    VoiceLineInterrupted(usize),
+    /// the sound with this caption started to be heard, the caption is shared
+    /// with the setting so that emitting it doesn't allocate
+    CaptionStart(Arc<String>),
+    /// the sound with this caption ended or has been stopped
+    CaptionEnd(Arc<String>),
+    /// the effect or music with this path has been reloaded, see `hot_reload`
+    AssetReloaded(PathBuf),
+    /// the effect or music with this path failed to reload, see `hot_reload`
+    AssetReloadFailed(PathBuf, String),
+    /// this number of samples per second of the mix were beyond full scale during
+    /// the last second, it is emitted by `update` once per second while the mix
+    /// clips, only with the render backend, see `Stats::clipped_per_second`
+    Clipping(usize),
+    /// a sound panicked, like a decoder or a user filter, it has been dropped and
+    /// the other sounds keep playing, the message of the panic is given
+    VoiceError(String),
+    /// the audio device consumed no samples during this duration, see `watchdog`
+    OutputStalled(Duration),
+    /// the download of the music streamed from an url failed, see `music::play_url`
+    MusicStreamError(String),
+}
+
+/// a callback receiving audio events
+pub type Callback = Box<FnMut(AudioEvent) + Send + Sync>;
+
+#[doc(hidden)]
+pub struct Events {
+    definitions: HashMap<String, Vec<EventAction>>,
+    pending: Vec<(Duration, Pending)>,
+    rng: XorShiftRng,
+    emitted: Vec<AudioEvent>,
+    callback: Option<Callback>,
+}
+
+impl Events {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<Events,InitError> {
+        for action in setting.events.values().flat_map(|actions| actions.iter()) {
+            match *action {
+                EventAction::SetBusVolume(ref name, _) | EventAction::Duck(ref name, _, _) => {
+                    if !bus::exists(name) {
+                        return Err(InitError::UnknownBus(name.clone()));
+                    }
+                },
+                EventAction::Play(effect) | EventAction::Loop(effect, _) => {
+                    if effect >= setting.short_effects.len() {
+                        return Err(InitError::UnknownShortEffect(effect));
+                    }
+                },
+                EventAction::PlayRandom(ref effects) => {
+                    if let Some(&effect) = effects.iter().find(|&&effect| effect >= setting.short_effects.len()) {
+                        return Err(InitError::UnknownShortEffect(effect));
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        Ok(Events {
+            definitions: setting.events.clone(),
+            pending: vec!(),
+            rng: match setting.deterministic_seed {
+                Some(seed) => XorShiftRng::from_seed([seed, 0x193a6754, 0xa8a7d469, 0x97830e05]),
+                None => ::rand::weak_rng(),
+            },
+            emitted: vec!(),
+            callback: None,
+        })
+    }
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
+        let emitted = ::std::mem::replace(&mut self.emitted, vec!());
+        let callback = self.callback.take();
+        *self = try!(Events::init(setting));
+        self.emitted = emitted;
+        self.callback = callback;
+        Ok(())
+    }
+}
+
+/// set the callback receiving audio events
+///
+/// events emitted while no callback is set are discarded
+pub fn set_callback<F>(callback: F) where F: FnMut(AudioEvent) + Send + Sync + 'static {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.events.callback = Some(Box::new(callback));
+}
+
+/// remove the callback receiving audio events
+pub fn remove_callback() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.events.callback = None;
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn emit(event: AudioEvent, state: &mut super::State) {
+    if state.events.callback.is_some() {
+        state.events.emitted.push(event);
+    }
+}
+
+/// take the events and the callback out of the state so they can be dispatched
+/// without the lock
+#[doc(hidden)]
+pub fn take_emitted(state: &mut super::State) -> (Vec<AudioEvent>, Option<Callback>) {
+    let emitted = ::std::mem::replace(&mut state.events.emitted, vec!());
+    (emitted, state.events.callback.take())
+}
+
+#[doc(hidden)]
+pub fn dispatch(emitted: Vec<AudioEvent>, callback: Option<Callback>) {
+    if let Some(mut callback) = callback {
+        for event in emitted {
+            callback(event);
+        }
+
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if state.events.callback.is_none() {
+            state.events.callback = Some(callback);
+        }
+    }
+}
+
+/// execute the actions of the event
+///
+/// panic if the event doesn't exist
+pub fn post_event(name: &str, pos: [f32;3]) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let actions = state.events.definitions[name].clone();
+    let now = super::clock_inner(&state);
+    let mut delay = Duration::new(0, 0);
+
+    for action in actions {
+        match action {
+            EventAction::Play(effect) => {
+                effect::short::play_scheduled(effect, pos, 1, delay, &mut state);
+            }
+            EventAction::PlayRandom(effects) => {
+                if !effects.is_empty() {
+                    let effect = effects[state.events.rng.gen_range(0, effects.len())];
+                    effect::short::play_scheduled(effect, pos, 1, delay, &mut state);
+                }
+            }
+            EventAction::Loop(effect, n) => {
+                effect::short::play_scheduled(effect, pos, n, delay, &mut state);
+            }
+            EventAction::SetBusVolume(name, volume) => {
+                state.events.pending.push((now + delay, Pending::SetBusVolume(name, volume)));
+            }
+            EventAction::Duck(name, factor, duration) => {
+                state.events.pending.push((now + delay, Pending::DuckStart(name.clone(), factor)));
+                state.events.pending.push((now + delay + duration, Pending::DuckEnd(name, factor)));
+            }
+            EventAction::Delay(duration) => {
+                delay += duration;
+            }
+        }
+    }
+
+    update(&mut state);
+}
+
+/// execute the pending actions whose time is reached
+#[doc(hidden)]
+pub fn update(state: &mut super::State) {
+    let now = super::clock_inner(state);
+    let global_volume = state.global_volume;
+
+    let (mut ready, pending): (Vec<_>, Vec<_>) = state.events.pending.drain(..)
+        .partition(|&(time, _)| time <= now);
+    state.events.pending = pending;
+    ready.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (_, action) in ready {
+        match action {
+            Pending::SetBusVolume(name, volume) => {
+                bus::get_mut(&name, state).unwrap().set_volume(volume, global_volume);
+            }
+            Pending::DuckStart(name, factor) => {
+                bus::get_mut(&name, state).unwrap().start_duck(factor, global_volume);
+            }
+            Pending::DuckEnd(name, factor) => {
+                bus::get_mut(&name, state).unwrap().end_duck(factor, global_volume);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! this module allow to forward the focus and lifecycle events of the application
+//!
+//! windowing events are forwarded with `baal::on_focus_lost` and
+//! `baal::on_focus_gained`, what happens while the focus is lost is set by
+//! `Setting::focus_policy`
+//!
+//! audio session interruptions of mobile platforms, like phone calls, are
+//! forwarded with `baal::handle_interruption_begin` and
+//! `baal::handle_interruption_end`, all the buses are paused during them,
+//! route changes are handled by `baal::handle_route_change`
+//!
+//! all the buses are affected including the ui bus, their own pause and mute
+//! states are kept
+
+use super::RAW_STATE;
+use super::Setting;
+use super::bus;
+
+/// what happens to the audio while the application doesn't have the focus
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum FocusPolicy {
+    /// the audio continues
+    Continue,
+    /// all the buses are paused, sounds resume where they were
+    Pause,
+    /// all the buses are muted, sounds continue silently
+    Mute,
+}
+
+impl Default for FocusPolicy {
+    fn default() -> FocusPolicy {
+        FocusPolicy::Continue
+    }
+}
+
+#[doc(hidden)]
+pub struct Focus {
+    policy: FocusPolicy,
+    lost: bool,
+    interrupted: bool,
+}
+
+impl Focus {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Focus {
+        Focus {
+            policy: setting.focus_policy,
+            lost: false,
+            interrupted: false,
+        }
+    }
+
+    /// the focus and interruption are kept on reset
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) {
+        self.policy = setting.focus_policy;
+    }
+}
+
+/// apply the focus policy to the buses
+#[doc(hidden)]
+pub fn apply(state: &mut super::State) {
+    let (suspend, mute) = match (state.focus.lost, state.focus.policy) {
+        (false, _) | (true, FocusPolicy::Continue) => (false, false),
+        (true, FocusPolicy::Pause) => (true, false),
+        (true, FocusPolicy::Mute) => (false, true),
+    };
+    let suspend = suspend || state.focus.interrupted;
+    let global_volume = state.global_volume;
+    for name in &bus::BUSES {
+        let bus = bus::get_mut(name, state).unwrap();
+        bus.set_suspended(suspend);
+        bus.set_focus_muted(mute, global_volume);
+    }
+}
+
+#[doc(hidden)]
+pub fn set_focus_inner(focus: bool, state: &mut super::State) {
+    if state.focus.lost != focus {
+        return;
+    }
+    state.focus.lost = !focus;
+    debug!("baal: focus {}", if focus { "gained" } else { "lost" });
+    apply(state);
+}
+
+/// the application lost the focus, apply the focus policy
+pub fn on_focus_lost() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    set_focus_inner(false, &mut *state);
+}
+
+/// the application gained the focus, the audio is restored
+pub fn on_focus_gained() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    set_focus_inner(true, &mut *state);
+}
+
+/// return whether the application has the focus, it is assumed at init
+pub fn has_focus() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    !state.focus.lost
+}
+
+#[doc(hidden)]
+pub fn set_interrupted_inner(interrupted: bool, state: &mut super::State) {
+    if state.focus.interrupted == interrupted {
+        return;
+    }
+    state.focus.interrupted = interrupted;
+    debug!("baal: interruption {}", if interrupted { "began" } else { "ended" });
+    apply(state);
+}
+
+/// the audio session is interrupted, all the buses are paused
+pub fn handle_interruption_begin() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    set_interrupted_inner(true, &mut *state);
+}
+
+/// the interruption of the audio session ended, the buses resume
+///
+/// if the output changed during the interruption `baal::handle_route_change`
+/// must be called too
+pub fn handle_interruption_end() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    set_interrupted_inner(false, &mut *state);
+}
+
+/// return whether the audio session is interrupted
+pub fn is_interrupted() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.focus.interrupted
+}
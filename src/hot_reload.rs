@@ -0,0 +1,126 @@
+//! this module reload the effects and musics whose file changed on the disk
+//!
+//! it is available with the `hot-reload` feature and is meant for development:
+//! effect and music directories are watched and changes are handled during
+//! `baal::update` calls, `AssetReloaded` or `AssetReloadFailed` audio events are emitted
+//!
+//! only the files declared in the setting are reloaded, use `baal::reset` to
+//! take new files into account
+
+use notify;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::Setting;
+use super::effect;
+use super::event;
+use super::event::AudioEvent;
+#[cfg(feature = "music")]
+use super::music;
+
+/// changes of a file are reported once it hasn't been modified for this duration
+const DEBOUNCE_MS: u64 = 200;
+
+#[derive(Clone,Copy,Debug)]
+enum Asset {
+    Short(usize),
+    #[cfg(feature = "persistent")]
+    Persistent(usize),
+    #[cfg(feature = "music")]
+    Music(usize),
+}
+
+#[doc(hidden)]
+pub struct HotReload {
+    _watcher: Option<RecommendedWatcher>,
+    receiver: Mutex<Receiver<DebouncedEvent>>,
+    assets: Vec<(PathBuf, PathBuf, Asset)>,
+}
+
+impl HotReload {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> HotReload {
+        let (sender, receiver) = channel();
+        let watcher = notify::watcher(sender, Duration::from_millis(DEBOUNCE_MS))
+            .and_then(|mut watcher| {
+                try!(watcher.watch(&setting.effect_dir, RecursiveMode::Recursive));
+                try!(watcher.watch(&setting.music_dir, RecursiveMode::Recursive));
+                Ok(watcher)
+            })
+            .map_err(|e| warn!("baal: hot reload disabled, failed to watch directories: {:?}", e))
+            .ok();
+
+        let mut assets = vec!();
+        for (i, effect) in setting.short_effects.iter().enumerate() {
+            assets.push((setting.effect_dir.join(&effect.path), effect.path.clone(), Asset::Short(i)));
+        }
+        #[cfg(feature = "persistent")]
+        for (i, effect) in setting.persistent_effects.iter().enumerate() {
+            assets.push((setting.effect_dir.join(&effect.path), effect.path.clone(), Asset::Persistent(i)));
+        }
+        #[cfg(feature = "music")]
+        for (i, music) in setting.musics.iter().enumerate() {
+            assets.push((setting.music_dir.join(&music.path), music.path.clone(), Asset::Music(i)));
+        }
+        // events are reported with absolute paths
+        let assets = assets.into_iter()
+            .filter_map(|(path, source, asset)| path.canonicalize().ok().map(|path| (path, source, asset)))
+            .collect();
+
+        HotReload {
+            _watcher: watcher,
+            receiver: Mutex::new(receiver),
+            assets: assets,
+        }
+    }
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn update(state: &mut super::State) {
+    let mut changed = vec!();
+    {
+        let receiver = state.hot_reload.receiver.lock().unwrap();
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                DebouncedEvent::Write(path)
+                | DebouncedEvent::Create(path)
+                | DebouncedEvent::Rename(_, path) => changed.push(path),
+                _ => (),
+            }
+        }
+    }
+
+    for path in changed {
+        let path = match path.canonicalize() {
+            Ok(path) => path,
+            Err(_) => continue,
+        };
+        let assets = state.hot_reload.assets.iter()
+            .filter(|&&(ref asset_path, _, _)| *asset_path == path)
+            .map(|&(_, ref source, asset)| (source.clone(), asset))
+            .collect::<Vec<_>>();
+
+        for (source, asset) in assets {
+            let result = match asset {
+                Asset::Short(i) => effect::reload_short_inner(i, state),
+                #[cfg(feature = "persistent")]
+                Asset::Persistent(i) => effect::reload_persistent_inner(i, state),
+                #[cfg(feature = "music")]
+                Asset::Music(i) => music::reload_inner(i, state),
+            };
+            let event = match result {
+                Ok(()) => AudioEvent::AssetReloaded(source),
+                Err(e) => {
+                    warn!("baal: failed to reload {:?}: {}", source, e);
+                    AudioEvent::AssetReloadFailed(source, e.to_string())
+                },
+            };
+            event::emit(event, state);
+        }
+    }
+}
@@ -2,43 +2,129 @@
 //!
 //! **it is still in early development**
 //!
-//! it allows to play three different kind of sounds:
+//! it allows to play four different kind of sounds:
 //!
 //! * short effects like for shoots
 //! * persistent effects like for fans and other ambiant sounds
 //! * musics
+//! * voice lines like dialogues, played one at a time
 //!
 //! due to rodio backend it support WAV and Vorbis audio format
 //!
 //! there is no spatialisation
 //!
+//! runtime state can be saved with `snapshot` and restored with `restore`,
+//! setting and snapshots implement serde traits with the `serde-serialize` feature
+//!
+//! setting can be loaded from YAML or TOML files with the `config-yaml` and
+//! `config-toml` features, see `config`
+//!
+//! musics are streamed from urls with the `http` feature, see `music::play_url`
+//!
+//! effects and musics are reloaded when their file change with the `hot-reload` feature
+//!
+//! logs are emitted with the [log](https://crates.io/crates/log) crate with the `log` feature
+//!
+//! musics and persistent effects can be compiled out by disabling the `music` and
+//! `persistent` default features
+//!
+//! integration with [specs](https://crates.io/crates/specs) is available with the `specs` feature
+//!
 //! see the example and tests for usages
 
 #![warn(missing_docs)]
 
 extern crate rodio;
+extern crate rand;
+#[cfg(feature = "specs")]
+extern crate specs;
+#[cfg(feature = "serde-serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "hot-reload")]
+extern crate notify;
+#[cfg(feature = "config-yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "config-toml")]
+extern crate toml;
+#[cfg(any(feature = "config-yaml", feature = "config-toml"))]
+extern crate serde_path_to_error;
+#[cfg(feature = "http")]
+extern crate reqwest;
+
+// logs are discarded without the log feature
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { format_args!($($arg)*); } }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)*) => { if false { format_args!($($arg)*); } }
+}
 
 pub mod music;
 pub mod effect;
+pub mod bus;
+pub mod parameter;
+pub mod event;
+pub mod voice;
+pub mod analysis;
+pub mod backend;
+pub mod console;
+pub mod focus;
+pub mod spatial;
+pub mod debug;
+pub mod watchdog;
+pub mod preview;
+pub mod prelude;
+#[cfg(feature = "specs")]
+pub mod ecs;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+#[cfg(any(feature = "config-yaml", feature = "config-toml"))]
+pub mod config;
 
 mod source;
+mod path;
+
+pub use effect::emitter::Emitter;
+pub use parameter::{set_parameter, parameter};
+pub use event::post_event;
+pub use event::AudioEvent;
+pub use focus::{on_focus_lost, on_focus_gained, handle_interruption_begin, handle_interruption_end};
 
 use std::sync::RwLock;
-use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::error;
 use std::fmt;
 use std::io;
+use std::time::Duration;
 
 use rodio::decoder::DecoderError;
 
+use backend::AudioBackend;
 use effect::DistanceModel;
 use music::MusicTransition;
 
+// a panic while the state is locked, like an unknown bus, doesn't poison it for
+// good: the lock is recovered by the next call
 static mut RAW_STATE: *mut RwLock<State> = 0 as *mut RwLock<State>;
 
+const CLOCK_SAMPLES_RATE: u32 = 44_100;
+
 #[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 /// set musics, effects, volumes and audio player.
 ///
-/// impl rustc_decodable and rustc_encodable
+/// impl serde traits with the `serde-serialize` feature so it can be loaded from
+/// a configuration file
 pub struct Setting {
     /// the base directory of effects
     pub effect_dir: PathBuf,
@@ -46,6 +132,13 @@ pub struct Setting {
     /// the base directory of musics
     pub music_dir: PathBuf,
 
+    /// the directory relative effect, music and voice directories are joined to,
+    /// they are relative to the current directory if none
+    ///
+    /// `~`, `$VAR`, `${VAR}` and `$EXE_DIR` components of the directories are expanded at init
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub root: Option<PathBuf>,
+
     /// global volume in [0,1]
     pub global_volume: f32,
 
@@ -55,32 +148,190 @@ pub struct Setting {
     /// effect volume in [0,1]
     pub effect_volume: f32,
 
+    /// how volumes are mapped to gains
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub volume_curve: bus::VolumeCurve,
+
+    /// the ceiling of the gain of each bus: `global_volume * bus_volume`, and of
+    /// each sound: `effect_volume * distance_gain`
+    #[cfg_attr(feature = "serde-serialize", serde(default = "default_max_gain"))]
+    pub max_gain: f32,
+
+    /// the gain in decibels applied to all the buses after their ceiling, a negative
+    /// value reserves headroom for effects played at the same time
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub master_pre_gain_db: f32,
+
     /// distance model for effect volume computation
     pub distance_model: DistanceModel,
 
+    /// air absorption coefficient, effects are low-passed with a cutoff frequency of
+    /// `20000 / (1 + air_absorption * distance)` Hz, 0 disables it
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub air_absorption: f32,
+
+    /// the distance under which effects are as loud as at this distance, so that an
+    /// effect at the listener isn't louder than a near one, 0 disables it
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub near_field: f32,
+
     /// the kind of transition between musics
     pub music_transition: MusicTransition,
 
     /// the list of short effects
     ///
     /// each effect is identified by its position in the vector
-    pub short_effects: Vec<PathBuf>,
+    pub short_effects: Vec<effect::EffectSetting>,
+
+    /// the maximum number of short effects played at once, none is unlimited
+    ///
+    /// when it is reached the quietest, and then oldest, short effect is stopped
+    /// before a new one is played, with zero no short effect is played
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub max_short_effects: Option<usize>,
+
+    /// the attenuation in decibels of a bus each time the number of short effects
+    /// heard on it doubles, so that many overlapping effects don't clip while a
+    /// single one stays prominent, 0 disables it
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub auto_gain_db: f32,
 
     /// the list of persistent effects
     ///
     /// each effect is identified by its position in the vector
-    pub persistent_effects: Vec<PathBuf>,
+    pub persistent_effects: Vec<effect::EffectSetting>,
+
+    /// the tiers of distance at which the volumes of persistent effects are updated
+    /// less often by `update`, see `effect::LodTier`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub lod_tiers: Vec<effect::LodTier>,
+
+    /// the duration of the ramp of the volume of persistent effects from 0 to 1,
+    /// so that adding or clearing positions isn't heard as a cut, 0 disables it
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub persistent_fade: Duration,
+
+    /// the list of blend containers of persistent effects
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub blend_containers: Vec<effect::blend::BlendContainer>,
+
+    /// the mixer snapshots by name, see `transition_to_snapshot`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub mixer_snapshots: HashMap<String, bus::MixerSnapshot>,
+
+    /// the return buses by name, effects are sent to them with `EffectSetting::sends`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub return_buses: HashMap<String, bus::ReturnBus>,
+
+    /// the bindings of parameters to controls, see `set_parameter`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub parameter_bindings: Vec<parameter::ParameterBinding>,
+
+    /// the events by name, see `post_event`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub events: HashMap<String, Vec<event::EventAction>>,
 
     /// the list of music
     ///
     /// each music is identified by its position in the vector
-    pub musics: Vec<PathBuf>,
+    pub musics: Vec<music::MusicSetting>,
+
+    /// enable the deterministic mode: the audio clock only advances with `advance`
+    /// and random choices use this seed
+    ///
+    /// it is read at init only
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub deterministic_seed: Option<u32>,
+
+    /// the voice lines and their volume
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub voice: voice::VoiceSetting,
+
+    /// what happens to the audio while the application doesn't have the focus,
+    /// see `on_focus_lost`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub focus_policy: focus::FocusPolicy,
+
+    /// detect when the audio device stops consuming samples, none disables it,
+    /// see `watchdog`
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub watchdog: Option<watchdog::WatchdogSetting>,
+}
+
+/// no effects nor musics, the directories are the current directory and the
+/// volumes are 1, see `init_default`
+impl Default for Setting {
+    fn default() -> Setting {
+        Setting {
+            effect_dir: PathBuf::new(),
+            music_dir: PathBuf::new(),
+            root: None,
+
+            global_volume: 1.,
+            music_volume: 1.,
+            effect_volume: 1.,
+            volume_curve: Default::default(),
+            max_gain: 1.,
+            master_pre_gain_db: 0.,
+
+            distance_model: DistanceModel::Linear(10., 100.),
+            air_absorption: 0.,
+            near_field: 0.,
+
+            music_transition: MusicTransition::Instant,
+
+            short_effects: vec!(),
+            max_short_effects: None,
+            auto_gain_db: 0.,
+            persistent_effects: vec!(),
+            lod_tiers: vec!(),
+            persistent_fade: Duration::new(0, 0),
+            blend_containers: vec!(),
+            mixer_snapshots: HashMap::new(),
+            return_buses: HashMap::new(),
+            parameter_bindings: vec!(),
+            events: HashMap::new(),
+            musics: vec!(),
+            deterministic_seed: None,
+            voice: Default::default(),
+            focus_policy: Default::default(),
+            watchdog: None,
+        }
+    }
+}
+
+impl Setting {
+    /// return the id of the short effect of this path
+    pub fn short_effect_id<P: AsRef<Path>>(&self, path: P) -> Option<effect::ShortId> {
+        self.short_effects.iter()
+            .position(|effect| effect.path == path.as_ref())
+            .map(effect::ShortId)
+    }
+
+    /// return the id of the persistent effect of this path
+    pub fn persistent_effect_id<P: AsRef<Path>>(&self, path: P) -> Option<effect::PersistentId> {
+        self.persistent_effects.iter()
+            .position(|effect| effect.path == path.as_ref())
+            .map(effect::PersistentId)
+    }
+
+    /// return the id of the music of this path
+    pub fn music_id<P: AsRef<Path>>(&self, path: P) -> Option<music::MusicId> {
+        self.musics.iter()
+            .position(|music| music.path == path.as_ref())
+            .map(music::MusicId)
+    }
+}
+
+#[cfg(feature = "serde-serialize")]
+fn default_max_gain() -> f32 {
+    1.
 }
 
 /// error possible on init
 #[derive(Debug)]
 pub enum InitError {
-    /// baal has already been initialiazed
+    /// baal has already been initialiazed with a different setting
     DoubleInit,
     /// no endpoint available
     NoDefaultEndpoint,
@@ -88,6 +339,14 @@ pub enum InitError {
     FileOpenError(PathBuf, io::Error),
     /// failed to decode file
     DecodeError(PathBuf, DecoderError),
+    /// the setting refers to a bus that doesn't exist
+    UnknownBus(String),
+    /// the setting refers to a short effect that doesn't exist
+    UnknownShortEffect(usize),
+    /// the setting refers to a persistent effect that doesn't exist
+    UnknownPersistentEffect(usize),
+    /// the directory of the setting cannot be resolved for the reason
+    InvalidDirectory(PathBuf, String),
 }
 
 impl fmt::Display for InitError {
@@ -98,57 +357,270 @@ impl fmt::Display for InitError {
             NoDefaultEndpoint => write!(fmt, "no endpoint available"),
             FileOpenError(ref source, ref error) => write!(fmt, "cannot open file {} : {}", source.to_string_lossy(), error),
             DecodeError(ref source, ref error) => write!(fmt, "cannot decode file {} : {:?}", source.to_string_lossy(), error),
+            UnknownBus(ref name) => write!(fmt, "unknown bus {}", name),
+            UnknownShortEffect(effect) => write!(fmt, "unknown short effect {}", effect),
+            UnknownPersistentEffect(effect) => write!(fmt, "unknown persistent effect {}", effect),
+            InvalidDirectory(ref dir, ref reason) => write!(fmt, "invalid directory {} : {}", dir.to_string_lossy(), reason),
+        }
+    }
+}
+
+impl error::Error for InitError {
+    fn description(&self) -> &str {
+        use self::InitError::*;
+        match *self {
+            DoubleInit => "baal has already been initialized",
+            NoDefaultEndpoint => "no endpoint available",
+            FileOpenError(..) => "cannot open file",
+            DecodeError(..) => "cannot decode file",
+            UnknownBus(..) => "unknown bus",
+            UnknownShortEffect(..) => "unknown short effect",
+            UnknownPersistentEffect(..) => "unknown persistent effect",
+            InvalidDirectory(..) => "invalid directory",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        use self::InitError::*;
+        match *self {
+            FileOpenError(_, ref error) => Some(error),
+            DecodeError(_, ref error) => Some(error),
+            DoubleInit | NoDefaultEndpoint | UnknownBus(..) | UnknownShortEffect(..)
+                | UnknownPersistentEffect(..) | InvalidDirectory(..) => None,
         }
     }
 }
 
 #[doc(hidden)]
 pub struct State {
+    setting: Setting,
     global_volume: f32,
-    endpoint: rodio::Endpoint,
+    master_pre_gain_db: f32,
+    time_scale: f32,
+    backend: backend::Backend,
+    clock: Arc<AtomicUsize>,
+    virtual_clock: bool,
+    /// the part of a sample not advanced yet on the virtual clock, in
+    /// billionths of a sample
+    clock_remainder: u64,
+    last_meter: Duration,
+    _clock_sink: backend::Sink,
     music: music::State,
     effect: effect::State,
+    voice: voice::State,
+    mixer: bus::Mixer,
+    parameters: parameter::Parameters,
+    events: event::Events,
+    focus: focus::Focus,
+    spatial: spatial::Spatial,
+    watchdog: watchdog::Watchdog,
+    preview: preview::Preview,
+    #[cfg(feature = "hot-reload")]
+    hot_reload: hot_reload::HotReload,
 }
 
 impl State {
-    fn init(setting: &Setting) -> Result<State,InitError> {
-        let endpoint = try!(rodio::get_default_endpoint().ok_or(InitError::NoDefaultEndpoint));
+    fn init(setting: &Setting, backend: backend::Backend) -> Result<State,InitError> {
+        let clock = Arc::new(AtomicUsize::new(0));
+        let clock_sink = backend.new_sink();
+        if setting.deterministic_seed.is_none() {
+            clock_sink.append(source::clock(CLOCK_SAMPLES_RATE, clock.clone()));
+        }
 
         Ok(State {
-            global_volume: setting.global_volume,
-            effect: try!(effect::State::init(setting, &endpoint)),
+            setting: setting.clone(),
+            global_volume: bus::sanitize_volume(setting.global_volume),
+            master_pre_gain_db: setting.master_pre_gain_db,
+            time_scale: 1.,
+            clock: clock,
+            virtual_clock: setting.deterministic_seed.is_some(),
+            clock_remainder: 0,
+            last_meter: Duration::new(0, 0),
+            _clock_sink: clock_sink,
+            effect: try!(effect::State::init(setting, &backend)),
             music: try!(music::State::init(setting)),
-            endpoint: endpoint,
+            voice: try!(voice::State::init(setting)),
+            mixer: try!(bus::Mixer::init(setting)),
+            parameters: try!(parameter::Parameters::init(setting)),
+            events: try!(event::Events::init(setting)),
+            focus: focus::Focus::init(setting),
+            spatial: spatial::Spatial::init(),
+            watchdog: watchdog::Watchdog::init(setting),
+            preview: preview::Preview::init(),
+            #[cfg(feature = "hot-reload")]
+            hot_reload: hot_reload::HotReload::init(setting),
+            backend: backend,
         })
     }
     fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
-        self.global_volume = setting.global_volume;
+        // the inserts are added at runtime
+        let inserts = bus::take_inserts(self);
+        self.setting = setting.clone();
+        self.global_volume = bus::sanitize_volume(setting.global_volume);
+        self.master_pre_gain_db = setting.master_pre_gain_db;
         try!(self.music.reset(setting));
-        try!(self.effect.reset(setting, &self.endpoint));
+        try!(self.effect.reset(setting, &self.backend));
+        self.mixer = try!(bus::Mixer::init(setting));
+        try!(self.parameters.reset(setting));
+        try!(self.voice.reset(setting));
+        try!(self.events.reset(setting));
+        self.focus.reset(setting);
+        self.watchdog.reset(setting);
+        self.preview.reset();
+        bus::restore_inserts(inserts, self);
+        #[cfg(feature = "hot-reload")]
+        {
+            self.hot_reload = hot_reload::HotReload::init(setting);
+        }
+        parameter::reapply(self);
+        bus::update_time_scale(self);
+        focus::apply(self);
 
         Ok(())
     }
+    fn rebuild(&mut self, backend: backend::Backend) -> Result<(),InitError> {
+        // the sinks of the previous backend are dropped by the reset
+        self.backend = backend;
+        let clock_sink = self.backend.new_sink();
+        if !self.virtual_clock {
+            clock_sink.append(source::clock(CLOCK_SAMPLES_RATE, self.clock.clone()));
+        }
+        self._clock_sink = clock_sink;
+        let setting = self.setting.clone();
+        self.reset(&setting)
+    }
+    /// how the sources measure their delays and fades, on the audio clock in
+    /// deterministic mode so that they only advance with `advance`
+    fn timing(&self) -> source::Timing {
+        if self.virtual_clock {
+            source::Timing::Clock(self.clock.clone(), CLOCK_SAMPLES_RATE, None)
+        } else {
+            source::Timing::Samples
+        }
+    }
 }
 
-/// init the audio player
+/// init the audio player on the default endpoint
+///
+/// if it is already initialized with an equal setting nothing is done,
+/// otherwise `DoubleInit` is returned
 pub fn init(setting: &Setting) -> Result<(), InitError> {
+    init_inner(setting, || {
+        rodio::get_default_endpoint()
+            .map(backend::Backend::Rodio)
+            .ok_or(InitError::NoDefaultEndpoint)
+    })
+}
+
+/// init the audio player on the default endpoint with the default setting
+///
+/// effects and musics can then be added with `effect::short::register`,
+/// `effect::persistent::register` and `music::register`
+pub fn init_default() -> Result<(), InitError> {
+    init(&Setting::default())
+}
+
+/// init the audio player on the default endpoint, the endpoint is looked for
+/// `attempts` times waiting `delay` between them
+///
+/// it is meant for systems where the audio server may not be ready when the game
+/// starts, the thread is blocked while waiting, see `init` for double initialization
+pub fn init_with_retry(setting: &Setting, attempts: usize, delay: Duration) -> Result<(), InitError> {
+    init_inner(setting, || {
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                ::std::thread::sleep(delay);
+            }
+            if let Some(endpoint) = rodio::get_default_endpoint() {
+                return Ok(backend::Backend::Rodio(endpoint));
+            }
+            warn!("baal: no default endpoint, attempt {} of {}", attempt + 1, attempts);
+        }
+        Err(InitError::NoDefaultEndpoint)
+    })
+}
+
+/// init the audio player on the endpoint
+///
+/// it allows to share the device with the rest of the application,
+/// see `init` for double initialization
+pub fn init_with_endpoint(setting: &Setting, endpoint: rodio::Endpoint) -> Result<(), InitError> {
+    init_inner(setting, || Ok(backend::Backend::Rodio(endpoint)))
+}
+
+/// init the audio player on the backend
+///
+/// the null backend allows to use baal without audio device,
+/// see `init` for double initialization
+pub fn init_with_backend(setting: &Setting, backend: backend::Backend) -> Result<(), InitError> {
+    init_inner(setting, || Ok(backend))
+}
+
+#[inline]
+fn init_inner<F>(setting: &Setting, backend: F) -> Result<(), InitError>
+    where F: FnOnce() -> Result<backend::Backend, InitError>
+{
+    let setting = &try!(path::resolve_setting(setting));
     unsafe {
         if !RAW_STATE.is_null() {
+            if (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()).setting == *setting {
+                return Ok(());
+            }
+            warn!("baal: {}", InitError::DoubleInit);
             return Err(InitError::DoubleInit);
         }
-        let box_state = Box::new(RwLock::new(try!(State::init(setting))));
+        let state = try!(backend().and_then(|backend| State::init(setting, backend)).map_err(|e| {
+            warn!("baal: init failed: {}", e);
+            e
+        }));
+        debug!("baal: init with {} short effects, {} persistent effects, {} musics and {} voice lines",
+               setting.short_effects.len(), setting.persistent_effects.len(),
+               setting.musics.len(), setting.voice.lines.len());
+        let box_state = Box::new(RwLock::new(state));
         RAW_STATE = Box::into_raw(box_state);
 
         Ok(())
     }
 }
 
+/// rebuild the audio player on the current default endpoint, it is meant to be
+/// called when the audio route changes, like when headphones are plugged
+///
+/// the runtime state saved by `snapshot` is restored, short effects and voice
+/// lines are stopped, nothing is done if the backend is not the rodio backend
+pub fn handle_route_change() -> Result<(),InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    match state.backend {
+        backend::Backend::Rodio(_) => (),
+        _ => return Ok(()),
+    }
+    let endpoint = try!(rodio::get_default_endpoint().ok_or(InitError::NoDefaultEndpoint).map_err(|e| {
+        warn!("baal: route change failed: {}", e);
+        e
+    }));
+    let snapshot = snapshot_state(&state);
+    try!(state.rebuild(backend::Backend::Rodio(endpoint)));
+    restore_state(&snapshot, &mut state);
+    #[cfg(feature = "music")]
+    {
+        let opened = music::open_restore_inner(&snapshot, &state).map(music::Opened::skip);
+        music::restore_inner(&snapshot, opened, &mut state);
+    }
+    debug!("baal: rebuild on route change");
+    Ok(())
+}
+
+/// return whether the audio player is initialized
+pub fn is_initialized() -> bool {
+    unsafe { !RAW_STATE.is_null() }
+}
+
 /// close the audio player, it can be init again.
 pub fn close() {
     unsafe {
         if !RAW_STATE.is_null() {
             let mutex_state = Box::from_raw(RAW_STATE);
-            let _ = mutex_state.read().unwrap();
+            let _ = mutex_state.read().unwrap_or_else(|e| e.into_inner());
         }
         RAW_STATE = 0 as *mut RwLock<State>;
     }
@@ -156,31 +628,452 @@ pub fn close() {
 
 /// reset audio from setting on the fly
 pub fn reset(setting: &Setting) -> Result<(),InitError> {
+    let setting = &try!(path::resolve_setting(setting));
     unsafe {
-        let mut state = (*RAW_STATE).write().unwrap();
+        let mut state = (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner());
 
-        try!(state.reset(setting));
+        try!(state.reset(setting).map_err(|e| {
+            warn!("baal: reset failed: {}", e);
+            e
+        }));
+        debug!("baal: reset");
 
         Ok(())
     }
 }
 
 /// set the global volume
+///
+/// volumes are clamped in [0,`bus::MAX_VOLUME`] and NaN is 0
 pub fn set_global_volume(v: f32) {
-    let mut state = unsafe { (*RAW_STATE).write().unwrap() };
-    state.global_volume = v;
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.global_volume = bus::sanitize_volume(v);
     update_volume(&mut *state);
 }
 
+/// set the time scale, 1 is the normal speed
+///
+/// sounds of buses following the time scale are played faster or slower,
+/// by default only the effect bus follows it, see `bus::set_time_scaled`
+pub fn set_time_scale(scale: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.time_scale = scale;
+    bus::update_time_scale(&state);
+}
+
+/// return the time scale
+pub fn time_scale() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.time_scale
+}
+
+/// perform all the periodic work, it is meant to be called once per frame
+///
+/// * step the transition between mixer snapshots
+/// * execute the delayed actions of events
+/// * move the listener toward its target
+/// * update the volume of all persistent effects
+/// * free the short effects that have ended
+/// * play the queued voice lines
+/// * open the music played after the current iteration of the music
+/// * watch the output, see `watchdog`
+/// * give the audio events to the callback
+pub fn update(dt: Duration) {
+    let (emitted, callback, reinit) = {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        bus::update(dt, &mut *state);
+        event::update(&mut *state);
+        effect::update(dt, &mut *state);
+        voice::update(&mut *state);
+        #[cfg(feature = "music")]
+        music::update(&mut *state);
+        #[cfg(feature = "hot-reload")]
+        hot_reload::update(&mut *state);
+        let reinit = watchdog::update(dt, &mut *state);
+        let (emitted, callback) = event::take_emitted(&mut *state);
+        (emitted, callback, reinit)
+    };
+    event::dispatch(emitted, callback);
+    if reinit {
+        // the failure is logged
+        let _ = handle_route_change();
+    }
+}
+
+/// advance the audio clock and perform all the periodic work, see `update`
+///
+/// the audio clock is only advanced in deterministic mode, see
+/// `Setting::deterministic_seed`, the delays of the effects and the fades of the
+/// effects and musics are measured on it so that they are reproducible
+pub fn advance(dt: Duration) {
+    {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if state.virtual_clock {
+            // the remainder is carried so that the clock doesn't drift from the
+            // sum of the durations
+            let rate = CLOCK_SAMPLES_RATE as u64;
+            let fraction = dt.subsec_nanos() as u64 * rate + state.clock_remainder;
+            let samples = dt.as_secs() * rate + fraction / 1_000_000_000;
+            state.clock_remainder = fraction % 1_000_000_000;
+            state.clock.fetch_add(samples as usize, Relaxed);
+        }
+    }
+    update(dt);
+}
+
+/// mix the next samples of the sounds played and return them, the samples are
+/// mono at `backend::RENDER_SAMPLES_RATE`
+///
+/// it is meant for tests, panic if the backend is not the render backend
+pub fn render(duration: Duration) -> Vec<f32> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let rate = backend::RENDER_SAMPLES_RATE as u64;
+    let samples = duration.as_secs() * rate + duration.subsec_nanos() as u64 * rate / 1_000_000_000;
+    match state.backend {
+        backend::Backend::Render(ref backend) => backend.render(samples as usize),
+        _ => panic!("the backend is not the render backend"),
+    }
+}
+
+/// blend the volumes and filters of the buses toward the mixer snapshot
+///
+/// the blending is performed by `update`, panic if the snapshot doesn't exist
+pub fn transition_to_snapshot(name: &str, duration: Duration) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    bus::transition_to_snapshot_inner(name, duration, &mut *state);
+}
+
+/// attenuate and low-pass the music, effect and voice buses for a pause menu,
+/// the ui bus is untouched
+///
+/// the fade is performed by `update`, nothing is done if the preset is already entered
+pub fn enter_pause_preset() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    bus::enter_pause_preset_inner(&mut *state);
+}
+
+/// fade out the attenuation and the low pass of `enter_pause_preset`, the mix
+/// changed meanwhile is kept
+///
+/// the fade is performed by `update`, nothing is done if the preset isn't entered
+pub fn exit_pause_preset() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    bus::exit_pause_preset_inner(&mut *state);
+}
+
+/// pause all the buses except the listed ones, they are not resumed
+///
+/// panic if a listed bus doesn't exist
+pub fn pause_all_except(buses: &[&str]) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    bus::pause_all_except_inner(buses, &*state);
+}
+
+/// resume all the buses
+pub fn resume_all() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    bus::resume_all_inner(&*state);
+}
+
+/// return whether the pause preset is entered
+pub fn is_pause_preset() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    bus::is_pause_preset_inner(&*state)
+}
+
 #[inline]
 fn update_volume(state: &mut State) {
-    music::update_volume(state);
-    effect::update_volume(state);
+    bus::update_volume(state);
 }
 
 /// return the global volume
 pub fn global_volume() -> f32 {
-    let state = unsafe { (*RAW_STATE).read().unwrap() };
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
     state.global_volume
 }
 
+/// set the global volume so that its gain is the decibels
+pub fn set_global_volume_db(db: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.global_volume = bus::sanitize_volume(state.setting.volume_curve.volume(bus::db_to_gain(db)));
+    update_volume(&mut *state);
+}
+
+/// return the gain of the global volume in decibels
+pub fn global_volume_db() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    bus::gain_to_db(state.setting.volume_curve.gain(state.global_volume))
+}
+
+/// set the gain in decibels applied to all the buses, see `Setting::master_pre_gain_db`
+pub fn set_master_pre_gain_db(db: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.master_pre_gain_db = db;
+    bus::update_pre_gain(&mut *state);
+}
+
+/// return the gain in decibels applied to all the buses
+pub fn master_pre_gain_db() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.master_pre_gain_db
+}
+
+/// return the time elapsed on the audio clock
+///
+/// the audio clock is driven by the samples consumed by the audio device,
+/// or by `advance` in deterministic mode, it starts at init and is not affected
+/// by reset
+pub fn clock() -> Duration {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    clock_inner(&state)
+}
+
+/// return the levels of the output measured since the last call
+///
+/// levels are measured on each bus after its volume is applied
+pub fn meter() -> bus::LevelMeter {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let now = clock_inner(&state);
+    let elapsed = now - state.last_meter;
+    state.last_meter = now;
+    bus::meter_inner(elapsed, &state)
+}
+
+#[inline]
+fn clock_inner(state: &State) -> Duration {
+    let samples = state.clock.load(Relaxed) as u64;
+    let rate = CLOCK_SAMPLES_RATE as u64;
+    Duration::new(samples / rate, ((samples % rate) * 1_000_000_000 / rate) as u32)
+}
+
+/// the runtime state of the audio, see `snapshot` and `restore`
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AudioSnapshot {
+    /// the global volume
+    pub global_volume: f32,
+
+    /// the music volume
+    pub music_volume: f32,
+
+    /// the effect volume
+    pub effect_volume: f32,
+
+    /// the music played if any
+    pub music_index: Option<music::MusicId>,
+
+    /// the position in the music played
+    pub music_position: Duration,
+
+    /// whether the music is paused
+    pub music_paused: bool,
+
+    /// whether the effects are paused
+    pub effect_paused: bool,
+
+    /// the position of the listener
+    pub listener: [f32;3],
+
+    /// the sources of each persistent effect
+    pub persistent_positions: Vec<Vec<[f32;3]>>,
+}
+
+/// return the runtime state of the audio
+pub fn snapshot() -> AudioSnapshot {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    snapshot_state(&state)
+}
+
+fn snapshot_state(state: &State) -> AudioSnapshot {
+    let mut snapshot = AudioSnapshot {
+        global_volume: state.global_volume,
+        music_volume: 0.,
+        effect_volume: 0.,
+        music_index: None,
+        music_position: Duration::new(0, 0),
+        music_paused: false,
+        effect_paused: false,
+        listener: [0.;3],
+        persistent_positions: vec!(),
+    };
+    #[cfg(feature = "music")]
+    music::snapshot_inner(state, &mut snapshot);
+    effect::snapshot_inner(state, &mut snapshot);
+    snapshot
+}
+
+/// restore the runtime state of the audio
+///
+/// the music is played from the saved position using the current transition
+///
+/// persistent effects that doesn't exist anymore are ignored
+pub fn restore(snapshot: &AudioSnapshot) {
+    // the music is decoded up to its position before the lock is taken for
+    // writing so that the other calls are not blocked meanwhile
+    #[cfg(feature = "music")]
+    let opened = {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        music::open_restore_inner(snapshot, &state)
+    };
+    #[cfg(feature = "music")]
+    let opened = opened.map(music::Opened::skip);
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    restore_state(snapshot, &mut state);
+    #[cfg(feature = "music")]
+    music::restore_inner(snapshot, opened, &mut state);
+}
+
+/// restore the runtime state but the music
+fn restore_state(snapshot: &AudioSnapshot, state: &mut State) {
+    state.global_volume = bus::sanitize_volume(snapshot.global_volume);
+    effect::restore_inner(snapshot, state);
+    update_volume(state);
+}
+
+/// the status of the audio for bug reports and audio consoles, see `status`
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AudioStatus {
+    /// the global volume
+    pub global_volume: f32,
+
+    /// the time scale
+    pub time_scale: f32,
+
+    /// the status of the music
+    pub music: music::MusicStatus,
+
+    /// the music played if any
+    pub music_index: Option<music::MusicId>,
+
+    /// the position in the music played if any
+    pub music_position: Option<Duration>,
+
+    /// the music volume
+    pub music_volume: f32,
+
+    /// the effect volume
+    pub effect_volume: f32,
+
+    /// whether the effects are paused
+    pub effect_paused: bool,
+
+    /// the position of the listener
+    pub listener: [f32;3],
+
+    /// the distance model
+    pub distance_model: DistanceModel,
+}
+
+/// return the status of the audio
+pub fn status() -> AudioStatus {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let mut status = AudioStatus {
+        global_volume: state.global_volume,
+        time_scale: state.time_scale,
+        music: music::MusicStatus::Stop,
+        music_index: None,
+        music_position: None,
+        music_volume: 0.,
+        effect_volume: 0.,
+        effect_paused: false,
+        listener: [0.;3],
+        distance_model: DistanceModel::Linear(0., 0.),
+    };
+    #[cfg(feature = "music")]
+    music::report_inner(&state, &mut status);
+    effect::report_inner(&state, &mut status);
+    status
+}
+
+/// statistics of the audio for debugging, see `stats`
+#[derive(Clone,Debug,PartialEq)]
+pub struct Stats {
+    /// the number of short effects played or waiting to be played
+    pub short_effects: usize,
+
+    /// the number of persistent effects with a volume greater than zero
+    pub persistent_audible: usize,
+
+    /// the number of persistent effects played silently
+    pub persistent_virtual: usize,
+
+    /// the status of the music
+    pub music: music::MusicStatus,
+
+    /// whether a voice line is played
+    pub voice_line: bool,
+
+    /// the number of voice lines queued
+    pub voice_lines_queued: usize,
+
+    /// the memory used by the buffered sources of the effects in bytes
+    pub buffered_bytes: usize,
+
+    /// the number of sinks allocated, each one is mixed by the audio device
+    pub sinks: usize,
+
+    /// the number of samples per second of the mix beyond full scale during the
+    /// last second measured by `update`
+    ///
+    /// only the render backend gives access to the mix, it is none with the
+    /// other backends
+    pub clipped_per_second: Option<usize>,
+}
+
+/// return statistics of the audio for debugging
+pub fn stats() -> Stats {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let mut stats = Stats {
+        short_effects: 0,
+        persistent_audible: 0,
+        persistent_virtual: 0,
+        music: music::MusicStatus::Stop,
+        voice_line: false,
+        voice_lines_queued: 0,
+        buffered_bytes: 0,
+        // the clock
+        sinks: 1,
+        clipped_per_second: bus::clipped_per_second_inner(&state),
+    };
+    #[cfg(feature = "music")]
+    music::stats_inner(&state, &mut stats);
+    effect::stats_inner(&state, &mut stats);
+    voice::stats_inner(&state, &mut stats);
+    stats
+}
+
+/// the memory used by the effects loaded in memory, see `memory_report`
+#[derive(Clone,Debug,PartialEq)]
+pub struct MemoryReport {
+    /// the memory used by all the files in bytes
+    pub total_bytes: usize,
+
+    /// the files sorted from the largest, a file used by several effects is
+    /// counted once
+    pub assets: Vec<AssetMemory>,
+}
+
+/// the memory used by a file, see `MemoryReport`
+#[derive(Clone,Debug,PartialEq)]
+pub struct AssetMemory {
+    /// the path of the file joined to the effect directory
+    pub path: PathBuf,
+
+    /// the memory used by its decoded samples in bytes
+    pub bytes: usize,
+}
+
+/// return the memory used by the effects loaded in memory, streamed effects
+/// and musics are not kept in memory
+pub fn memory_report() -> MemoryReport {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    let mut report = MemoryReport {
+        total_bytes: 0,
+        assets: vec!(),
+    };
+    effect::memory_report_inner(&state, &mut report);
+    report.assets.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    report.total_bytes = report.assets.iter().fold(0, |sum, asset| sum + asset.bytes);
+    report
+}
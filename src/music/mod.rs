@@ -0,0 +1,150 @@
+//! this module allow to play music
+//!
+//! the playback is available with the `music` feature, enabled by default
+
+#[cfg(feature = "music")]
+mod player;
+
+#[cfg(feature = "music")]
+pub use self::player::*;
+
+#[cfg(all(feature = "music", feature = "http"))]
+mod stream;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(not(feature = "music"))]
+use super::bus::Bus;
+#[cfg(not(feature = "music"))]
+use super::InitError;
+#[cfg(not(feature = "music"))]
+use super::Setting;
+
+/// the id of a music: its index in the musics of the setting, see `Setting::music_id`
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MusicId(pub(crate) usize);
+
+impl MusicId {
+    /// the index of the music in the musics of the setting
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// a music entry of the setting
+///
+/// it can be created from a path: `"village.ogg".into()`
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MusicSetting {
+    /// the path of the file relative to the music directory
+    pub path: PathBuf,
+
+    /// the volume of the music in [0,1], it is multiplied by the music bus volume
+    #[cfg_attr(feature = "serde-serialize", serde(default = "default_volume"))]
+    pub volume: f32,
+
+    /// the transition used when the music is played, the current transition if none
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub transition: Option<MusicTransition>,
+
+    /// the path of a file played once before the music, the music then loops
+    /// right after it without gap
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub intro: Option<PathBuf>,
+}
+
+#[cfg(feature = "serde-serialize")]
+fn default_volume() -> f32 {
+    1.
+}
+
+impl From<PathBuf> for MusicSetting {
+    fn from(path: PathBuf) -> MusicSetting {
+        MusicSetting {
+            path: path,
+            volume: 1.,
+            transition: None,
+            intro: None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for MusicSetting {
+    fn from(path: &'a str) -> MusicSetting {
+        PathBuf::from(path).into()
+    }
+}
+
+impl From<String> for MusicSetting {
+    fn from(path: String) -> MusicSetting {
+        PathBuf::from(path).into()
+    }
+}
+
+/// the status of the music
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum MusicStatus {
+    /// the music is paused
+    Pause,
+    /// there is no music
+    Stop,
+    /// the music is played
+    Play,
+    /// a transition between musics or to the end of the music is in progress
+    Transition,
+}
+
+/// the type of transition between musics
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum MusicTransition {
+    /// the current music end smoothly and then the new one is played.
+    Smooth(Duration),
+    /// the current music end smoothly while the new one begin smoothly.
+    Overlap(Duration),
+    /// the current music is stopped and the new one is played.
+    Instant,
+}
+
+impl MusicTransition {
+    /// whether music transition is smooth
+    pub fn is_smooth(&self) -> bool {
+        if let &MusicTransition::Smooth(_) = self {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// without the `music` feature only the music bus is kept so that mixer snapshots
+/// and ducking behave the same
+#[cfg(not(feature = "music"))]
+#[doc(hidden)]
+pub struct State {
+    #[doc(hidden)]
+    pub bus: Bus,
+}
+#[cfg(not(feature = "music"))]
+impl State {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<State,InitError> {
+        if !setting.musics.is_empty() {
+            warn!("baal: musics are ignored without the music feature");
+        }
+        Ok(State {
+            bus: Bus::new(setting.music_volume, setting),
+        })
+    }
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
+        *self = try!(State::init(setting));
+        Ok(())
+    }
+}
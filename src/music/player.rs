@@ -0,0 +1,712 @@
+//! the playback of musics
+
+use rodio::decoder::Decoder;
+use rodio::Sample;
+use rodio::Source;
+use rodio::source::Amplify;
+
+use std::fs::File;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::path::PathBuf;
+
+use super::super::backend::{AudioBackend, AudioSink, Sink};
+use super::super::AudioSnapshot;
+use super::super::AudioStatus;
+use super::super::Stats;
+use super::super::bus;
+use super::super::bus::Bus;
+use super::super::InitError;
+use super::super::RAW_STATE;
+use super::super::Setting;
+use super::super::CLOCK_SAMPLES_RATE;
+use super::super::source;
+use super::{MusicId, MusicSetting, MusicStatus, MusicTransition};
+
+#[cfg(feature = "http")]
+use super::stream;
+#[cfg(feature = "http")]
+use super::super::event;
+#[cfg(feature = "http")]
+use super::super::event::AudioEvent;
+
+/// the source of a music from the setting
+type MusicSource = Amplify<Decoder<File>>;
+
+/// the musics of the queue of the current music
+struct Queue {
+    /// the index and the next music, they are updated by the audio thread when
+    /// the music changes
+    index: Arc<AtomicUsize>,
+    next: Arc<AtomicUsize>,
+    /// the music played after the current iteration, it is opened by `update`
+    /// so that the audio thread doesn't access the disk
+    prepared: source::Prepared<MusicSource>,
+}
+
+struct Current {
+    /// none for musics played from an url
+    queue: Option<Queue>,
+    /// the clock value the fade out starts at
+    fade_out: Arc<AtomicUsize>,
+    /// the duration of the fade out
+    fade: Duration,
+    position: Arc<AtomicUsize>,
+    channels: u16,
+    samples_rate: u32,
+    sink: Sink,
+}
+
+#[doc(hidden)]
+pub struct State {
+    transition: MusicTransition,
+    #[doc(hidden)]
+    pub bus: Bus,
+    sources: Vec<PathBuf>,
+    intros: Vec<Option<PathBuf>>,
+    volumes: Vec<f32>,
+    transitions: Vec<Option<MusicTransition>>,
+    durations: Vec<Option<Duration>>,
+    current: Option<Current>,
+    /// the playback speed of the musics * 10_000
+    speed: Arc<AtomicUsize>,
+    /// the clock value the current transition ends at
+    transition_end: usize,
+    /// the errors of the musics streamed from urls
+    #[cfg(feature = "http")]
+    stream_errors: Arc<Mutex<Vec<String>>>,
+}
+impl State {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<State,InitError> {
+        let mut state = State {
+            transition: setting.music_transition,
+            bus: Bus::new(setting.music_volume, setting),
+            sources: vec!(),
+            intros: vec!(),
+            volumes: vec!(),
+            transitions: vec!(),
+            durations: vec!(),
+            current: None,
+            speed: Arc::new(AtomicUsize::new(10_000)),
+            transition_end: 0,
+            #[cfg(feature = "http")]
+            stream_errors: Arc::new(Mutex::new(vec!())),
+        };
+
+        for music in &setting.musics {
+            try!(state.add(setting, music));
+        }
+
+        Ok(state)
+    }
+    /// check the files of the music and add it, return its index
+    fn add(&mut self, setting: &Setting, music: &MusicSetting) -> Result<usize,InitError> {
+        let path = setting.music_dir.join(&music.path);
+        let decoder = try!(decode(&music.path, &path));
+
+        let intro = match music.intro {
+            Some(ref intro) => {
+                let path = setting.music_dir.join(intro);
+                try!(decode(intro, &path));
+                Some(path)
+            },
+            None => None,
+        };
+
+        self.sources.push(path);
+        self.intros.push(intro);
+        self.volumes.push(bus::sanitize_volume(music.volume));
+        self.transitions.push(music.transition);
+        self.durations.push(total_duration(decoder));
+        Ok(self.sources.len() - 1)
+    }
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
+        *self = try!(State::init(setting));
+        Ok(())
+    }
+}
+
+/// set the volume of the music
+/// the actual music volume is `music_volume * global_volume`
+pub fn set_volume(v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    state.music.bus.set_volume(v, global_volume);
+}
+
+/// return the volume of the music
+pub fn volume() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.bus.volume()
+}
+
+/// set the playback speed of the musics, 1 is the normal speed
+///
+/// it affects the current and future musics, the pitch changes with the speed
+pub fn set_speed(speed: f32) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.speed.store((speed.max(0.) * 10_000.) as usize, Relaxed);
+}
+
+/// return the playback speed of the musics
+pub fn speed() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.speed.load(Relaxed) as f32 / 10_000.
+}
+
+/// play the music with its transition, the current transition if it has none
+pub fn play(music: MusicId) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    play_inner(music.0, Duration::new(0, 0), &mut state);
+}
+
+/// play the music with the transition, it overrides the transition of the music
+/// and the current transition for this call only
+pub fn play_with_transition(music: MusicId, transition: MusicTransition) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    play_queue_inner(music.0, source::REPEAT, Duration::new(0, 0), transition, &mut state);
+}
+
+/// the delay in samples of the audio clock before the start of an overlap,
+/// it must be longer than the buffer of the audio device
+const ALIGNMENT_LATENCY: usize = 2048;
+
+/// the milliseconds of music decoded before a music streamed from an url starts to play
+#[cfg(feature = "http")]
+pub const URL_PREBUFFER_MILLIS: u64 = 2_000;
+
+/// stream the music from the url and play it once, the music must be Vorbis or WAV
+///
+/// the music starts once `URL_PREBUFFER_MILLIS` are decoded, baal is not locked while
+/// connecting and buffering, the music has no index and can't be restored from a snapshot
+///
+/// the error is returned if the connection or the decoding can't start, the
+/// music is then downloaded and decoded in a background thread: silence is
+/// played while the network is late and `AudioEvent::MusicStreamError` is
+/// emitted by `baal::update` if the download is interrupted
+#[cfg(feature = "http")]
+pub fn play_url(url: &str) -> Result<(),InitError> {
+    let errors = {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        state.music.stream_errors.clone()
+    };
+    let source = try!(stream::open(url, Duration::from_millis(URL_PREBUFFER_MILLIS), errors));
+
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    debug!("baal: play music from {} with transition {:?}", url, state.music.transition);
+    let position = Arc::new(AtomicUsize::new(0));
+    let source = source::counter(source, position.clone());
+    let transition = state.music.transition;
+    play_source(source, None, position, transition, &mut state);
+    Ok(())
+}
+
+/// open the music played after the current iteration and emit the errors of
+/// the musics streamed from urls
+#[doc(hidden)]
+pub fn update(state: &mut super::super::State) {
+    prepare(state);
+
+    #[cfg(feature = "http")]
+    {
+        let errors = match state.music.stream_errors.lock() {
+            Ok(mut errors) => errors.drain(..).collect::<Vec<_>>(),
+            Err(_) => vec!(),
+        };
+        for error in errors {
+            warn!("baal: music stream error: {}", error);
+            event::emit(AudioEvent::MusicStreamError(error), state);
+        }
+    }
+}
+
+/// open the music played after the current iteration of the queue unless it
+/// is already prepared, the queue ends if it can't be opened
+fn prepare(state: &super::super::State) {
+    let queue = match current(state).and_then(|current| current.queue.as_ref()) {
+        Some(queue) => queue,
+        None => return,
+    };
+    let (music, start) = match queue.next.load(Relaxed) {
+        source::END => return,
+        source::REPEAT => (queue.index.load(Relaxed), false),
+        next => (next, true),
+    };
+    if let Ok(prepared) = queue.prepared.lock() {
+        match *prepared {
+            Some((prepared_music, prepared_start, _)) if prepared_music == music && prepared_start == start => return,
+            _ => (),
+        }
+    }
+
+    // the queue isn't locked while the file is opened
+    match open_music(music, start, &state.music) {
+        Ok(source) => {
+            if let Ok(mut prepared) = queue.prepared.lock() {
+                *prepared = Some((music, start, source));
+            }
+        },
+        Err(e) => {
+            warn!("baal: music {} cannot be played: {}", music, e);
+            queue.next.store(source::END, Relaxed);
+        },
+    }
+}
+
+/// open the music, or its intro if it starts
+fn open_music(music: usize, start: bool, state: &State) -> Result<MusicSource,InitError> {
+    let path = match (start, state.intros[music].as_ref()) {
+        (true, Some(intro)) => intro,
+        _ => &state.sources[music],
+    };
+    Ok(try!(open(path)).amplify(state.volumes[music]))
+}
+
+#[inline]
+fn play_inner(music: usize, start: Duration, state: &mut super::super::State) {
+    let transition = state.music.transitions[music].unwrap_or(state.music.transition);
+    play_queue_inner(music, source::REPEAT, start, transition, state);
+}
+
+/// play the music from the start position and then `next`
+#[inline]
+fn play_queue_inner(music: usize, next: usize, start: Duration, transition: MusicTransition, state: &mut super::super::State) {
+    debug!("baal: play music {} from {:?} with transition {:?}", music, start, transition);
+    if let Some(opened) = open_inner(music, next, start, state) {
+        play_opened(opened.skip(), transition, state);
+    }
+}
+
+/// a music opened to be played from a start position, see `Opened::skip`
+#[doc(hidden)]
+pub struct Opened {
+    music: usize,
+    source: source::Counter<source::MusicQueue<MusicSource>>,
+    queue: Queue,
+    position: Arc<AtomicUsize>,
+    /// the number of samples before the start position
+    skip: usize,
+}
+
+impl Opened {
+    /// decode the music up to its start position, it doesn't need the state so
+    /// that it can be done without holding its lock
+    #[doc(hidden)]
+    pub fn skip(mut self) -> Opened {
+        for _ in 0..self.skip {
+            self.source.next();
+        }
+        self.skip = 0;
+        self
+    }
+}
+
+/// open the music to play it from the start position and then `next`
+fn open_inner(music: usize, next: usize, start: Duration, state: &super::super::State) -> Option<Opened> {
+    let input = match open_music(music, true, &state.music) {
+        Ok(input) => input,
+        Err(e) => {
+            warn!("baal: music {} cannot be played: {}", music, e);
+            return None;
+        },
+    };
+    let queue = Queue {
+        index: Arc::new(AtomicUsize::new(music)),
+        next: Arc::new(AtomicUsize::new(next)),
+        prepared: Arc::new(Mutex::new(None)),
+    };
+    let position = Arc::new(AtomicUsize::new(0));
+    let source = source::music_queue(input, queue.prepared.clone(), queue.index.clone(), queue.next.clone(), position.clone());
+    let skip = duration_to_samples(start, source.get_channels(), source.get_samples_rate());
+
+    // skipped samples are counted in the position
+    let source = source::counter(source, position.clone());
+    Some(Opened {
+        music: music,
+        source: source,
+        queue: queue,
+        position: position,
+        skip: skip,
+    })
+}
+
+/// play the opened music once it is decoded up to its start position
+fn play_opened(opened: Opened, transition: MusicTransition, state: &mut super::super::State) {
+    play_source(opened.source, Some(opened.queue), opened.position, transition, state);
+    prepare(state);
+}
+
+/// play the source with the transition, the source counts its samples in `position`
+fn play_source<I>(source: I, queue: Option<Queue>, position: Arc<AtomicUsize>, transition: MusicTransition, state: &mut super::super::State)
+    where I: Source + Send + 'static, I::Item: Sample + Send
+{
+    use self::MusicTransition::*;
+
+    // an overlap starts slightly later on the audio clock so that the fade out
+    // of the current music and the fade in of the new one start on the same sample
+    let now = state.clock.load(Relaxed);
+    let at = match transition {
+        Overlap(_) if !state.virtual_clock => now + ALIGNMENT_LATENCY,
+        _ => now,
+    };
+    stop_at(at, state);
+
+    let fade_out = Arc::new(AtomicUsize::new(source::NO_FADE_OUT));
+    let sink = state.backend.new_sink();
+
+    let channels = source.get_channels();
+    let samples_rate = source.get_samples_rate();
+    let (fade, delay) = match transition {
+        Smooth(duration) => (duration, duration),
+        Overlap(duration) => (duration, Duration::new(0, 0)),
+        Instant => (Duration::new(0, 0), Duration::new(0, 0)),
+    };
+    let source = source::speed_ctrl(source, state.music.speed.clone());
+    let timing = state.timing();
+    let source = source::fade_out_ctrl(source, fade, state.clock.clone(), fade_out.clone(), timing.clone());
+    // the fade in starts after the delay and the delay when the clock reaches `at`
+    let fade_start = at + duration_to_samples(delay, 1, CLOCK_SAMPLES_RATE);
+    let source = source::fade_in(source, fade, timing.starting_at(fade_start));
+    let source = source::wait(source, delay, timing.starting_at(at));
+    let source = source::start_at(source, state.clock.clone(), at);
+    let end = at + duration_to_samples(delay + fade, 1, CLOCK_SAMPLES_RATE);
+    state.music.transition_end = state.music.transition_end.max(end);
+    let source = state.music.bus.apply(source);
+
+    sink.append(source);
+
+    state.music.current = Some(Current {
+        queue: queue,
+        sink: sink,
+        fade_out: fade_out,
+        fade: fade,
+        position: position,
+        channels: channels,
+        samples_rate: samples_rate,
+    });
+}
+
+/// open and decode the music to check it at init or reload
+fn decode(source: &PathBuf, path: &PathBuf) -> Result<Decoder<File>,InitError> {
+    let file = try!(File::open(path).map_err(|e| InitError::FileOpenError(source.clone(), e)));
+    Decoder::new(file).map_err(|e| InitError::DecodeError(source.clone(), e))
+}
+
+/// the duration given by the decoder, the samples are counted if it doesn't know it
+fn total_duration(decoder: Decoder<File>) -> Option<Duration> {
+    decoder.get_total_duration().or_else(|| {
+        let rate = decoder.get_samples_rate() as u64 * decoder.get_channels() as u64;
+        if rate == 0 {
+            return None;
+        }
+        let samples = decoder.count() as u64;
+        Some(Duration::new(samples / rate, ((samples % rate) * 1_000_000_000 / rate) as u32))
+    })
+}
+
+/// decode the music from the file, it may have been modified or removed since init
+#[inline]
+fn open(path: &PathBuf) -> Result<Decoder<File>,InitError> {
+    decode(path, path)
+}
+
+#[inline]
+fn duration_to_samples(duration: Duration, channels: u16, samples_rate: u32) -> usize {
+    let frames = duration.as_secs() * samples_rate as u64
+        + duration.subsec_nanos() as u64 * samples_rate as u64 / 1_000_000_000;
+    frames as usize * channels as usize
+}
+
+/// play the music once and then play the next one in loop
+///
+/// the next music starts exactly when the first one ends, it is meant for
+/// an intro followed by a loop
+pub fn play_then(music: MusicId, next: MusicId) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let transition = state.music.transitions[music.0].unwrap_or(state.music.transition);
+    play_queue_inner(music.0, next.0, Duration::new(0, 0), transition, &mut state);
+}
+
+/// play the next music in loop when the current iteration of the current music ends
+///
+/// nothing is done if there is no music or if it is played from an url
+pub fn set_next(next: MusicId) {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    set_next_inner(next.0, &state);
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn set_next_inner(next: usize, state: &super::super::State) {
+    if let Some(queue) = current(state).and_then(|current| current.queue.as_ref()) {
+        queue.next.store(next, Relaxed);
+    }
+}
+
+/// play the music if is different from the current one
+pub fn play_or_continue(music: MusicId) {
+    play_or_continue_with_transition(music, None)
+}
+
+/// play the music if is different from the current one, the transition
+/// overrides the transition of the music and the current transition for this
+/// call only
+pub fn play_or_continue_with_transition(music: MusicId, transition: Option<MusicTransition>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    if index_inner(&state) != Some(music.0) {
+        let transition = transition
+            .or(state.music.transitions[music.0])
+            .unwrap_or(state.music.transition);
+        play_queue_inner(music.0, source::REPEAT, Duration::new(0, 0), transition, &mut state);
+    }
+}
+
+/// pause the music
+pub fn pause() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.bus.set_paused(true);
+}
+
+/// resume the music
+pub fn resume() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.bus.set_paused(false);
+}
+
+/// return whereas music is paused
+pub fn is_paused() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.bus.is_paused()
+}
+
+/// stop the music
+pub fn stop() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    stop_inner(&mut state);
+}
+
+#[inline]
+fn stop_inner(state: &mut super::super::State) {
+    let now = state.clock.load(Relaxed);
+    stop_at(now, state);
+}
+
+/// stop the music at the end of its current iteration instead of repeating it
+///
+/// nothing is done if there is no music or if it is played from an url
+pub fn stop_at_loop_end() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    set_next_inner(source::END, &state);
+}
+
+/// return the current music unless it ended, see `stop_at_loop_end`
+#[inline]
+fn current(state: &super::super::State) -> Option<&Current> {
+    state.music.current.as_ref().and_then(|current| {
+        match current.queue {
+            Some(ref queue) if queue.index.load(Relaxed) == source::END => None,
+            _ => Some(current),
+        }
+    })
+}
+
+/// fade out the current music once the audio clock reaches `at`
+#[inline]
+fn stop_at(at: usize, state: &mut super::super::State) {
+    if let Some(current) = state.music.current.take() {
+        let end = at + duration_to_samples(current.fade, 1, CLOCK_SAMPLES_RATE);
+        state.music.transition_end = state.music.transition_end.max(end);
+        current.fade_out.store(at, Relaxed);
+        current.sink.detach();
+    }
+}
+
+/// return whereas music is stopped
+pub fn is_stopped() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    current(&state).is_none()
+}
+
+/// return the status of the music
+///
+/// the transition is measured on the audio clock, the pause takes precedence
+pub fn status() -> MusicStatus {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    status_inner(&state)
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn status_inner(state: &super::super::State) -> MusicStatus {
+    let transition = state.clock.load(Relaxed) < state.music.transition_end;
+    if current(state).is_none() && !transition {
+        MusicStatus::Stop
+    } else if state.music.bus.is_paused() {
+        MusicStatus::Pause
+    } else if transition {
+        MusicStatus::Transition
+    } else {
+        MusicStatus::Play
+    }
+}
+
+/// return the current type of transition
+pub fn transition() -> MusicTransition {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.transition
+}
+
+/// set the type of transition between musics
+///
+/// musics with their own transition in the setting ignore it
+pub fn set_transition(trans: MusicTransition) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.music.transition = trans;
+}
+
+/// return the id of the current music if any
+pub fn index() -> Option<MusicId> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    index_inner(&state).map(MusicId)
+}
+
+#[inline]
+fn index_inner(state: &super::super::State) -> Option<usize> {
+    current(state)
+        .and_then(|current| current.queue.as_ref())
+        .map(|queue| queue.index.load(Relaxed))
+}
+
+/// return the position in the current music if any
+///
+/// the position keeps increasing when the music loops
+pub fn position() -> Option<Duration> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    position_inner(&state)
+}
+
+#[inline]
+fn position_inner(state: &super::super::State) -> Option<Duration> {
+    current(state).map(|current| {
+        let frames = (current.position.load(Relaxed) / current.channels as usize) as u64;
+        let rate = current.samples_rate as u64;
+        Duration::new(frames / rate, ((frames % rate) * 1_000_000_000 / rate) as u32)
+    })
+}
+
+/// return the number of musics of the setting
+pub fn count() -> usize {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.sources.len()
+}
+
+/// return the duration of the music
+///
+/// the files whose duration is not known by the decoder are decoded entirely at
+/// init and reload to count their samples
+pub fn duration(music: MusicId) -> Option<Duration> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.music.durations[music.0]
+}
+
+/// reload the music from the disk, if it is the current music it is restarted
+/// at its position using the transition
+///
+/// it is meant to iterate on the file while the game is running
+pub fn reload(music: MusicId) -> Result<(),InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    reload_inner(music.0, &mut state)
+}
+
+/// load a new music from the music directory and return its id
+///
+/// it is meant for prototypes and editors that start the audio player before
+/// the list of musics is known, see `baal::init_default`
+pub fn register(music: MusicSetting) -> Result<MusicId,InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    register_inner(music, &mut state)
+}
+
+#[doc(hidden)]
+pub fn register_inner(music: MusicSetting, state: &mut super::super::State) -> Result<MusicId,InitError> {
+    let index = try!(state.music.add(&state.setting, &music));
+    // kept so that reload and reset find the music
+    state.setting.musics.push(music);
+    debug!("baal: register music {}", index);
+    Ok(MusicId(index))
+}
+
+#[doc(hidden)]
+pub fn reload_inner(music: usize, state: &mut super::super::State) -> Result<(),InitError> {
+    let decoder = try!(decode(&state.setting.musics[music].path, &state.music.sources[music]));
+    state.music.durations[music] = total_duration(decoder);
+    debug!("baal: reload music {}", music);
+
+    if index_inner(state) == Some(music) {
+        let position = position_inner(state).unwrap_or(Duration::new(0, 0));
+        play_inner(music, position, state);
+    } else if let Some(queue) = current(state).and_then(|current| current.queue.as_ref()) {
+        // the music prepared may be the previous file, it is opened again by `update`
+        if let Ok(mut prepared) = queue.prepared.lock() {
+            *prepared = None;
+        }
+    }
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn report_inner(state: &super::super::State, status: &mut AudioStatus) {
+    status.music = status_inner(state);
+    status.music_index = index_inner(state).map(MusicId);
+    status.music_position = position_inner(state);
+    status.music_volume = state.music.bus.volume();
+}
+
+#[doc(hidden)]
+pub fn stats_inner(state: &super::super::State, stats: &mut Stats) {
+    stats.music = status_inner(state);
+    if current(state).is_some() {
+        stats.sinks += 1;
+    }
+}
+
+#[doc(hidden)]
+pub fn snapshot_inner(state: &super::super::State, snapshot: &mut AudioSnapshot) {
+    snapshot.music_volume = state.music.bus.volume();
+    snapshot.music_index = index_inner(state).map(MusicId);
+    snapshot.music_position = position_inner(state).unwrap_or(Duration::new(0, 0));
+    snapshot.music_paused = state.music.bus.is_paused();
+}
+
+/// open the music of the snapshot, it must be decoded up to its position with
+/// `Opened::skip` before it is given to `restore_inner`
+///
+/// none if the music doesn't exist, the snapshot may have been taken before a reset
+#[doc(hidden)]
+pub fn open_restore_inner(snapshot: &AudioSnapshot, state: &super::super::State) -> Option<Opened> {
+    snapshot.music_index
+        .and_then(|music| if music.0 < state.music.sources.len() { Some(music.0) } else { None })
+        .and_then(|music| open_inner(music, source::REPEAT, snapshot.music_position, state))
+}
+
+#[doc(hidden)]
+pub fn restore_inner(snapshot: &AudioSnapshot, opened: Option<Opened>, state: &mut super::super::State) {
+    let global_volume = state.global_volume;
+    state.music.bus.set_volume(snapshot.music_volume, global_volume);
+    state.music.bus.set_paused(snapshot.music_paused);
+    match (snapshot.music_index, opened) {
+        // the musics may have been reset since the music was opened
+        (Some(_), Some(opened)) => if let Some(&transition) = state.music.transitions.get(opened.music) {
+            let transition = transition.unwrap_or(state.music.transition);
+            play_opened(opened, transition, state);
+        },
+        // the music cannot be opened
+        (Some(_), None) => (),
+        (None, _) => stop_inner(state),
+    }
+}
@@ -0,0 +1,307 @@
+//! a music streamed from an url
+//!
+//! the file is downloaded and decoded in a background thread, the audio thread
+//! only takes the decoded samples so that a network stall doesn't block the
+//! other sounds: it plays silence until the samples are available
+
+use reqwest;
+use reqwest::header::ContentLength;
+
+use rodio::decoder::Decoder;
+use rodio::Source;
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::panic;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::super::InitError;
+
+/// the size of the chunks read from the connection
+const CHUNK: usize = 16 * 1024;
+
+/// the number of bytes kept behind the read position, older bytes are discarded
+/// so that endless streams don't fill the memory
+const KEPT: usize = 1024 * 1024;
+
+/// the number of samples decoded at once by the background thread
+const DECODED_CHUNK: usize = 4096;
+
+/// the duration of the samples decoded ahead of the audio thread
+const DECODED_AHEAD_SECS: usize = 4;
+
+/// the maximum number of samples taken at once by the audio thread
+const BLOCK: usize = 1024;
+
+/// a reader of the connection keeping the downloaded bytes so that the decoder
+/// can seek in them, it is only read by the background thread
+struct HttpReader {
+    response: reqwest::Response,
+    data: Vec<u8>,
+    /// the number of bytes discarded before `data`
+    offset: usize,
+    position: usize,
+    /// the length given by the server
+    length: Option<usize>,
+    done: bool,
+    errors: Arc<Mutex<Vec<String>>>,
+}
+
+impl HttpReader {
+    /// download until the bytes up to `end` are downloaded or the download is done
+    fn download(&mut self, end: usize) -> io::Result<()> {
+        let mut chunk = [0u8; CHUNK];
+        while !self.done && self.offset + self.data.len() < end {
+            match self.response.read(&mut chunk) {
+                Ok(0) => self.done = true,
+                Ok(len) => self.data.extend_from_slice(&chunk[..len]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    self.done = true;
+                    if let Ok(mut errors) = self.errors.lock() {
+                        errors.push(format!("the download is interrupted: {}", e));
+                    }
+                    return Err(e);
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let end = self.position + 1;
+        try!(self.download(end));
+        if self.position < self.offset {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "position discarded from the stream"));
+        }
+        let start = self.position - self.offset;
+        let len = cmp::min(buf.len(), self.data.len().saturating_sub(start));
+        buf[..len].copy_from_slice(&self.data[start..start + len]);
+        self.position += len;
+
+        let discarded = (start + len).saturating_sub(KEPT);
+        if discarded > KEPT {
+            self.data.drain(..discarded);
+            self.offset += discarded;
+        }
+        Ok(len)
+    }
+}
+
+impl Seek for HttpReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => position as i64,
+            SeekFrom::Current(delta) => self.position as i64 + delta,
+            SeekFrom::End(delta) => match self.length {
+                Some(length) => length as i64 + delta,
+                // an endless stream has no end to wait for
+                None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "the length of the stream is unknown")),
+            },
+        };
+        if position < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the stream"));
+        }
+        self.position = position as usize;
+        Ok(self.position as u64)
+    }
+}
+
+struct Queue {
+    samples: VecDeque<i16>,
+    done: bool,
+    stopped: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    /// notified when samples are decoded
+    decoded: Condvar,
+    /// notified when samples are taken by the audio thread
+    consumed: Condvar,
+}
+
+/// connect to the url and decode it in a background thread, block until
+/// `prebuffer` is decoded
+///
+/// the errors of the download are pushed to `errors`
+pub fn open(url: &str, prebuffer: Duration, errors: Arc<Mutex<Vec<String>>>) -> Result<UrlSource,InitError> {
+    let source = PathBuf::from(url);
+    let response = try!(reqwest::get(url).map_err(|e| InitError::FileOpenError(source.clone(), io::Error::new(io::ErrorKind::Other, e))));
+    if !response.status().is_success() {
+        let error = io::Error::new(io::ErrorKind::Other, format!("http status {}", response.status()));
+        return Err(InitError::FileOpenError(source, error));
+    }
+    let length = response.headers().get::<ContentLength>().map(|&ContentLength(length)| length as usize);
+
+    let reader = HttpReader {
+        response: response,
+        data: vec!(),
+        offset: 0,
+        position: 0,
+        length: length,
+        done: false,
+        errors: errors.clone(),
+    };
+    let mut decoder = try!(Decoder::new(reader).map_err(|e| InitError::DecodeError(source, e)));
+    let channels = decoder.get_channels().max(1);
+    let samples_rate = decoder.get_samples_rate();
+    let rate = channels as usize * samples_rate as usize;
+
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            samples: VecDeque::with_capacity(rate * DECODED_AHEAD_SECS),
+            done: false,
+            stopped: false,
+        }),
+        decoded: Condvar::new(),
+        consumed: Condvar::new(),
+    });
+
+    let decoding = shared.clone();
+    thread::spawn(move || {
+        let mut chunk = Vec::with_capacity(DECODED_CHUNK);
+        loop {
+            {
+                let mut queue = decoding.queue.lock().unwrap();
+                while !queue.stopped && queue.samples.len() >= rate * DECODED_AHEAD_SECS {
+                    queue = decoding.consumed.wait(queue).unwrap();
+                }
+                if queue.stopped {
+                    break;
+                }
+            }
+
+            // the decoder may wait for the network, the queue isn't locked meanwhile
+            let decoded = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                chunk.extend(decoder.by_ref().take(DECODED_CHUNK));
+            }));
+            if decoded.is_err() {
+                if let Ok(mut errors) = errors.lock() {
+                    errors.push("the decoder panicked".into());
+                }
+            }
+            let done = decoded.is_err() || chunk.len() < DECODED_CHUNK;
+
+            let mut queue = decoding.queue.lock().unwrap();
+            queue.samples.extend(chunk.drain(..));
+            queue.done = done;
+            decoding.decoded.notify_all();
+            if done {
+                break;
+            }
+        }
+    });
+
+    let prebuffer = prebuffer.as_secs() * rate as u64 + prebuffer.subsec_nanos() as u64 * rate as u64 / 1_000_000_000;
+    // the background thread doesn't decode more than the queue holds
+    let prebuffer = cmp::min(prebuffer as usize, rate * DECODED_AHEAD_SECS);
+    {
+        let mut queue = shared.queue.lock().unwrap();
+        while !queue.done && queue.samples.len() < prebuffer {
+            queue = shared.decoded.wait(queue).unwrap();
+        }
+    }
+
+    Ok(UrlSource {
+        shared: shared,
+        block: Vec::with_capacity(BLOCK),
+        position: 0,
+        ended: false,
+        channels: channels,
+        samples_rate: samples_rate,
+    })
+}
+
+/// the samples decoded by the background thread, silence is played while
+/// they are not available
+pub struct UrlSource {
+    shared: Arc<Shared>,
+    block: Vec<i16>,
+    position: usize,
+    ended: bool,
+    channels: u16,
+    samples_rate: u32,
+}
+
+impl UrlSource {
+    fn refill(&mut self) {
+        self.block.clear();
+        self.position = 0;
+        {
+            let mut queue = self.shared.queue.lock().unwrap();
+            // the block is made of whole frames
+            let len = cmp::min(queue.samples.len(), BLOCK) / self.channels as usize * self.channels as usize;
+            if len == 0 && queue.done {
+                self.ended = true;
+                return;
+            }
+            self.block.extend(queue.samples.drain(..len));
+        }
+        self.shared.consumed.notify_one();
+
+        if self.block.is_empty() {
+            // the decoding is late, a frame of silence is played
+            for _ in 0..self.channels {
+                self.block.push(0);
+            }
+        }
+    }
+}
+
+impl Iterator for UrlSource {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.ended {
+            return None;
+        }
+        if self.position == self.block.len() {
+            self.refill();
+            if self.ended {
+                return None;
+            }
+        }
+        let sample = self.block[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for UrlSource {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.samples_rate
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Drop for UrlSource {
+    fn drop(&mut self) {
+        // the background thread stops before decoding its next chunk
+        self.shared.queue.lock().unwrap().stopped = true;
+        self.shared.consumed.notify_all();
+    }
+}
@@ -0,0 +1,167 @@
+//! this module allow to bind game parameters to audio controls
+//!
+//! a parameter is a named value set with `baal::set_parameter`, bindings defined
+//! in the setting map it through a curve to a target:
+//!
+//! ```lua
+//! target_value = curve(parameter_value)
+//! ```
+//!
+//! parameters also drive the blend containers, see `effect::blend`
+//!
+//! parameters values are kept on reset and applied again
+
+use std::collections::HashMap;
+
+use super::InitError;
+use super::RAW_STATE;
+use super::Setting;
+use super::bus;
+use super::effect;
+
+/// a binding from a parameter to a target
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ParameterBinding {
+    /// the name of the parameter
+    pub parameter: String,
+
+    /// the control driven by the parameter
+    pub target: ParameterTarget,
+
+    /// piecewise linear curve of (parameter value, target value) points sorted by parameter value
+    pub curve: Vec<(f32,f32)>,
+}
+
+/// a control that can be driven by a parameter
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(rename_all = "snake_case"))]
+pub enum ParameterTarget {
+    /// the volume of the bus
+    BusVolume(String),
+    /// the cutoff frequency in Hz of the low pass filter of the bus
+    BusLowPass(String),
+    /// the pitch of the short effect
+    ShortPitch(usize),
+    /// the pitch of the persistent effect
+    PersistentPitch(usize),
+}
+
+#[doc(hidden)]
+pub struct Parameters {
+    bindings: Vec<ParameterBinding>,
+    values: HashMap<String, f32>,
+}
+
+impl Parameters {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<Parameters,InitError> {
+        for binding in &setting.parameter_bindings {
+            match binding.target {
+                ParameterTarget::BusVolume(ref name) | ParameterTarget::BusLowPass(ref name) => {
+                    if !bus::exists(name) {
+                        return Err(InitError::UnknownBus(name.clone()));
+                    }
+                }
+                ParameterTarget::ShortPitch(effect) => {
+                    if effect >= setting.short_effects.len() {
+                        return Err(InitError::UnknownShortEffect(effect));
+                    }
+                }
+                ParameterTarget::PersistentPitch(effect) => {
+                    if effect >= setting.persistent_effects.len() {
+                        return Err(InitError::UnknownPersistentEffect(effect));
+                    }
+                }
+            }
+        }
+
+        Ok(Parameters {
+            bindings: setting.parameter_bindings.clone(),
+            values: HashMap::new(),
+        })
+    }
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
+        let values = ::std::mem::replace(&mut self.values, HashMap::new());
+        *self = try!(Parameters::init(setting));
+        self.values = values;
+        Ok(())
+    }
+}
+
+/// set the value of the parameter and update the controls bound to it
+pub fn set_parameter(name: &str, value: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    set_parameter_inner(name, value, &mut state);
+}
+
+/// return the value of the parameter if it has been set
+pub fn parameter(name: &str) -> Option<f32> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.parameters.values.get(name).cloned()
+}
+
+#[doc(hidden)]
+pub fn set_parameter_inner(name: &str, value: f32, state: &mut super::State) {
+    state.parameters.values.insert(name.into(), value);
+    effect::update_blend_containers(name, value, state);
+
+    let global_volume = state.global_volume;
+    let bindings = state.parameters.bindings.iter()
+        .filter(|binding| binding.parameter == name)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for binding in bindings {
+        let target_value = curve(&binding.curve, value);
+        match binding.target {
+            ParameterTarget::BusVolume(ref bus_name) => {
+                bus::get_mut(bus_name, state).unwrap().set_volume(target_value, global_volume);
+            }
+            ParameterTarget::BusLowPass(ref bus_name) => {
+                bus::get_mut(bus_name, state).unwrap().set_low_pass(Some(target_value), global_volume);
+            }
+            ParameterTarget::ShortPitch(effect) => {
+                effect::short::set_pitch_inner(effect, target_value, state);
+            }
+            #[cfg(feature = "persistent")]
+            ParameterTarget::PersistentPitch(effect) => {
+                effect::persistent::set_pitch_inner(effect, target_value, state);
+            }
+            #[cfg(not(feature = "persistent"))]
+            ParameterTarget::PersistentPitch(_) => (),
+        }
+    }
+}
+
+/// apply again all the parameters
+#[doc(hidden)]
+pub fn reapply(state: &mut super::State) {
+    let values = state.parameters.values.clone();
+    for (name, value) in values {
+        set_parameter_inner(&name, value, state);
+    }
+}
+
+/// evaluate the piecewise linear curve of (x, y) points sorted by x
+///
+/// if x <= first x then first y
+///
+/// if x >= last x then last y
+///
+/// else y is interpolated between the two surrounding points
+///
+/// if there is no points then 1
+pub fn curve(points: &[(f32,f32)], x: f32) -> f32 {
+    match points.iter().position(|&(px, _)| x < px) {
+        Some(0) => points[0].1,
+        Some(i) => {
+            let (x0, y0) = points[i-1];
+            let (x1, y1) = points[i];
+            y0 + (y1-y0)*(x-x0)/(x1-x0)
+        }
+        None => points.last().map(|&(_, y)| y).unwrap_or(1.),
+    }
+}
@@ -0,0 +1,105 @@
+//! resolution of the effect, music and voice directories of the setting
+//!
+//! `~` components are expanded to the home directory, `$VAR` and `${VAR}` components
+//! to the value of the environment variable and `$EXE_DIR` to the directory of the
+//! executable, relative directories are then joined to `Setting::root` if any
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use super::InitError;
+use super::Setting;
+use super::voice::VoiceSetting;
+
+/// the variable expanded to the directory of the executable
+const EXE_DIR: &'static str = "EXE_DIR";
+
+/// return the setting with its directories resolved
+pub fn resolve_setting(setting: &Setting) -> Result<Setting,InitError> {
+    let root = match setting.root {
+        Some(ref root) => Some(try!(resolve(root, None))),
+        None => None,
+    };
+    let root = root.as_ref().map(|root| root.as_path());
+    Ok(Setting {
+        effect_dir: try!(resolve(&setting.effect_dir, root)),
+        music_dir: try!(resolve(&setting.music_dir, root)),
+        voice: VoiceSetting {
+            dir: try!(resolve(&setting.voice.dir, root)),
+            .. setting.voice.clone()
+        },
+        root: None,
+        .. setting.clone()
+    })
+}
+
+/// expand the components of the directory and join it to the root if relative
+fn resolve(dir: &Path, root: Option<&Path>) -> Result<PathBuf,InitError> {
+    let mut resolved = PathBuf::new();
+    for component in dir.components() {
+        let component = component.as_os_str();
+        let expanded = match component.to_str() {
+            Some("~") => env::home_dir()
+                .ok_or_else(|| "the home directory is unknown".to_string()),
+            Some(component) => match variable(component) {
+                Some(name) => expand(name),
+                None => Ok(PathBuf::from(component)),
+            },
+            None => Ok(PathBuf::from(component)),
+        };
+        // an absolute expansion replaces the previous components
+        resolved.push(try!(expanded.map_err(|reason| InitError::InvalidDirectory(dir.to_path_buf(), reason))));
+    }
+
+    match root {
+        Some(root) if resolved.is_relative() => Ok(root.join(resolved)),
+        _ => Ok(resolved),
+    }
+}
+
+/// return the name of the variable if the component is `$VAR` or `${VAR}`
+fn variable(component: &str) -> Option<&str> {
+    if component.starts_with("${") && component.ends_with('}') {
+        Some(&component[2..component.len() - 1])
+    } else if component.starts_with('$') {
+        Some(&component[1..])
+    } else {
+        None
+    }
+}
+
+fn expand(name: &str) -> Result<PathBuf,String> {
+    if name == EXE_DIR {
+        env::current_exe().ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+            .ok_or_else(|| "the directory of the executable is unknown".to_string())
+    } else {
+        env::var_os(name)
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("the environment variable {} is not set", name))
+    }
+}
+
+#[test]
+fn test_resolve() {
+    env::set_var("BAAL_TEST_ASSETS", "/opt/game/assets");
+    assert_eq!(resolve(Path::new("$BAAL_TEST_ASSETS/effects"), None).unwrap(),
+               PathBuf::from("/opt/game/assets/effects"));
+    assert_eq!(resolve(Path::new("${BAAL_TEST_ASSETS}/musics"), Some(Path::new("/root"))).unwrap(),
+               PathBuf::from("/opt/game/assets/musics"));
+    assert_eq!(resolve(Path::new("assets/effects"), Some(Path::new("/root"))).unwrap(),
+               PathBuf::from("/root/assets/effects"));
+    assert_eq!(resolve(Path::new("assets/effects"), None).unwrap(),
+               PathBuf::from("assets/effects"));
+    assert!(resolve(Path::new("$BAAL_TEST_UNSET/effects"), None).is_err());
+
+    let setting = Setting {
+        root: Some("/root".into()),
+        voice: VoiceSetting {
+            dir: "$BAAL_TEST_ASSETS/voices".into(),
+            .. Default::default()
+        },
+        .. Default::default()
+    };
+    assert_eq!(resolve_setting(&setting).unwrap().voice.dir, PathBuf::from("/opt/game/assets/voices"));
+}
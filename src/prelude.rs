@@ -0,0 +1,21 @@
+//! the types commonly needed to configure and drive baal
+//!
+//! ```ignore
+//! use baal::prelude::*;
+//! ```
+
+pub use super::{Setting, InitError, AudioEvent, Emitter};
+pub use super::effect::{DistanceModel, PersistentId, ShortId, EffectSetting, Load, Group, LodTier};
+pub use super::effect::blend::{BlendContainer, BlendLayer};
+pub use super::music::{MusicId, MusicSetting, MusicStatus, MusicTransition};
+pub use super::voice::{VoiceSetting, VoicePolicy};
+pub use super::event::EventAction;
+pub use super::parameter::{ParameterBinding, ParameterTarget};
+pub use super::focus::FocusPolicy;
+pub use super::watchdog::WatchdogSetting;
+pub use super::spatial::{RoomGraph, RoomId, Portal};
+pub use super::bus::{MixerSnapshot, BusMix, ReturnBus, VolumeCurve, AudioFilter, Equalizer};
+pub use super::backend::Backend;
+
+/// the rodio endpoint given to `baal::init_with_endpoint`
+pub use rodio::Endpoint;
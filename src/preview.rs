@@ -0,0 +1,152 @@
+//! this module allow to preview any audio file, it is meant for the asset
+//! browsers of editors
+//!
+//! the file is played once through the ui bus so that it is heard while the game
+//! is paused, one file is previewed at a time and the effects and musics of the
+//! setting are not modified
+//!
+//! the preview is stopped when the audio player is reset
+
+use rodio::decoder::Decoder;
+use rodio::Source;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::backend::{AudioBackend, AudioSink, Sink};
+use super::InitError;
+use super::RAW_STATE;
+use super::source;
+
+struct Current {
+    id: usize,
+    path: PathBuf,
+    /// the position the file was started at
+    start: Duration,
+    /// the number of samples played since start
+    played: Arc<AtomicUsize>,
+    ended: Arc<AtomicBool>,
+    /// the number of samples per second
+    rate: u64,
+    _sink: Sink,
+}
+
+#[doc(hidden)]
+pub struct Preview {
+    next_id: usize,
+    current: Option<Current>,
+}
+
+impl Preview {
+    #[doc(hidden)]
+    pub fn init() -> Preview {
+        Preview {
+            next_id: 0,
+            current: None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
+
+/// play the file from the beginning, the previous preview is stopped
+///
+/// the path is not relative to the effect or music directories
+pub fn play_file<P: AsRef<Path>>(path: P) -> Result<PreviewHandle,InitError> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let id = state.preview.next_id;
+    state.preview.next_id += 1;
+    try!(play_inner(id, path.as_ref().to_path_buf(), Duration::new(0, 0), &mut state));
+    Ok(PreviewHandle {
+        id: id,
+    })
+}
+
+fn play_inner(id: usize, path: PathBuf, start: Duration, state: &mut super::State) -> Result<(),InitError> {
+    let file = try!(File::open(&path).map_err(|e| InitError::FileOpenError(path.clone(), e)));
+    let decoder = try!(Decoder::new(file).map_err(|e| InitError::DecodeError(path.clone(), e)));
+
+    let played = Arc::new(AtomicUsize::new(0));
+    let ended = Arc::new(AtomicBool::new(false));
+    let rate = decoder.get_samples_rate() as u64 * decoder.get_channels() as u64;
+
+    let source = source::trim(decoder, start, None);
+    let source = source::counter(source, played.clone());
+    let source = state.effect.ui_bus.apply(source);
+    let sink = state.backend.new_sink();
+    sink.append(source::end_signal(source, ended.clone()));
+
+    // the previous sink is dropped and thus stopped
+    state.preview.current = Some(Current {
+        id: id,
+        path: path,
+        start: start,
+        played: played,
+        ended: ended,
+        rate: rate,
+        _sink: sink,
+    });
+    Ok(())
+}
+
+/// a file being previewed, see `play_file`
+///
+/// the handle does nothing once another file is previewed
+#[derive(Clone,Debug,PartialEq)]
+pub struct PreviewHandle {
+    id: usize,
+}
+
+impl PreviewHandle {
+    /// stop the preview
+    pub fn stop(&self) {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        if self.is_current(&state) {
+            state.preview.current = None;
+        }
+    }
+
+    /// restart the preview at the position, it is played until the end of the
+    /// file even if it had ended
+    ///
+    /// the file is decoded up to the position before this function returns
+    pub fn seek(&self, position: Duration) -> Result<(),InitError> {
+        let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+        let path = match state.preview.current {
+            Some(ref current) if current.id == self.id => current.path.clone(),
+            _ => return Ok(()),
+        };
+        play_inner(self.id, path, position, &mut state)
+    }
+
+    /// return the position in the file, none if the preview is stopped or ended
+    pub fn position(&self) -> Option<Duration> {
+        let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+        match state.preview.current {
+            Some(ref current) if current.id == self.id && !current.ended.load(Relaxed) => {
+                let played = current.played.load(Relaxed) as u64;
+                let rate = current.rate.max(1);
+                let played = Duration::new(played / rate, ((played % rate) * 1_000_000_000 / rate) as u32);
+                Some(current.start + played)
+            },
+            _ => None,
+        }
+    }
+
+    /// return whether the preview is playing
+    pub fn is_playing(&self) -> bool {
+        self.position().is_some()
+    }
+
+    fn is_current(&self, state: &super::State) -> bool {
+        state.preview.current.as_ref().map_or(false, |current| current.id == self.id)
+    }
+}
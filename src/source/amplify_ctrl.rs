@@ -6,6 +6,9 @@ use std::sync::Arc;
 use rodio::Sample;
 use rodio::Source;
 
+use super::block;
+use super::block::Block;
+
 /// Internal function that builds a `AmplifyCtrl` object.
 pub fn amplify_ctrl<I>(input: I, factor: Arc<AtomicUsize>) -> AmplifyCtrl<I>
                   where I: Source, I::Item: Sample
@@ -13,26 +16,38 @@ pub fn amplify_ctrl<I>(input: I, factor: Arc<AtomicUsize>) -> AmplifyCtrl<I>
     AmplifyCtrl {
         input: input,
         factor: factor,
+        block: Block::new(),
     }
 }
 
+/// amplify the input by blocks, the factor is loaded once per block
 #[derive(Clone, Debug)]
 pub struct AmplifyCtrl<I> where I: Source, I::Item: Sample {
     input: I,
     factor: Arc<AtomicUsize>,
+    block: Block,
 }
 
 impl<I> Iterator for AmplifyCtrl<I> where I: Source, I::Item: Sample {
-    type Item = I::Item;
+    type Item = f32;
 
     #[inline]
-    fn next(&mut self) -> Option<I::Item> {
-        self.input.next().map(|value| value.amplify(self.factor.load(Ordering::Relaxed) as f32 / 10_000f32))
+    fn next(&mut self) -> Option<f32> {
+        if self.block.remaining() == 0 {
+            self.block.refill(&mut self.input);
+            let factor = self.factor.load(Ordering::Relaxed) as f32 / 10_000f32;
+            if factor != 1. {
+                block::amplify(self.block.samples_mut(), factor);
+            }
+        }
+        self.block.next()
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.input.size_hint()
+        let buffered = self.block.remaining();
+        let (min, max) = self.input.size_hint();
+        (min + buffered, max.map(|max| max + buffered))
     }
 }
 
@@ -42,17 +57,29 @@ impl<I> ExactSizeIterator for AmplifyCtrl<I> where I: Source + ExactSizeIterator
 impl<I> Source for AmplifyCtrl<I> where I: Source, I::Item: Sample {
     #[inline]
     fn get_current_frame_len(&self) -> Option<usize> {
-        self.input.get_current_frame_len()
+        if self.block.remaining() > 0 {
+            Some(self.block.remaining())
+        } else {
+            self.input.get_current_frame_len()
+        }
     }
 
     #[inline]
     fn get_channels(&self) -> u16 {
-        self.input.get_channels()
+        if self.block.remaining() > 0 {
+            self.block.channels()
+        } else {
+            self.input.get_channels()
+        }
     }
 
     #[inline]
     fn get_samples_rate(&self) -> u32 {
-        self.input.get_samples_rate()
+        if self.block.remaining() > 0 {
+            self.block.samples_rate()
+        } else {
+            self.input.get_samples_rate()
+        }
     }
 
     #[inline]
@@ -60,3 +87,19 @@ impl<I> Source for AmplifyCtrl<I> where I: Source, I::Item: Sample {
         self.input.get_total_duration()
     }
 }
+
+#[test]
+fn test_amplify_ctrl() {
+    use rodio::buffer::SamplesBuffer;
+
+    let factor = Arc::new(AtomicUsize::new(5_000));
+    let mut amplify = amplify_ctrl(SamplesBuffer::new(2, 1000, vec!(1f32; 100)), factor.clone());
+    assert_eq!(amplify.next(), Some(0.5));
+
+    // the factor is loaded at the start of the next block
+    factor.store(20_000, Ordering::Relaxed);
+    let samples = amplify.collect::<Vec<_>>();
+    assert_eq!(samples.len(), 99);
+    assert!(samples[..block::BLOCK - 1].iter().all(|&sample| sample == 0.5));
+    assert!(samples[block::BLOCK - 1..].iter().all(|&sample| sample == 2.));
+}
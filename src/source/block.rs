@@ -0,0 +1,161 @@
+//! the blocks of samples processed at once by the gain stages
+//!
+//! rodio pulls the sources one sample at a time, a gain stage pulls a block from
+//! its input and applies its gain to the whole block in one pass, with SSE on
+//! x86_64, instead of loading its controls and multiplying on each sample
+
+use rodio::Sample;
+use rodio::Source;
+
+/// the maximum number of samples of a block
+pub const BLOCK: usize = 32;
+
+/// a block of samples pulled from a source, it doesn't go over a change of format
+#[derive(Clone, Debug)]
+pub struct Block {
+    samples: [f32; BLOCK],
+    len: usize,
+    position: usize,
+    /// the format of the block
+    channels: u16,
+    samples_rate: u32,
+}
+
+impl Block {
+    pub fn new() -> Block {
+        Block {
+            samples: [0.; BLOCK],
+            len: 0,
+            position: 0,
+            channels: 1,
+            samples_rate: 1,
+        }
+    }
+
+    /// pull the next block of whole frames from the input, it is empty once the
+    /// input ended
+    pub fn refill<I>(&mut self, input: &mut I) where I: Source, I::Item: Sample {
+        self.len = 0;
+        self.position = 0;
+        self.channels = input.get_channels().max(1);
+        self.samples_rate = input.get_samples_rate().max(1);
+
+        let channels = self.channels as usize;
+        let mut len = if channels <= BLOCK { BLOCK / channels * channels } else { BLOCK };
+        if let Some(frame_len) = input.get_current_frame_len() {
+            len = len.min(frame_len);
+        }
+        while self.len < len {
+            match input.next() {
+                Some(sample) => {
+                    self.samples[self.len] = sample.to_f32();
+                    self.len += 1;
+                },
+                None => break,
+            }
+        }
+    }
+
+    /// return the next sample of the block
+    #[inline]
+    pub fn next(&mut self) -> Option<f32> {
+        if self.position < self.len {
+            self.position += 1;
+            Some(self.samples[self.position - 1])
+        } else {
+            None
+        }
+    }
+
+    /// the number of samples of the block not returned yet
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.len - self.position
+    }
+
+    /// the samples of the block, it must be called before `next`
+    #[inline]
+    pub fn samples_mut(&mut self) -> &mut [f32] {
+        &mut self.samples[..self.len]
+    }
+
+    /// drop the samples after the first frames
+    #[inline]
+    pub fn truncate_frames(&mut self, frames: usize) {
+        self.len = self.len.min(frames * self.channels as usize);
+    }
+
+    #[inline]
+    pub fn frames(&self) -> usize {
+        self.len / self.channels as usize
+    }
+
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    pub fn samples_rate(&self) -> u32 {
+        self.samples_rate
+    }
+}
+
+/// multiply the samples by the gain
+#[inline]
+pub fn amplify(samples: &mut [f32], gain: f32) {
+    #[cfg(target_arch = "x86_64")]
+    let samples = {
+        use std::arch::x86_64::{_mm_loadu_ps, _mm_mul_ps, _mm_set1_ps, _mm_storeu_ps};
+
+        let len = samples.len() / 4 * 4;
+        let (vectors, rest) = samples.split_at_mut(len);
+        // sse is available on every x86_64 processor
+        unsafe {
+            let gains = _mm_set1_ps(gain);
+            for vector in vectors.chunks_mut(4) {
+                let pointer = vector.as_mut_ptr();
+                _mm_storeu_ps(pointer, _mm_mul_ps(_mm_loadu_ps(pointer), gains));
+            }
+        }
+        rest
+    };
+    for sample in samples {
+        *sample *= gain;
+    }
+}
+
+/// multiply the frames of the samples by a gain moving linearly: the frame `k`
+/// is multiplied by `start + step * k`
+#[inline]
+pub fn amplify_linear(samples: &mut [f32], channels: u16, start: f32, step: f32) {
+    for (k, frame) in samples.chunks_mut(channels.max(1) as usize).enumerate() {
+        let gain = start + step * k as f32;
+        for sample in frame {
+            *sample *= gain;
+        }
+    }
+}
+
+#[test]
+fn test_block() {
+    use rodio::buffer::SamplesBuffer;
+
+    let mut source = SamplesBuffer::new(3, 1000, (0..40).map(|i| i as f32).collect::<Vec<_>>());
+    let mut block = Block::new();
+    block.refill(&mut source);
+    // whole frames only
+    assert_eq!(block.frames(), 10);
+    assert_eq!(block.remaining(), 30);
+
+    amplify(block.samples_mut(), 2.);
+    assert_eq!(block.samples_mut()[29], 58.);
+    amplify_linear(block.samples_mut(), 3, 1., -0.5);
+    assert_eq!(&block.samples_mut()[..6], &[0., 2., 4., 3., 4., 5.]);
+
+    block.truncate_frames(1);
+    assert_eq!(block.next(), Some(0.));
+    assert_eq!(block.remaining(), 2);
+    block.refill(&mut source);
+    assert_eq!(block.remaining(), 10);
+}
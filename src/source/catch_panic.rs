@@ -0,0 +1,137 @@
+use std::panic;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// Internal function that builds a `CatchPanic` object.
+///
+/// the messages of the panics are pushed to errors
+pub fn catch_panic<I>(input: I, errors: Arc<Mutex<Vec<String>>>) -> CatchPanic<I>
+                  where I: Source, I::Item: Sample
+{
+    let channels = input.get_channels();
+    let samples_rate = input.get_samples_rate();
+    CatchPanic {
+        input: input,
+        errors: errors,
+        panicked: false,
+        channels: channels,
+        samples_rate: samples_rate,
+    }
+}
+
+/// end the input when it panics instead of killing the audio thread
+#[derive(Clone, Debug)]
+pub struct CatchPanic<I> where I: Source, I::Item: Sample {
+    input: I,
+    errors: Arc<Mutex<Vec<String>>>,
+    panicked: bool,
+    /// the format reported once the input panicked
+    channels: u16,
+    samples_rate: u32,
+}
+
+impl<I> Iterator for CatchPanic<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.panicked {
+            return None;
+        }
+        let input = &mut self.input;
+        match panic::catch_unwind(panic::AssertUnwindSafe(|| input.next())) {
+            Ok(next) => next,
+            Err(payload) => {
+                self.panicked = true;
+                let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                // the lock is never held while panicking
+                if let Ok(mut errors) = self.errors.lock() {
+                    errors.push(message);
+                }
+                None
+            },
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.panicked {
+            (0, Some(0))
+        } else {
+            self.input.size_hint()
+        }
+    }
+}
+
+impl<I> Source for CatchPanic<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        if self.panicked {
+            Some(0)
+        } else {
+            self.input.get_current_frame_len()
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        if self.panicked {
+            self.channels
+        } else {
+            self.input.get_channels()
+        }
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        if self.panicked {
+            self.samples_rate
+        } else {
+            self.input.get_samples_rate()
+        }
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Debug)]
+struct Panicking(usize);
+
+#[cfg(test)]
+impl Iterator for Panicking {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.0 == 0 {
+            panic!("decoder error");
+        }
+        self.0 -= 1;
+        Some(1.)
+    }
+}
+
+#[cfg(test)]
+impl Source for Panicking {
+    fn get_current_frame_len(&self) -> Option<usize> { None }
+    fn get_channels(&self) -> u16 { 1 }
+    fn get_samples_rate(&self) -> u32 { 44_100 }
+    fn get_total_duration(&self) -> Option<Duration> { None }
+}
+
+#[test]
+fn test_catch_panic() {
+    let errors = Arc::new(Mutex::new(vec!()));
+    let samples = catch_panic(Panicking(10), errors.clone()).collect::<Vec<_>>();
+    assert_eq!(samples.len(), 10);
+    assert_eq!(*errors.lock().unwrap(), vec!("decoder error".to_string()));
+}
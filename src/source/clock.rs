@@ -0,0 +1,58 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Source;
+
+/// Internal function that builds a `Clock` object.
+pub fn clock(samples_rate: u32, count: Arc<AtomicUsize>) -> Clock {
+    Clock {
+        samples_rate: samples_rate,
+        count: count,
+    }
+}
+
+/// an infinite silent mono source that counts the samples consumed
+#[derive(Clone, Debug)]
+pub struct Clock {
+    samples_rate: u32,
+    count: Arc<AtomicUsize>,
+}
+
+impl Iterator for Clock {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        Some(0.)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl Source for Clock {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        1
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.samples_rate
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// Internal function that builds a `Counter` object.
+pub fn counter<I>(input: I, count: Arc<AtomicUsize>) -> Counter<I>
+                  where I: Source, I::Item: Sample
+{
+    Counter {
+        input: input,
+        count: count,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Counter<I> where I: Source, I::Item: Sample {
+    input: I,
+    count: Arc<AtomicUsize>,
+}
+
+impl<I> Iterator for Counter<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.input.next();
+        if next.is_some() {
+            self.count.fetch_add(1, Ordering::Relaxed);
+        }
+        next
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Counter<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for Counter<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
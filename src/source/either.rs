@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// a source that is one of two sources, it allows to choose a source at runtime
+#[derive(Clone, Debug)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Iterator for Either<A, B> where A: Source, A::Item: Sample, B: Source<Item = A::Item> {
+    type Item = A::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<A::Item> {
+        match *self {
+            Either::Left(ref mut source) => source.next(),
+            Either::Right(ref mut source) => source.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self {
+            Either::Left(ref source) => source.size_hint(),
+            Either::Right(ref source) => source.size_hint(),
+        }
+    }
+}
+
+impl<A, B> Source for Either<A, B> where A: Source, A::Item: Sample, B: Source<Item = A::Item> {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        match *self {
+            Either::Left(ref source) => source.get_current_frame_len(),
+            Either::Right(ref source) => source.get_current_frame_len(),
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        match *self {
+            Either::Left(ref source) => source.get_channels(),
+            Either::Right(ref source) => source.get_channels(),
+        }
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        match *self {
+            Either::Left(ref source) => source.get_samples_rate(),
+            Either::Right(ref source) => source.get_samples_rate(),
+        }
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        match *self {
+            Either::Left(ref source) => source.get_total_duration(),
+            Either::Right(ref source) => source.get_total_duration(),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// Internal function that builds a `EndSignal` object.
+pub fn end_signal<I>(input: I, ended: Arc<AtomicBool>) -> EndSignal<I>
+                  where I: Source, I::Item: Sample
+{
+    EndSignal {
+        input: input,
+        ended: ended,
+        signaled: false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EndSignal<I> where I: Source, I::Item: Sample {
+    input: I,
+    ended: Arc<AtomicBool>,
+    signaled: bool,
+}
+
+impl<I> Iterator for EndSignal<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.input.next();
+        if next.is_none() && !self.signaled {
+            self.signaled = true;
+            self.ended.store(true, Ordering::Relaxed);
+        }
+        next
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+// a source dropped before its end, like by the null backend, is ended too, the
+// flag isn't stored again once signaled since it may be reused by the next sound
+impl<I> Drop for EndSignal<I> where I: Source, I::Item: Sample {
+    fn drop(&mut self) {
+        if !self.signaled {
+            self.ended.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<I> ExactSizeIterator for EndSignal<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for EndSignal<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
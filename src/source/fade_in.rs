@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use rodio::Sample;
+use rodio::Source;
+
+use super::Timing;
+
+/// Internal function that builds a `FadeIn` object.
+///
+/// with a clock timing the fade is measured on the clock from the start of the
+/// timing or from the first sample
+pub fn fade_in<I>(input: I, duration: Duration, timing: Timing) -> FadeIn<I>
+                  where I: Source, I::Item: Sample
+{
+    FadeIn {
+        input: input,
+        timing: timing,
+        elapsed_ns: 0,
+        total_ns: duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64,
+        factor: 0.,
+        channel: 0,
+        channels: 1,
+    }
+}
+
+/// amplify the input by a factor going from 0 to 1 during the duration
+#[derive(Clone, Debug)]
+pub struct FadeIn<I> where I: Source, I::Item: Sample {
+    input: I,
+    timing: Timing,
+    /// the duration of the samples played, used when the timing is the samples
+    elapsed_ns: u64,
+    total_ns: u64,
+    /// the factor of the current frame
+    factor: f32,
+    /// the position of the next sample in its frame
+    channel: u16,
+    channels: u16,
+}
+
+impl<I> Iterator for FadeIn<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.factor == 1. {
+            return self.input.next();
+        }
+        // the factor is stepped once per frame
+        if self.channel == 0 {
+            self.channels = self.input.get_channels().max(1);
+            let elapsed = match self.timing.elapsed_ns() {
+                Some(elapsed) => elapsed,
+                None => {
+                    let elapsed = self.elapsed_ns;
+                    self.elapsed_ns += 1_000_000_000 / self.input.get_samples_rate().max(1) as u64;
+                    elapsed
+                },
+            };
+            self.factor = if elapsed >= self.total_ns {
+                1.
+            } else {
+                elapsed as f32 / self.total_ns as f32
+            };
+        }
+        self.channel = (self.channel + 1) % self.channels;
+        let factor = self.factor;
+        self.input.next().map(|value| value.amplify(factor))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for FadeIn<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for FadeIn<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[test]
+fn test_fade_in() {
+    use rodio::buffer::SamplesBuffer;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::Relaxed;
+
+    let source = SamplesBuffer::new(2, 1000, vec![1f32; 8]);
+    let samples = fade_in(source, Duration::new(0, 2_000_000), Timing::Samples).collect::<Vec<_>>();
+    assert_eq!(samples, vec![0., 0., 0.5, 0.5, 1., 1., 1., 1.]);
+
+    // the clock ticks 1000 times per second
+    let clock = Arc::new(AtomicUsize::new(5));
+    let timing = Timing::Clock(clock.clone(), 1000, Some(4));
+    let mut source = fade_in(SamplesBuffer::new(2, 1000, vec![1f32; 8]), Duration::new(0, 2_000_000), timing);
+    assert_eq!(source.by_ref().take(4).collect::<Vec<_>>(), vec![0.5, 0.5, 0.5, 0.5]);
+    clock.store(6, Relaxed);
+    assert_eq!(source.collect::<Vec<_>>(), vec![1., 1., 1., 1.]);
+}
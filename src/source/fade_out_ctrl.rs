@@ -1,70 +1,147 @@
+use std::cmp;
 use std::time::Duration;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
 
 use rodio::Sample;
 use rodio::Source;
 
+use super::Timing;
+use super::block;
+use super::block::Block;
+
+/// the value of the start of the fade while it is not started
+pub const NO_FADE_OUT: usize = ::std::usize::MAX;
+
 /// Internal function that builds a `FadeOutCtrl` object.
-pub fn fade_out_ctrl<I>(input: I, duration: Duration, signal: Arc<AtomicBool>) -> FadeOutCtrl<I>
+///
+/// the fade starts once the clock reaches `at`, with a clock timing it is measured
+/// on the clock instead of the samples played
+pub fn fade_out_ctrl<I>(input: I, duration: Duration, clock: Arc<AtomicUsize>, at: Arc<AtomicUsize>, timing: Timing) -> FadeOutCtrl<I>
                   where I: Source, I::Item: Sample
 {
     let duration = duration.as_secs() * 1000000000 + duration.subsec_nanos() as u64;
 
     FadeOutCtrl {
         input: input,
-        signal: signal,
-        remaining_ns: duration as f32,
-        total_ns: duration as f32,
+        clock: clock,
+        at: at,
+        remaining_ns: duration,
+        total_ns: duration,
+        timing: timing,
+        block: Block::new(),
+        ended: false,
     }
 }
 
+/// fade out the input by blocks, the clock is compared to the start of the fade
+/// once per block
 #[derive(Clone, Debug)]
 pub struct FadeOutCtrl<I> where I: Source, I::Item: Sample {
     input: I,
-    signal: Arc<AtomicBool>,
-    remaining_ns: f32,
-    total_ns: f32,
+    clock: Arc<AtomicUsize>,
+    at: Arc<AtomicUsize>,
+    remaining_ns: u64,
+    total_ns: u64,
+    timing: Timing,
+    block: Block,
+    ended: bool,
+}
+
+impl<I> FadeOutCtrl<I> where I: Source, I::Item: Sample {
+    fn refill(&mut self) {
+        let at = self.at.load(Relaxed);
+        if self.clock.load(Relaxed) < at {
+            self.block.refill(&mut self.input);
+            return;
+        }
+        if let Timing::Clock(_, _, ref mut start) = self.timing {
+            *start = Some(at);
+        }
+        if let Some(elapsed) = self.timing.elapsed_ns() {
+            self.remaining_ns = self.total_ns.saturating_sub(elapsed);
+            if self.remaining_ns == 0 {
+                self.ended = true;
+                return;
+            }
+            // the clock doesn't move during the block
+            self.block.refill(&mut self.input);
+            let factor = self.remaining_ns as f32 / self.total_ns as f32;
+            block::amplify(self.block.samples_mut(), factor);
+            return;
+        }
+        if self.remaining_ns == 0 {
+            self.ended = true;
+            return;
+        }
+        self.block.refill(&mut self.input);
+
+        // the fade is stepped once per frame with the rate of the block, the
+        // frames after its end are dropped
+        let frame_ns = 1_000_000_000 / self.block.samples_rate() as u64;
+        let frames = if frame_ns == 0 {
+            self.block.frames()
+        } else {
+            cmp::min(self.block.frames() as u64, (self.remaining_ns + frame_ns - 1) / frame_ns) as usize
+        };
+        self.block.truncate_frames(frames);
+        let start = self.remaining_ns as f32 / self.total_ns as f32;
+        let step = -(frame_ns as f32) / self.total_ns as f32;
+        let channels = self.block.channels();
+        block::amplify_linear(self.block.samples_mut(), channels, start, step);
+        self.remaining_ns = self.remaining_ns.saturating_sub(frame_ns * frames as u64);
+    }
 }
 
 impl<I> Iterator for FadeOutCtrl<I> where I: Source, I::Item: Sample {
-    type Item = I::Item;
+    type Item = f32;
 
     #[inline]
-    fn next(&mut self) -> Option<I::Item> {
-        if !self.signal.load(Relaxed) {
-            self.input.next()
-        } else if self.remaining_ns > 0.0 {
-            let factor = self.remaining_ns / self.total_ns;
-            self.remaining_ns -= 1000000000.0 / (self.input.get_samples_rate() as f32 *
-                                                 self.get_channels() as f32);
-            self.input.next().map(|value| value.amplify(factor))
-        } else {
-            None
+    fn next(&mut self) -> Option<f32> {
+        if self.ended {
+            return None;
         }
+        if self.block.remaining() == 0 {
+            self.refill();
+        }
+        self.block.next()
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.input.size_hint()
+        let buffered = self.block.remaining();
+        let (min, max) = self.input.size_hint();
+        (min + buffered, max.map(|max| max + buffered))
     }
 }
 
 impl<I> Source for FadeOutCtrl<I> where I: Source, I::Item: Sample {
     #[inline]
     fn get_current_frame_len(&self) -> Option<usize> {
-        self.input.get_current_frame_len()
+        if self.block.remaining() > 0 {
+            Some(self.block.remaining())
+        } else {
+            self.input.get_current_frame_len()
+        }
     }
 
     #[inline]
     fn get_channels(&self) -> u16 {
-        self.input.get_channels()
+        if self.block.remaining() > 0 {
+            self.block.channels()
+        } else {
+            self.input.get_channels()
+        }
     }
 
     #[inline]
     fn get_samples_rate(&self) -> u32 {
-        self.input.get_samples_rate()
+        if self.block.remaining() > 0 {
+            self.block.samples_rate()
+        } else {
+            self.input.get_samples_rate()
+        }
     }
 
     #[inline]
@@ -72,3 +149,24 @@ impl<I> Source for FadeOutCtrl<I> where I: Source, I::Item: Sample {
         self.input.get_total_duration()
     }
 }
+
+#[test]
+fn test_fade_out_ctrl() {
+    use rodio::buffer::SamplesBuffer;
+
+    let source = SamplesBuffer::new(2, 1000, vec![1f32; 8]);
+    let clock = Arc::new(AtomicUsize::new(10));
+    let at = Arc::new(AtomicUsize::new(10));
+    let samples = fade_out_ctrl(source, Duration::new(0, 2_000_000), clock.clone(), at.clone(), Timing::Samples).collect::<Vec<_>>();
+    assert_eq!(samples, vec![1., 1., 0.5, 0.5]);
+
+    // the clock ticks 1000 times per second
+    let timing = Timing::Clock(clock.clone(), 1000, None);
+    let mut source = fade_out_ctrl(SamplesBuffer::new(2, 1000, vec![1f32; 1000]), Duration::new(0, 2_000_000), clock.clone(), at, timing);
+    // the clock doesn't move during the block
+    assert_eq!(source.by_ref().take(block::BLOCK).collect::<Vec<_>>(), vec![1.; block::BLOCK]);
+    clock.store(11, Relaxed);
+    assert_eq!(source.by_ref().take(block::BLOCK).collect::<Vec<_>>(), vec![0.5; block::BLOCK]);
+    clock.store(12, Relaxed);
+    assert_eq!(source.next(), None);
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use rodio::Sample;
+use rodio::Source;
+
+use super::super::bus::AudioFilter;
+
+/// the number of frames given at once to the filters
+const BLOCK_FRAMES: usize = 256;
+
+/// Internal function that builds a `Insert` object.
+pub fn insert<I>(input: I, filters: Vec<Box<AudioFilter>>) -> Insert<I>
+                  where I: Source, I::Item: Sample
+{
+    Insert {
+        input: input,
+        // a source without filters doesn't allocate the block
+        block: if filters.is_empty() { vec!() } else { Vec::with_capacity(BLOCK_FRAMES * 2) },
+        filters: filters,
+        position: 0,
+        channels: 1,
+        samples_rate: 1,
+    }
+}
+
+/// process the input by blocks with the filters, one after the other
+pub struct Insert<I> where I: Source, I::Item: Sample {
+    input: I,
+    filters: Vec<Box<AudioFilter>>,
+    block: Vec<f32>,
+    position: usize,
+    /// the format of the block
+    channels: u16,
+    samples_rate: u32,
+}
+
+impl<I> Insert<I> where I: Source, I::Item: Sample {
+    fn refill(&mut self) {
+        self.block.clear();
+        self.position = 0;
+        self.channels = self.input.get_channels().max(1);
+        self.samples_rate = self.input.get_samples_rate();
+
+        // the block doesn't go over a change of format
+        let mut len = BLOCK_FRAMES * self.channels as usize;
+        if let Some(frame_len) = self.input.get_current_frame_len() {
+            len = len.min(frame_len);
+        }
+        while self.block.len() < len {
+            match self.input.next() {
+                Some(sample) => self.block.push(sample.to_f32()),
+                None => break,
+            }
+        }
+
+        for filter in &mut self.filters {
+            filter.process(&mut self.block, self.channels, self.samples_rate);
+        }
+    }
+}
+
+impl<I> Iterator for Insert<I> where I: Source, I::Item: Sample {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.filters.is_empty() {
+            return self.input.next().map(|sample| sample.to_f32());
+        }
+        if self.position == self.block.len() {
+            self.refill();
+        }
+        let sample = self.block.get(self.position).cloned();
+        self.position += 1;
+        sample
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.block.len().saturating_sub(self.position);
+        let (min, max) = self.input.size_hint();
+        (min + buffered, max.map(|max| max + buffered))
+    }
+}
+
+impl<I> ExactSizeIterator for Insert<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for Insert<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        if self.position < self.block.len() {
+            Some(self.block.len() - self.position)
+        } else {
+            self.input.get_current_frame_len()
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        if self.position < self.block.len() {
+            self.channels
+        } else {
+            self.input.get_channels()
+        }
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        if self.position < self.block.len() {
+            self.samples_rate
+        } else {
+            self.input.get_samples_rate()
+        }
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[cfg(test)]
+struct Halve;
+
+#[cfg(test)]
+impl AudioFilter for Halve {
+    fn process(&mut self, samples: &mut [f32], _channels: u16, _samples_rate: u32) {
+        for sample in samples {
+            *sample /= 2.;
+        }
+    }
+
+    fn instance(&self) -> Box<AudioFilter> {
+        Box::new(Halve)
+    }
+}
+
+#[test]
+fn test_insert() {
+    use rodio::buffer::SamplesBuffer;
+
+    let samples = vec!(1f32; 1000);
+    let filtered = insert(SamplesBuffer::new(2, 44_100, samples.clone()), vec!(Box::new(Halve), Box::new(Halve)))
+        .collect::<Vec<_>>();
+    assert_eq!(filtered, vec!(0.25; 1000));
+
+    let unfiltered = insert(SamplesBuffer::new(2, 44_100, samples.clone()), vec!()).collect::<Vec<_>>();
+    assert_eq!(unfiltered, samples);
+}
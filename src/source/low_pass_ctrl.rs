@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+const PRECISION: u32 = 10_000;
+
+/// Internal function that builds a `LowPassCtrl` object.
+///
+/// cutoff is the frequency in Hz, the filter is bypassed if it is over the nyquist frequency
+pub fn low_pass_ctrl<I>(input: I, cutoff: Arc<AtomicUsize>) -> LowPassCtrl<I>
+                  where I: Source, I::Item: Sample
+{
+    LowPassCtrl {
+        input: input,
+        cutoff: cutoff,
+        current_cutoff: 0,
+        current_samples_rate: 0,
+        factor: PRECISION,
+        previous: vec!(),
+        channel: 0,
+    }
+}
+
+/// one pole low pass filter
+#[derive(Clone, Debug)]
+pub struct LowPassCtrl<I> where I: Source, I::Item: Sample {
+    input: I,
+    cutoff: Arc<AtomicUsize>,
+    current_cutoff: usize,
+    current_samples_rate: u32,
+    factor: u32,
+    previous: Vec<I::Item>,
+    channel: usize,
+}
+
+impl<I> Iterator for LowPassCtrl<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let cutoff = self.cutoff.load(Ordering::Relaxed);
+        let samples_rate = self.input.get_samples_rate();
+        let channels = self.input.get_channels() as usize;
+
+        if cutoff != self.current_cutoff || samples_rate != self.current_samples_rate {
+            self.current_cutoff = cutoff;
+            self.current_samples_rate = samples_rate;
+            self.factor = if cutoff.saturating_mul(2) >= samples_rate as usize {
+                PRECISION
+            } else {
+                let a = 1. - (-2. * PI * cutoff as f32 / samples_rate as f32).exp();
+                (a * PRECISION as f32) as u32
+            };
+        }
+
+        if self.previous.len() != channels {
+            self.previous = vec!(I::Item::zero_value(); channels);
+            self.channel = 0;
+        }
+
+        self.input.next().map(|value| {
+            let filtered = if self.factor == PRECISION {
+                value
+            } else {
+                Sample::lerp(self.previous[self.channel], value, self.factor, PRECISION)
+            };
+            self.previous[self.channel] = filtered;
+            self.channel = (self.channel + 1) % channels;
+            filtered
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for LowPassCtrl<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for LowPassCtrl<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
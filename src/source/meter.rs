@@ -0,0 +1,154 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+use super::block::BLOCK;
+
+/// the precision of the energy, it is stored in nanoseconds of full scale signal
+const ENERGY_PRECISION: f32 = 1_000_000_000f32;
+
+/// the levels measured by meters since the last reset
+///
+/// the peak is stored * 10_000 and the energy is the sum of the squared samples
+/// divided by the rate of samples, so that energies of sources played at the same
+/// time can be summed
+#[derive(Debug, Default)]
+pub struct MeterLevels {
+    pub peak: AtomicUsize,
+    pub energy: AtomicUsize,
+}
+
+impl MeterLevels {
+    /// return the peak and the energy in seconds of full scale signal and reset them
+    pub fn take(&self) -> (f32, f32) {
+        let peak = self.peak.swap(0, Ordering::Relaxed) as f32 / 10_000f32;
+        let energy = self.energy.swap(0, Ordering::Relaxed) as f32 / ENERGY_PRECISION;
+        (peak, energy)
+    }
+}
+
+/// Internal function that builds a `Meter` object.
+pub fn meter<I>(input: I, levels: Arc<MeterLevels>) -> Meter<I>
+                  where I: Source, I::Item: Sample
+{
+    Meter {
+        input: input,
+        levels: levels,
+        energy: 0.,
+        peak: 0.,
+        samples: 0,
+    }
+}
+
+/// the levels are accumulated over a block of samples before being added to
+/// the shared ones, so that quiet signals aren't lost to the precision
+#[derive(Clone, Debug)]
+pub struct Meter<I> where I: Source, I::Item: Sample {
+    input: I,
+    levels: Arc<MeterLevels>,
+    /// the energy of the block in seconds of full scale signal, with the part
+    /// below the precision left from the previous blocks
+    energy: f64,
+    peak: f32,
+    /// the number of samples of the block
+    samples: usize,
+}
+
+impl<I> Meter<I> where I: Source, I::Item: Sample {
+    /// add the levels of the block to the shared ones
+    fn flush(&mut self) {
+        let energy = (self.energy * ENERGY_PRECISION as f64) as usize;
+        self.levels.energy.fetch_add(energy, Ordering::Relaxed);
+        self.energy -= energy as f64 / ENERGY_PRECISION as f64;
+
+        let peak = (self.peak * 10_000f32) as usize;
+        let mut current = self.levels.peak.load(Ordering::Relaxed);
+        while peak > current {
+            let previous = self.levels.peak.compare_and_swap(current, peak, Ordering::Relaxed);
+            if previous == current {
+                break;
+            }
+            current = previous;
+        }
+        self.peak = 0.;
+        self.samples = 0;
+    }
+}
+
+impl<I> Iterator for Meter<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.input.next();
+        match next {
+            Some(value) => {
+                let value = value.to_f32();
+                // silence, like paused sounds, doesn't change the levels
+                if value == 0. {
+                    return next;
+                }
+                let rate = self.input.get_samples_rate().max(1) as f64 * self.input.get_channels().max(1) as f64;
+                self.energy += value as f64 * value as f64 / rate;
+                self.peak = self.peak.max(value.abs());
+                self.samples += 1;
+                if self.samples >= BLOCK {
+                    self.flush();
+                }
+            },
+            None => {
+                if self.samples > 0 {
+                    self.flush();
+                }
+            },
+        }
+        next
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Meter<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for Meter<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[test]
+fn test_meter() {
+    use rodio::buffer::SamplesBuffer;
+
+    // a quiet signal adds less than the precision on each sample
+    let levels = Arc::new(MeterLevels::default());
+    let source = SamplesBuffer::new(1, 1000, vec![0.0005f32; 1000]);
+    meter(source, levels.clone()).count();
+    let (peak, energy) = levels.take();
+    assert_eq!(peak, 0.0005);
+    assert!((energy - 0.00000025).abs() < 0.00000001);
+}
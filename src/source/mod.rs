@@ -1,9 +1,52 @@
+mod block;
 mod amplify_ctrl;
+mod ramp_ctrl;
 mod play_pause_ctrl;
 mod fade_out_ctrl;
+mod fade_in;
 mod wait;
+mod start_at;
+mod trim;
+mod end_signal;
+mod counter;
+mod clock;
+mod low_pass_ctrl;
+mod speed_ctrl;
+mod stop_ctrl;
+mod meter;
+mod catch_panic;
+mod either;
+#[cfg(feature = "music")]
+mod music_queue;
+mod tap;
+mod insert;
+mod reverb;
+mod pool;
+#[cfg(feature = "persistent")]
+mod stream_loop;
 
 pub use self::amplify_ctrl::{amplify_ctrl, AmplifyCtrl};
+pub use self::ramp_ctrl::{ramp_ctrl, RampCtrl};
 pub use self::play_pause_ctrl::{play_pause_ctrl, PlayPauseCtrl};
-pub use self::fade_out_ctrl::{fade_out_ctrl, FadeOutCtrl};
-pub use self::wait::{wait, Wait};
+pub use self::fade_out_ctrl::{fade_out_ctrl, FadeOutCtrl, NO_FADE_OUT};
+pub use self::fade_in::{fade_in, FadeIn};
+pub use self::wait::{wait, Wait, Timing};
+pub use self::start_at::{start_at, StartAt};
+pub use self::trim::{trim, Trim};
+pub use self::end_signal::{end_signal, EndSignal};
+pub use self::counter::{counter, Counter};
+pub use self::clock::{clock, Clock};
+pub use self::low_pass_ctrl::{low_pass_ctrl, LowPassCtrl};
+pub use self::speed_ctrl::{speed_ctrl, SpeedCtrl};
+pub use self::stop_ctrl::{stop_ctrl, StopCtrl, PLAYING};
+pub use self::meter::{meter, Meter, MeterLevels};
+pub use self::catch_panic::{catch_panic, CatchPanic};
+pub use self::either::Either;
+#[cfg(feature = "music")]
+pub use self::music_queue::{music_queue, MusicQueue, Prepared, REPEAT, END};
+pub use self::tap::{tap, Tap, SendBuffer, Sends};
+pub use self::insert::{insert, Insert};
+pub use self::reverb::{reverb, Reverb};
+pub use self::pool::{pool, Pool, PoolSources};
+#[cfg(feature = "persistent")]
+pub use self::stream_loop::{stream_loop, StreamLoop};
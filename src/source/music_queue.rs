@@ -0,0 +1,159 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+
+use rodio::Sample;
+use rodio::Source;
+
+/// the value of `next` repeating the current music
+pub const REPEAT: usize = ::std::usize::MAX;
+
+/// the value of `next` ending the source at the end of the current music,
+/// `index` is set to it once the source has ended
+pub const END: usize = ::std::usize::MAX - 1;
+
+/// the music played after the current one: its index, whether it starts, as
+/// opposed to repeats, and its source
+pub type Prepared<S> = Arc<Mutex<Option<(usize, bool, S)>>>;
+
+/// Internal function that builds a `MusicQueue` object.
+///
+/// the input is the start of the music `index`, at its end the music `next` is
+/// played and then repeated, the position is reset when the music changes
+///
+/// the sources of the following musics are opened by the caller in `prepared`
+pub fn music_queue<S>(input: S, prepared: Prepared<S>, index: Arc<AtomicUsize>, next: Arc<AtomicUsize>, position: Arc<AtomicUsize>) -> MusicQueue<S>
+    where S: Source, S::Item: Sample
+{
+    MusicQueue {
+        input: input,
+        prepared: prepared,
+        index: index,
+        next: next,
+        position: position,
+        silence: 0,
+    }
+}
+
+/// play musics one after the other
+///
+/// silence is played while the following music isn't prepared
+pub struct MusicQueue<S> where S: Source, S::Item: Sample {
+    input: S,
+    prepared: Prepared<S>,
+    index: Arc<AtomicUsize>,
+    next: Arc<AtomicUsize>,
+    position: Arc<AtomicUsize>,
+    /// the number of samples of silence remaining in the frame
+    silence: u16,
+}
+
+impl<S> Iterator for MusicQueue<S> where S: Source, S::Item: Sample {
+    type Item = S::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<S::Item> {
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(S::Item::zero_value());
+        }
+        if let Some(value) = self.input.next() {
+            return Some(value);
+        }
+        let (music, start) = match self.next.load(Relaxed) {
+            END => {
+                self.index.store(END, Relaxed);
+                return None;
+            },
+            REPEAT => (self.index.load(Relaxed), false),
+            next => (next, true),
+        };
+
+        // the audio thread doesn't wait for the caller
+        let prepared = match self.prepared.try_lock() {
+            Ok(mut prepared) => match prepared.take() {
+                Some((prepared_music, prepared_start, input)) => {
+                    if prepared_music == music && prepared_start == start {
+                        Some(input)
+                    } else {
+                        *prepared = Some((prepared_music, prepared_start, input));
+                        None
+                    }
+                },
+                None => None,
+            },
+            Err(_) => None,
+        };
+
+        match prepared {
+            Some(input) => {
+                if start {
+                    self.next.store(REPEAT, Relaxed);
+                    self.index.store(music, Relaxed);
+                    self.position.store(0, Relaxed);
+                }
+                self.input = input;
+                self.next()
+            },
+            None => {
+                self.silence = self.input.get_channels().max(1) - 1;
+                Some(S::Item::zero_value())
+            },
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.input.size_hint().0, None)
+    }
+}
+
+impl<S> Source for MusicQueue<S> where S: Source, S::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        if self.silence > 0 {
+            Some(self.silence as usize)
+        } else {
+            self.input.get_current_frame_len()
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[test]
+fn test_music_queue() {
+    use rodio::buffer::SamplesBuffer;
+
+    let prepared = Arc::new(Mutex::new(None));
+    let index = Arc::new(AtomicUsize::new(0));
+    let next = Arc::new(AtomicUsize::new(1));
+    let position = Arc::new(AtomicUsize::new(0));
+    let mut queue = music_queue(SamplesBuffer::new(1, 1000, vec!(1f32; 2)), prepared.clone(), index.clone(), next.clone(), position);
+    assert_eq!(queue.by_ref().take(2).collect::<Vec<_>>(), vec!(1., 1.));
+
+    // silence while the next music isn't prepared
+    assert_eq!(queue.next(), Some(0.));
+    *prepared.lock().unwrap() = Some((1, true, SamplesBuffer::new(1, 1000, vec!(2f32; 2))));
+    assert_eq!(queue.by_ref().take(2).collect::<Vec<_>>(), vec!(2., 2.));
+    assert_eq!(index.load(Relaxed), 1);
+    assert_eq!(next.load(Relaxed), REPEAT);
+
+    next.store(END, Relaxed);
+    assert_eq!(queue.next(), None);
+    assert_eq!(index.load(Relaxed), END);
+}
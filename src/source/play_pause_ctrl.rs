@@ -16,6 +16,8 @@ pub fn play_pause_ctrl<I>(input: I, pause: Arc<AtomicBool>) -> PlayPauseCtrl<I>
     }
 }
 
+/// output silence while paused, the input isn't pulled so it isn't decoded
+/// and resumes where it was
 #[derive(Clone, Debug)]
 pub struct PlayPauseCtrl<I> where I: Source, I::Item: Sample {
     input: I,
@@ -0,0 +1,101 @@
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+
+use rodio::Source;
+
+/// the sources waiting to be mixed by a pool, they are moved to the pool at its
+/// next frame
+pub type PoolSources = Mutex<Vec<Box<Iterator<Item = f32> + Send>>>;
+
+/// Internal function that builds a `Pool` object.
+///
+/// the sources added must have the channels and the samples rate of the pool
+pub fn pool(channels: u16, samples_rate: u32, input: Arc<PoolSources>) -> Pool {
+    Pool {
+        input: input,
+        sources: vec!(),
+        channels: channels.max(1),
+        samples_rate: samples_rate.max(1),
+        channel: 0,
+    }
+}
+
+/// mix the sources added to its input until they end, it never ends and plays
+/// silence while it has no source
+pub struct Pool {
+    input: Arc<PoolSources>,
+    sources: Vec<Box<Iterator<Item = f32> + Send>>,
+    channels: u16,
+    samples_rate: u32,
+    /// the position of the next sample in its frame
+    channel: u16,
+}
+
+impl Iterator for Pool {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        // sources are added between frames, the audio thread doesn't wait for them
+        if self.channel == 0 {
+            if let Ok(mut input) = self.input.try_lock() {
+                self.sources.extend(input.drain(..));
+            }
+        }
+        self.channel = (self.channel + 1) % self.channels;
+
+        let mut value = 0.;
+        let mut i = 0;
+        while i < self.sources.len() {
+            match self.sources[i].next() {
+                Some(sample) => {
+                    value += sample;
+                    i += 1;
+                },
+                // the sources end between frames
+                None => {
+                    drop(self.sources.swap_remove(i));
+                },
+            }
+        }
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl Source for Pool {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.samples_rate
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[test]
+fn test_pool() {
+    let input = Arc::new(Mutex::new(vec!()));
+    let mut pool = pool(2, 1000, input.clone());
+    assert_eq!(pool.by_ref().take(2).collect::<Vec<_>>(), vec![0., 0.]);
+
+    input.lock().unwrap().push(Box::new(vec![1f32, 2.].into_iter()) as Box<Iterator<Item = f32> + Send>);
+    input.lock().unwrap().push(Box::new(vec![1f32, 1., 1., 1.].into_iter()));
+    assert_eq!(pool.by_ref().take(6).collect::<Vec<_>>(), vec![2., 3., 1., 1., 0., 0.]);
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+use super::block;
+use super::block::Block;
+
+/// Internal function that builds a `RampCtrl` object.
+///
+/// the factor moves toward the target by 1 per ramp duration, a null duration
+/// applies the target immediately
+pub fn ramp_ctrl<I>(input: I, target: Arc<AtomicUsize>, ramp: Duration) -> RampCtrl<I>
+                  where I: Source, I::Item: Sample
+{
+    let current = target.load(Ordering::Relaxed) as f32 / 10_000f32;
+    RampCtrl {
+        input: input,
+        target: target,
+        ramp_secs: ramp.as_secs() as f32 + ramp.subsec_nanos() as f32 / 1_000_000_000f32,
+        current: current,
+        block: Block::new(),
+    }
+}
+
+/// amplify the input by a factor ramping toward a target, the target is loaded
+/// once per block
+#[derive(Clone, Debug)]
+pub struct RampCtrl<I> where I: Source, I::Item: Sample {
+    input: I,
+    target: Arc<AtomicUsize>,
+    ramp_secs: f32,
+    /// the factor of the last frame
+    current: f32,
+    block: Block,
+}
+
+impl<I> RampCtrl<I> where I: Source, I::Item: Sample {
+    fn refill(&mut self) {
+        self.block.refill(&mut self.input);
+        let target = self.target.load(Ordering::Relaxed) as f32 / 10_000f32;
+        let frames = self.block.frames();
+        if self.ramp_secs <= 0. || self.current == target {
+            self.current = target;
+            if target != 1. {
+                block::amplify(self.block.samples_mut(), target);
+            }
+            return;
+        }
+
+        // the factor is stepped once per frame until it reaches the target
+        let step = 1. / (self.ramp_secs * self.block.samples_rate() as f32);
+        let steps = ((target - self.current).abs() / step).ceil() as usize;
+        let step = if self.current < target { step } else { -step };
+        let ramped = frames.min(steps - 1);
+        let channels = self.block.channels() as usize;
+        {
+            let (ramping, reached) = self.block.samples_mut().split_at_mut(ramped * channels);
+            block::amplify_linear(ramping, channels as u16, self.current + step, step);
+            block::amplify(reached, target);
+        }
+        self.current = if frames >= steps { target } else { self.current + step * frames as f32 };
+    }
+}
+
+impl<I> Iterator for RampCtrl<I> where I: Source, I::Item: Sample {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.block.remaining() == 0 {
+            self.refill();
+        }
+        self.block.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.block.remaining();
+        let (min, max) = self.input.size_hint();
+        (min + buffered, max.map(|max| max + buffered))
+    }
+}
+
+impl<I> ExactSizeIterator for RampCtrl<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for RampCtrl<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        if self.block.remaining() > 0 {
+            Some(self.block.remaining())
+        } else {
+            self.input.get_current_frame_len()
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        if self.block.remaining() > 0 {
+            self.block.channels()
+        } else {
+            self.input.get_channels()
+        }
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        if self.block.remaining() > 0 {
+            self.block.samples_rate()
+        } else {
+            self.input.get_samples_rate()
+        }
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[test]
+fn test_ramp_ctrl() {
+    use rodio::buffer::SamplesBuffer;
+
+    let target = Arc::new(AtomicUsize::new(0));
+    let mut ramp = ramp_ctrl(SamplesBuffer::new(2, 1000, vec!(1f32; 4000)), target.clone(), Duration::from_millis(1000));
+    assert_eq!(ramp.next(), Some(0.));
+
+    // the gain reaches 1 after one second: 1000 frames, the target is loaded at
+    // the start of the next block
+    target.store(10_000, Ordering::Relaxed);
+    let samples = ramp.skip(block::BLOCK - 1).collect::<Vec<_>>();
+    assert!(samples[0] > 0. && samples[0] < 0.01);
+    assert!((samples[1000] - 0.5).abs() < 0.01);
+    assert_eq!(samples[2500], 1.);
+}
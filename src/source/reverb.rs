@@ -0,0 +1,108 @@
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use rodio::Source;
+
+use super::tap::{SendBuffer, RETURN_SAMPLES_RATE};
+
+/// the delays of the comb filters in frames, tuned for 44100Hz
+const COMBS: [usize; 4] = [1557, 1617, 1491, 1422];
+
+/// the delays of the all pass filters in frames, tuned for 44100Hz
+const ALL_PASSES: [usize; 2] = [556, 225];
+
+/// Internal function that builds a `Reverb` object.
+pub fn reverb(buffer: Arc<Mutex<SendBuffer>>, decay: f32) -> Reverb {
+    Reverb {
+        buffer: buffer,
+        decay: decay,
+        combs: COMBS.iter().map(|&len| DelayLine::new(len)).collect(),
+        all_passes: ALL_PASSES.iter().map(|&len| DelayLine::new(len)).collect(),
+    }
+}
+
+#[derive(Clone, Debug)]
+struct DelayLine {
+    line: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(len: usize) -> DelayLine {
+        DelayLine {
+            line: vec!(0.; len),
+            position: 0,
+        }
+    }
+
+    /// return the delayed value and push the new one
+    fn swap(&mut self, value: f32) -> f32 {
+        let delayed = self.line[self.position];
+        self.line[self.position] = value;
+        self.position = (self.position + 1) % self.line.len();
+        delayed
+    }
+}
+
+/// an infinite mono source playing the sounds sent to a return through a
+/// Schroeder reverb
+#[derive(Clone, Debug)]
+pub struct Reverb {
+    buffer: Arc<Mutex<SendBuffer>>,
+    decay: f32,
+    combs: Vec<DelayLine>,
+    all_passes: Vec<DelayLine>,
+}
+
+impl Iterator for Reverb {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        let input = self.buffer.lock().unwrap().pop();
+
+        let mut output = 0.;
+        for comb in &mut self.combs {
+            let delayed = comb.line[comb.position];
+            comb.swap(input + delayed * self.decay);
+            output += delayed;
+        }
+        output /= self.combs.len() as f32;
+
+        for all_pass in &mut self.all_passes {
+            let delayed = all_pass.line[all_pass.position];
+            let value = output + delayed * 0.5;
+            all_pass.swap(value);
+            output = delayed - value * 0.5;
+        }
+        Some(output)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl Source for Reverb {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        1
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        RETURN_SAMPLES_RATE
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
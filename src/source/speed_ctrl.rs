@@ -0,0 +1,85 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// number of frames between two loads of the speed
+const BLOCK_FRAMES: usize = 1024;
+
+/// Internal function that builds a `SpeedCtrl` object.
+pub fn speed_ctrl<I>(input: I, speed: Arc<AtomicUsize>) -> SpeedCtrl<I>
+                  where I: Source, I::Item: Sample
+{
+    let remaining = BLOCK_FRAMES * input.get_channels() as usize;
+    let factor = speed.load(Ordering::Relaxed) as f32 / 10_000f32;
+
+    SpeedCtrl {
+        input: input,
+        speed: speed,
+        factor: factor,
+        remaining: remaining,
+    }
+}
+
+/// change the speed by changing the samples rate
+///
+/// the speed is loaded once per block so that frames reported to the output
+/// have a constant samples rate
+#[derive(Clone, Debug)]
+pub struct SpeedCtrl<I> where I: Source, I::Item: Sample {
+    input: I,
+    speed: Arc<AtomicUsize>,
+    factor: f32,
+    remaining: usize,
+}
+
+impl<I> Iterator for SpeedCtrl<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.input.next();
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.factor = self.speed.load(Ordering::Relaxed) as f32 / 10_000f32;
+            self.remaining = BLOCK_FRAMES * self.input.get_channels() as usize;
+        }
+        next
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for SpeedCtrl<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for SpeedCtrl<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        match self.input.get_current_frame_len() {
+            Some(len) => Some(len.min(self.remaining)),
+            None => Some(self.remaining),
+        }
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        ((self.input.get_samples_rate() as f32 * self.factor) as u32).max(1)
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
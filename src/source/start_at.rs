@@ -0,0 +1,79 @@
+use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// Internal function that builds a `StartAt` object.
+pub fn start_at<I>(input: I, clock: Arc<AtomicUsize>, at: usize) -> StartAt<I>
+                  where I: Source, I::Item: Sample
+{
+    StartAt {
+        input: input,
+        clock: clock,
+        at: at,
+        started: false,
+        channel: 0,
+    }
+}
+
+/// play silence until the clock reaches the value, then play the input
+#[derive(Clone, Debug)]
+pub struct StartAt<I> where I: Source, I::Item: Sample {
+    input: I,
+    clock: Arc<AtomicUsize>,
+    at: usize,
+    started: bool,
+    /// the position of the next sample in its frame
+    channel: u16,
+}
+
+impl<I> Iterator for StartAt<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.started {
+            return self.input.next();
+        }
+        // the input only starts on a frame boundary
+        if self.channel == 0 && self.clock.load(Relaxed) >= self.at {
+            self.started = true;
+            return self.input.next();
+        }
+        self.channel = (self.channel + 1) % self.input.get_channels().max(1);
+        Some(I::Item::zero_value())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for StartAt<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for StartAt<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
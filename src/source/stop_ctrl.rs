@@ -0,0 +1,120 @@
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+use rodio::Sample;
+use rodio::Source;
+
+use super::Timing;
+
+/// the value of the control while the source is playing
+pub const PLAYING: usize = ::std::usize::MAX;
+
+/// Internal function that builds a `StopCtrl` object.
+///
+/// the control is the duration of the fade out in milliseconds, `PLAYING` while
+/// the source plays, once the fade is done it is set to 0 so that the sources
+/// sharing the control are stopped immediately
+///
+/// with a clock timing the fade is measured from the first sample played after
+/// the control is set
+pub fn stop_ctrl<I>(input: I, control: Arc<AtomicUsize>, timing: Timing) -> StopCtrl<I>
+                  where I: Source, I::Item: Sample
+{
+    StopCtrl {
+        input: input,
+        control: control,
+        timing: timing,
+        remaining_ns: None,
+        total_ns: 0,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StopCtrl<I> where I: Source, I::Item: Sample {
+    input: I,
+    control: Arc<AtomicUsize>,
+    timing: Timing,
+    remaining_ns: Option<u64>,
+    total_ns: u64,
+}
+
+impl<I> Iterator for StopCtrl<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.remaining_ns.is_none() {
+            let control = self.control.load(Relaxed);
+            if control == PLAYING {
+                return self.input.next();
+            }
+            self.total_ns = control as u64 * 1_000_000;
+            self.remaining_ns = Some(self.total_ns);
+        }
+
+        let remaining_ns = match self.timing.elapsed_ns() {
+            Some(elapsed) => self.total_ns.saturating_sub(elapsed),
+            None => self.remaining_ns.unwrap(),
+        };
+        if remaining_ns > 0 {
+            let factor = remaining_ns as f32 / self.total_ns as f32;
+            let rate = self.input.get_samples_rate().max(1) as u64 * self.input.get_channels().max(1) as u64;
+            self.remaining_ns = Some(remaining_ns.saturating_sub(1_000_000_000 / rate));
+            self.input.next().map(|value| value.amplify(factor))
+        } else {
+            self.control.store(0, Relaxed);
+            None
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> Source for StopCtrl<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[test]
+fn test_stop_ctrl() {
+    use rodio::buffer::SamplesBuffer;
+
+    let control = Arc::new(AtomicUsize::new(PLAYING));
+    let mut source = stop_ctrl(SamplesBuffer::new(1, 1000, vec![1f32; 10]), control.clone(), Timing::Samples);
+    assert_eq!(source.next(), Some(1.));
+    control.store(2, Relaxed);
+    assert_eq!(source.collect::<Vec<_>>(), vec![1., 0.5]);
+    assert_eq!(control.load(Relaxed), 0);
+
+    // the clock ticks 1000 times per second
+    let clock = Arc::new(AtomicUsize::new(0));
+    let control = Arc::new(AtomicUsize::new(2));
+    let mut source = stop_ctrl(SamplesBuffer::new(1, 1000, vec![1f32; 10]), control, Timing::Clock(clock.clone(), 1000, None));
+    assert_eq!(source.by_ref().take(3).collect::<Vec<_>>(), vec![1., 1., 1.]);
+    clock.store(1, Relaxed);
+    assert_eq!(source.next(), Some(0.5));
+    clock.store(2, Relaxed);
+    assert_eq!(source.next(), None);
+}
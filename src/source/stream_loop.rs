@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rodio::decoder::Decoder;
+use rodio::Source;
+
+use super::super::InitError;
+
+/// Internal function that builds a `StreamLoop` object.
+pub fn stream_loop(path: PathBuf) -> Result<StreamLoop,InitError> {
+    let decoder = try!(open(&path));
+    Ok(StreamLoop {
+        path: path,
+        decoder: decoder,
+        ended: false,
+    })
+}
+
+#[inline]
+fn open(path: &PathBuf) -> Result<Decoder<File>,InitError> {
+    let file = try!(File::open(path).map_err(|e| InitError::FileOpenError(path.clone(), e)));
+    Decoder::new(file).map_err(|e| InitError::DecodeError(path.clone(), e))
+}
+
+/// decode the file from the disk and decode it again from the start at its end
+///
+/// the source ends if the file can't be decoded again, like when it has been removed
+pub struct StreamLoop {
+    path: PathBuf,
+    decoder: Decoder<File>,
+    ended: bool,
+}
+
+impl Iterator for StreamLoop {
+    type Item = i16;
+
+    #[inline]
+    fn next(&mut self) -> Option<i16> {
+        if self.ended {
+            return None;
+        }
+        if let Some(sample) = self.decoder.next() {
+            return Some(sample);
+        }
+        match open(&self.path) {
+            Ok(decoder) => {
+                self.decoder = decoder;
+                self.decoder.next()
+            },
+            Err(_) => {
+                self.ended = true;
+                None
+            },
+        }
+    }
+}
+
+impl Source for StreamLoop {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        // the same file is decoded again so the format doesn't change
+        None
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.decoder.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.decoder.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// the samples rate of the sounds sent to a return
+pub const RETURN_SAMPLES_RATE: u32 = 44_100;
+
+/// the delay before a sound sent is mixed in the return, in frames
+const LATENCY: u64 = 2048;
+
+/// the maximum number of frames ahead of the return, frames further are dropped
+/// so that a return which isn't played doesn't fill the memory
+const MAX_FRAMES: u64 = RETURN_SAMPLES_RATE as u64 * 10;
+
+/// the sounds sent to a return mixed in mono at `RETURN_SAMPLES_RATE`
+#[derive(Debug, Default)]
+pub struct SendBuffer {
+    /// the number of frames consumed by the return
+    read: u64,
+    frames: VecDeque<f32>,
+}
+
+impl SendBuffer {
+    /// add the value to the frame, frames already consumed are ignored
+    fn add(&mut self, frame: u64, value: f32) {
+        if frame < self.read || frame >= self.read + MAX_FRAMES {
+            return;
+        }
+        let index = (frame - self.read) as usize;
+        while self.frames.len() <= index {
+            self.frames.push_back(0.);
+        }
+        self.frames[index] += value;
+    }
+
+    /// consume the next frame
+    pub fn pop(&mut self) -> f32 {
+        self.read += 1;
+        self.frames.pop_front().unwrap_or(0.)
+    }
+}
+
+/// the returns a sound is sent to with their levels
+pub type Sends = Vec<(Arc<Mutex<SendBuffer>>, f32)>;
+
+/// Internal function that builds a `Tap` object.
+pub fn tap<I>(input: I, sends: Sends) -> Tap<I>
+                  where I: Source, I::Item: Sample
+{
+    Tap {
+        input: input,
+        sends: sends.into_iter().map(|(buffer, level)| (buffer, level, None)).collect(),
+        frame: 0,
+        channel: 0,
+        sum: 0.,
+    }
+}
+
+/// send the mono mix of the source to the returns
+#[derive(Clone, Debug)]
+pub struct Tap<I> where I: Source, I::Item: Sample {
+    input: I,
+    /// the returns, their levels and the frame of the return the source started at
+    sends: Vec<(Arc<Mutex<SendBuffer>>, f32, Option<u64>)>,
+    frame: u64,
+    channel: u16,
+    sum: f32,
+}
+
+impl<I> Iterator for Tap<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.input.next();
+        if let (Some(value), false) = (next, self.sends.is_empty()) {
+            self.sum += value.to_f32();
+            self.channel += 1;
+            if self.channel >= self.input.get_channels() {
+                let value = self.sum / self.channel as f32;
+                // the frame is held until the next one in the return
+                let rate = self.input.get_samples_rate() as u64;
+                let first = self.frame * RETURN_SAMPLES_RATE as u64 / rate;
+                let last = (self.frame + 1) * RETURN_SAMPLES_RATE as u64 / rate;
+                for &mut (ref buffer, level, ref mut start) in &mut self.sends {
+                    let mut buffer = buffer.lock().unwrap();
+                    let start = *start.get_or_insert(buffer.read + LATENCY);
+                    for frame in first..last {
+                        buffer.add(start + frame, value * level);
+                    }
+                }
+                self.frame += 1;
+                self.channel = 0;
+                self.sum = 0.;
+            }
+        }
+        next
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Tap<I> where I: Source + ExactSizeIterator, I::Item: Sample {
+}
+
+impl<I> Source for Tap<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.input.get_total_duration()
+    }
+}
+
+#[test]
+fn test_send_buffer() {
+    let mut buffer = SendBuffer::default();
+    buffer.add(2, 0.5);
+    buffer.add(2, 0.25);
+    buffer.add(MAX_FRAMES, 1.);
+    assert_eq!(buffer.pop(), 0.);
+    assert_eq!(buffer.pop(), 0.);
+    assert_eq!(buffer.pop(), 0.75);
+    buffer.add(1, 1.);
+    assert_eq!(buffer.pop(), 0.);
+}
@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use rodio::Sample;
+use rodio::Source;
+
+/// Internal function that builds a `Trim` object.
+///
+/// the start is skipped here, on the thread building the source, so that the
+/// audio thread doesn't decode the skipped part in one callback
+pub fn trim<I>(mut input: I, start: Duration, take: Option<Duration>) -> Trim<I>
+                  where I: Source, I::Item: Sample
+{
+    let total_duration = input.get_total_duration().map(|duration| {
+        let duration = duration.checked_sub(start).unwrap_or(Duration::new(0, 0));
+        take.map_or(duration, |take| duration.min(take))
+    });
+
+    // the start is skipped by whole frames
+    let mut skip_ns = as_nanos(start);
+    'skip: while skip_ns > 0 {
+        let rate = input.get_samples_rate().max(1) as u64;
+        for _ in 0..input.get_channels() {
+            if input.next().is_none() {
+                break 'skip;
+            }
+        }
+        skip_ns = skip_ns.saturating_sub(1_000_000_000 / rate);
+    }
+
+    Trim {
+        input: input,
+        remaining_ns: take.map(as_nanos),
+        total_duration: total_duration,
+        channel: 0,
+        channels: 1,
+    }
+}
+
+#[inline]
+fn as_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
+
+/// the input without its start, ended after a duration
+#[derive(Clone, Debug)]
+pub struct Trim<I> where I: Source, I::Item: Sample {
+    input: I,
+    remaining_ns: Option<u64>,
+    total_duration: Option<Duration>,
+    /// the position of the next sample in its frame
+    channel: u16,
+    channels: u16,
+}
+
+impl<I> Iterator for Trim<I> where I: Source, I::Item: Sample {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.channel == 0 {
+            self.channels = self.input.get_channels().max(1);
+            if let Some(remaining) = self.remaining_ns {
+                if remaining == 0 {
+                    return None;
+                }
+                let rate = self.input.get_samples_rate().max(1) as u64;
+                self.remaining_ns = Some(remaining.saturating_sub(1_000_000_000 / rate));
+            }
+        }
+        self.channel = (self.channel + 1) % self.channels;
+        self.input.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.input.size_hint().1)
+    }
+}
+
+impl<I> Source for Trim<I> where I: Source, I::Item: Sample {
+    #[inline]
+    fn get_current_frame_len(&self) -> Option<usize> {
+        self.input.get_current_frame_len()
+    }
+
+    #[inline]
+    fn get_channels(&self) -> u16 {
+        self.input.get_channels()
+    }
+
+    #[inline]
+    fn get_samples_rate(&self) -> u32 {
+        self.input.get_samples_rate()
+    }
+
+    #[inline]
+    fn get_total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+}
+
+#[test]
+fn test_trim() {
+    use rodio::buffer::SamplesBuffer;
+
+    let source = SamplesBuffer::new(2, 1000, vec![1f32, 1., 2., 2., 3., 3., 4., 4.]);
+    let samples = trim(source, Duration::new(0, 1_000_000), Some(Duration::new(0, 2_000_000))).collect::<Vec<_>>();
+    assert_eq!(samples, vec![2., 2., 3., 3.]);
+}
@@ -1,26 +1,78 @@
 use std::time::Duration;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
 
 use rodio::Sample;
 use rodio::Source;
 
+/// how a source measures the durations of its delays and fades
+#[derive(Clone, Debug)]
+pub enum Timing {
+    /// by the samples it plays
+    Samples,
+    /// on the virtual clock ticking at the rate, from the value of the clock given
+    /// or from the first time it is measured if none, see `Setting::deterministic_seed`
+    Clock(Arc<AtomicUsize>, u32, Option<usize>),
+}
+
+impl Timing {
+    /// the same timing measured from the value of the clock
+    pub fn starting_at(&self, start: usize) -> Timing {
+        match *self {
+            Timing::Clock(ref clock, rate, _) => Timing::Clock(clock.clone(), rate, Some(start)),
+            Timing::Samples => Timing::Samples,
+        }
+    }
+
+    /// the same timing measured from the current value of the clock
+    pub fn starting_now(&self) -> Timing {
+        match *self {
+            Timing::Clock(ref clock, _, _) => self.starting_at(clock.load(Relaxed)),
+            Timing::Samples => Timing::Samples,
+        }
+    }
+
+    /// the duration in nanoseconds elapsed on the clock since the start, none if
+    /// the duration is measured by the samples
+    #[inline]
+    pub fn elapsed_ns(&mut self) -> Option<u64> {
+        match *self {
+            Timing::Clock(ref clock, rate, ref mut start) => {
+                let now = clock.load(Relaxed);
+                let start = *start.get_or_insert(now);
+                Some(now.saturating_sub(start) as u64 * 1_000_000_000 / rate.max(1) as u64)
+            },
+            Timing::Samples => None,
+        }
+    }
+}
+
 /// Internal function that builds a `Wait` object.
-pub fn wait<I>(input: I, duration: Duration) -> Wait<I>
+pub fn wait<I>(input: I, duration: Duration, timing: Timing) -> Wait<I>
                   where I: Source, I::Item: Sample
 {
     let duration = duration.as_secs() * 1000000000 + duration.subsec_nanos() as u64;
 
     Wait {
         input: input,
-        remaining_ns: duration as f32,
-        total_ns: duration as f32,
+        duration_ns: duration,
+        remaining_ns: duration,
+        timing: timing,
+        channel: 0,
+        channels: 1,
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Wait<I> where I: Source, I::Item: Sample {
     input: I,
-    remaining_ns: f32,
-    total_ns: f32,
+    duration_ns: u64,
+    remaining_ns: u64,
+    timing: Timing,
+    /// the position of the next sample in its frame
+    channel: u16,
+    channels: u16,
 }
 
 impl<I> Iterator for Wait<I> where I: Source, I::Item: Sample {
@@ -28,11 +80,27 @@ impl<I> Iterator for Wait<I> where I: Source, I::Item: Sample {
 
     #[inline]
     fn next(&mut self) -> Option<I::Item> {
-        if self.remaining_ns <= 0.0 {
-            return self.input.next();
+        if self.channel == 0 {
+            // the input only starts on a frame boundary
+            if self.remaining_ns == 0 {
+                return self.input.next();
+            }
+            // the rate and channels are read once per frame so that they are consistent
+            self.channels = self.input.get_channels().max(1);
+            match self.timing.elapsed_ns() {
+                Some(elapsed) => {
+                    self.remaining_ns = self.duration_ns.saturating_sub(elapsed);
+                    if self.remaining_ns == 0 {
+                        return self.input.next();
+                    }
+                },
+                None => {
+                    let rate = self.input.get_samples_rate().max(1) as u64;
+                    self.remaining_ns = self.remaining_ns.saturating_sub(1_000_000_000 / rate);
+                },
+            }
         }
-        self.remaining_ns -= 1000000000.0 / (self.input.get_samples_rate() as f32 *
-                                             self.get_channels() as f32);
+        self.channel = (self.channel + 1) % self.channels;
         Some(I::Item::zero_value())
     }
 
@@ -66,3 +134,20 @@ impl<I> Source for Wait<I> where I: Source, I::Item: Sample {
         self.input.get_total_duration()
     }
 }
+
+#[test]
+fn test_wait() {
+    use rodio::buffer::SamplesBuffer;
+
+    let source = SamplesBuffer::new(2, 1000, vec![1f32; 4]);
+    let samples = wait(source, Duration::new(0, 3_000_000), Timing::Samples).collect::<Vec<_>>();
+    assert_eq!(samples, vec![0., 0., 0., 0., 0., 0., 1., 1., 1., 1.]);
+
+    // the clock ticks 1000 times per second
+    let clock = Arc::new(AtomicUsize::new(10));
+    let timing = Timing::Clock(clock.clone(), 1000, None).starting_now();
+    let mut source = wait(SamplesBuffer::new(2, 1000, vec![1f32; 4]), Duration::new(0, 3_000_000), timing);
+    assert_eq!(source.by_ref().take(10).collect::<Vec<_>>(), vec![0.; 10]);
+    clock.store(13, Relaxed);
+    assert_eq!(source.collect::<Vec<_>>(), vec![1., 1., 1., 1.]);
+}
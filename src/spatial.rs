@@ -0,0 +1,220 @@
+//! this module allow to occlude effects with a graph of rooms and portals
+//!
+//! the game registers its rooms and the portals between them in a `RoomGraph`
+//! and gives it to `set_room_graph`, the listener is assigned a room with
+//! `set_listener_room` and emitters with `Emitter::set_room`
+//!
+//! sounds of another room are heard through the portals of the loudest path:
+//! they are attenuated by the product of the gains of its portals and
+//! low-passed with the lowest cutoff of its portals, sounds of unreachable rooms
+//! are silent, sounds without room are not occluded
+
+use super::RAW_STATE;
+
+/// the id of a room: its index in the rooms of the graph
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct RoomId(pub usize);
+
+/// a portal between two rooms, like a door or a window
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Portal {
+    /// the gain of the sounds going through the portal, between 0 and 1
+    pub gain: f32,
+    /// the cutoff frequency in Hz of the low pass filter of the portal, none disables it
+    pub low_pass: Option<f32>,
+}
+
+/// the occlusion of a sound by the portals between its room and the listener room
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Occlusion {
+    /// the gain of the sound
+    pub gain: f32,
+    /// the cutoff frequency in Hz of the low pass filter, none if not filtered
+    pub low_pass: Option<f32>,
+}
+
+/// no occlusion: the sound and the listener are in the same room
+pub const NO_OCCLUSION: Occlusion = Occlusion {
+    gain: 1.,
+    low_pass: None,
+};
+
+/// the rooms and the portals between them
+#[derive(Clone,Debug,Default,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct RoomGraph {
+    /// for each room the portals to other rooms
+    portals: Vec<Vec<(usize, Portal)>>,
+}
+
+impl RoomGraph {
+    /// create an empty graph
+    pub fn new() -> RoomGraph {
+        RoomGraph {
+            portals: vec!(),
+        }
+    }
+
+    /// add a room without portal and return its id
+    pub fn add_room(&mut self) -> RoomId {
+        self.portals.push(vec!());
+        RoomId(self.portals.len() - 1)
+    }
+
+    /// return the number of rooms
+    pub fn rooms(&self) -> usize {
+        self.portals.len()
+    }
+
+    /// add a portal between the two rooms, sounds go through it both ways
+    ///
+    /// panic if a room is not in the graph
+    pub fn add_portal(&mut self, a: RoomId, b: RoomId, portal: Portal) {
+        assert!(a.0 < self.portals.len() && b.0 < self.portals.len(), "unknown room");
+        self.portals[a.0].push((b.0, portal));
+        self.portals[b.0].push((a.0, portal));
+    }
+
+    /// remove all the portals between the two rooms, like a door closing
+    pub fn remove_portals(&mut self, a: RoomId, b: RoomId) {
+        if let Some(portals) = self.portals.get_mut(a.0) {
+            portals.retain(|&(room, _)| room != b.0);
+        }
+        if let Some(portals) = self.portals.get_mut(b.0) {
+            portals.retain(|&(room, _)| room != a.0);
+        }
+    }
+
+    /// return the occlusion of the loudest path between the two rooms,
+    /// none if there is no path
+    pub fn occlusion(&self, from: RoomId, to: RoomId) -> Option<Occlusion> {
+        if from.0 >= self.portals.len() || to.0 >= self.portals.len() {
+            return None;
+        }
+
+        // gains are at most 1 so the loudest path is found like the shortest one
+        let mut best: Vec<Option<Occlusion>> = vec!(None; self.portals.len());
+        let mut done = vec!(false; self.portals.len());
+        best[from.0] = Some(NO_OCCLUSION);
+        loop {
+            let next = (0..best.len())
+                .filter(|&room| !done[room])
+                .filter_map(|room| best[room].map(|occlusion| (room, occlusion)))
+                .fold(None, |acc: Option<(usize, Occlusion)>, (room, occlusion)| match acc {
+                    Some((_, a)) if a.gain >= occlusion.gain => acc,
+                    _ => Some((room, occlusion)),
+                });
+            let (room, occlusion) = match next {
+                Some(next) => next,
+                None => return None,
+            };
+            if room == to.0 {
+                return Some(occlusion);
+            }
+            done[room] = true;
+            for &(other, portal) in &self.portals[room] {
+                let through = Occlusion {
+                    gain: occlusion.gain * portal.gain.max(0.).min(1.),
+                    low_pass: match (occlusion.low_pass, portal.low_pass) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    },
+                };
+                let better = match best[other] {
+                    Some(o) => through.gain > o.gain,
+                    None => true,
+                };
+                if !done[other] && better {
+                    best[other] = Some(through);
+                }
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct Spatial {
+    graph: Option<RoomGraph>,
+    listener: Option<RoomId>,
+}
+
+impl Spatial {
+    /// the graph is kept on reset
+    #[doc(hidden)]
+    pub fn init() -> Spatial {
+        Spatial {
+            graph: None,
+            listener: None,
+        }
+    }
+}
+
+/// set the graph of rooms, none disables the occlusion
+pub fn set_room_graph(graph: Option<RoomGraph>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.spatial.graph = graph;
+}
+
+/// return the graph of rooms
+pub fn room_graph() -> Option<RoomGraph> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.spatial.graph.clone()
+}
+
+/// set the room of the listener, none disables the occlusion
+pub fn set_listener_room(room: Option<RoomId>) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    state.spatial.listener = room;
+}
+
+/// return the room of the listener
+pub fn listener_room() -> Option<RoomId> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.spatial.listener
+}
+
+#[doc(hidden)]
+pub fn listener_room_inner(state: &super::State) -> Option<RoomId> {
+    state.spatial.listener
+}
+
+/// return the occlusion of a sound in the room
+///
+/// a sound without room or with no graph or listener room is not occluded,
+/// a sound in a room unreachable from the listener room has a gain of 0
+#[doc(hidden)]
+pub fn occlusion_inner(room: Option<RoomId>, state: &super::State) -> Occlusion {
+    match (&state.spatial.graph, state.spatial.listener, room) {
+        (&Some(ref graph), Some(listener), Some(room)) => {
+            graph.occlusion(room, listener).unwrap_or(Occlusion {
+                gain: 0.,
+                low_pass: None,
+            })
+        }
+        _ => NO_OCCLUSION,
+    }
+}
+
+#[test]
+fn test_occlusion() {
+    let mut graph = RoomGraph::new();
+    let hall = graph.add_room();
+    let kitchen = graph.add_room();
+    let cellar = graph.add_room();
+    let attic = graph.add_room();
+    let door = Portal { gain: 0.5, low_pass: Some(2000.) };
+    let hatch = Portal { gain: 0.5, low_pass: Some(500.) };
+    graph.add_portal(hall, kitchen, door);
+    graph.add_portal(kitchen, cellar, hatch);
+    graph.add_portal(hall, cellar, Portal { gain: 0.1, low_pass: None });
+
+    assert_eq!(graph.occlusion(hall, hall), Some(NO_OCCLUSION));
+    assert_eq!(graph.occlusion(kitchen, hall), Some(Occlusion { gain: 0.5, low_pass: Some(2000.) }));
+    assert_eq!(graph.occlusion(cellar, hall), Some(Occlusion { gain: 0.25, low_pass: Some(500.) }));
+    assert_eq!(graph.occlusion(attic, hall), None);
+
+    graph.remove_portals(kitchen, cellar);
+    assert_eq!(graph.occlusion(cellar, hall), Some(Occlusion { gain: 0.1, low_pass: None }));
+}
@@ -0,0 +1,305 @@
+//! this module allow to play voice lines like dialogues and voice-over
+//!
+//! only one line is played at a time, when a line is requested while another one
+//! is playing it is queued, played instead or dropped according to its policy
+//! and priority
+//!
+//! while a line is played the music and effect buses are ducked by the factor
+//! set in the setting
+//!
+//! queued lines are started and audio events are emitted by `baal::update`
+
+use rodio::decoder::Decoder;
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+
+use super::backend::{AudioBackend, AudioSink, Sink};
+use super::bus;
+use super::bus::Bus;
+use super::event;
+use super::event::AudioEvent;
+use super::effect::EffectSetting;
+use super::InitError;
+use super::RAW_STATE;
+use super::Setting;
+use super::Stats;
+use super::source;
+
+/// set voice lines, volume and ducking
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct VoiceSetting {
+    /// the base directory of voice lines
+    pub dir: PathBuf,
+
+    /// the voice volume in [0,1]
+    pub volume: f32,
+
+    /// the factor applied to music and effect volumes while a line is played
+    pub duck: f32,
+
+    /// the list of voice lines
+    ///
+    /// each line is identified by its position in the vector
+    pub lines: Vec<EffectSetting>,
+}
+
+impl Default for VoiceSetting {
+    fn default() -> VoiceSetting {
+        VoiceSetting {
+            dir: PathBuf::new(),
+            volume: 1.,
+            duck: 1.,
+            lines: vec!(),
+        }
+    }
+}
+
+/// what to do with a line requested while another one is playing
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum VoicePolicy {
+    /// queue the line, lines with greater priority are played first
+    Queue,
+    /// stop the current line if the priority is greater or equal, otherwise queue the line
+    Interrupt,
+    /// drop the line
+    Drop,
+}
+
+struct Request {
+    id: usize,
+    line: usize,
+    priority: u32,
+}
+
+struct Current {
+    id: usize,
+    priority: u32,
+    ended: Arc<AtomicBool>,
+    caption: Option<Arc<String>>,
+    _sink: Sink,
+}
+
+#[doc(hidden)]
+pub struct State {
+    #[doc(hidden)]
+    pub bus: Bus,
+    duck: f32,
+    ducking: bool,
+    sources: Vec<PathBuf>,
+    captions: Vec<Option<Arc<String>>>,
+    queue: Vec<Request>,
+    current: Option<Current>,
+    next_id: usize,
+}
+
+impl State {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Result<State,InitError> {
+        let mut sources = vec!();
+        let mut captions = vec!();
+
+        for line in &setting.voice.lines {
+            let source = &line.path;
+            let path = setting.voice.dir.join(source);
+            let file = try!(File::open(path.clone()).map_err(|e| InitError::FileOpenError(source.clone(), e)));
+            try!(Decoder::new(file).map_err(|e| InitError::DecodeError(source.clone(), e)));
+
+            sources.push(path);
+            captions.push(line.caption.clone().map(Arc::new));
+        }
+
+        Ok(State {
+            bus: Bus::new(setting.voice.volume, setting),
+            duck: setting.voice.duck,
+            ducking: false,
+            sources: sources,
+            captions: captions,
+            queue: vec!(),
+            current: None,
+            next_id: 0,
+        })
+    }
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) -> Result<(),InitError> {
+        let next_id = self.next_id;
+        *self = try!(State::init(setting));
+        self.next_id = next_id;
+        Ok(())
+    }
+}
+
+/// request the line to be played, return its id or none if it has been dropped
+pub fn play(line: usize, priority: u32, policy: VoicePolicy) -> Option<usize> {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+
+    let id = state.voice.next_id;
+    state.voice.next_id += 1;
+    let request = Request {
+        id: id,
+        line: line,
+        priority: priority,
+    };
+
+    let current_priority = state.voice.current.as_ref().map(|current| current.priority);
+    match (current_priority, policy) {
+        (None, _) => start(request, &mut state),
+        (Some(_), VoicePolicy::Drop) => {
+            debug!("baal: voice line {} dropped", line);
+            return None;
+        }
+        (Some(current_priority), VoicePolicy::Interrupt) if priority >= current_priority => {
+            debug!("baal: voice line {} interrupts the current line", line);
+            interrupt_current(&mut state);
+            start(request, &mut state);
+        }
+        (Some(_), _) => {
+            let position = state.voice.queue.iter()
+                .position(|queued| queued.priority < priority)
+                .unwrap_or(state.voice.queue.len());
+            state.voice.queue.insert(position, request);
+        }
+    }
+
+    Some(id)
+}
+
+/// stop the current line and remove all queued lines
+pub fn stop() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    interrupt_current(&mut state);
+    let queue = ::std::mem::replace(&mut state.voice.queue, vec!());
+    for request in queue {
+        event::emit(AudioEvent::VoiceLineInterrupted(request.id), &mut state);
+    }
+    update_duck(&mut state);
+}
+
+/// stop the current line, the next queued line is played at next update
+pub fn skip() {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    interrupt_current(&mut state);
+}
+
+/// return the id of the line played if any
+pub fn current() -> Option<usize> {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.current.as_ref().map(|current| current.id)
+}
+
+/// return the number of lines queued
+pub fn queued() -> usize {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.queue.len()
+}
+
+/// set the volume of voice lines
+pub fn set_volume(v: f32) {
+    let mut state = unsafe { (*RAW_STATE).write().unwrap_or_else(|e| e.into_inner()) };
+    let global_volume = state.global_volume;
+    state.voice.bus.set_volume(v, global_volume);
+}
+
+/// return the volume of voice lines
+pub fn volume() -> f32 {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.bus.volume()
+}
+
+/// pause the voice lines
+pub fn pause() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.bus.set_paused(true);
+}
+
+/// resume the voice lines
+pub fn resume() {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.bus.set_paused(false);
+}
+
+/// return whereas voice lines are paused
+pub fn is_paused() -> bool {
+    let state = unsafe { (*RAW_STATE).read().unwrap_or_else(|e| e.into_inner()) };
+    state.voice.bus.is_paused()
+}
+
+#[doc(hidden)]
+pub fn update(state: &mut super::State) {
+    let ended = state.voice.current.as_ref().map(|current| current.ended.load(Relaxed)).unwrap_or(false);
+    if ended {
+        let current = state.voice.current.take().unwrap();
+        if let Some(caption) = current.caption {
+            event::emit(AudioEvent::CaptionEnd(caption), state);
+        }
+        event::emit(AudioEvent::VoiceLineEnd(current.id), state);
+    }
+
+    if state.voice.current.is_none() && !state.voice.queue.is_empty() {
+        let request = state.voice.queue.remove(0);
+        start(request, state);
+    }
+
+    update_duck(state);
+}
+
+#[doc(hidden)]
+pub fn stats_inner(state: &super::State, stats: &mut Stats) {
+    stats.voice_line = state.voice.current.is_some();
+    stats.voice_lines_queued = state.voice.queue.len();
+    if state.voice.current.is_some() {
+        stats.sinks += 1;
+    }
+}
+
+#[inline]
+fn start(request: Request, state: &mut super::State) {
+    let ended = Arc::new(AtomicBool::new(false));
+
+    let source = Decoder::new(File::open(state.voice.sources[request.line].clone()).unwrap()).unwrap();
+    let source = state.voice.bus.apply(source);
+    let source = source::end_signal(source, ended.clone());
+
+    let sink = state.backend.new_sink();
+    sink.append(source);
+
+    state.voice.current = Some(Current {
+        id: request.id,
+        priority: request.priority,
+        ended: ended,
+        caption: state.voice.captions[request.line].clone(),
+        _sink: sink,
+    });
+    event::emit(AudioEvent::VoiceLineStart(request.id), state);
+    if let Some(caption) = state.voice.captions[request.line].clone() {
+        event::emit(AudioEvent::CaptionStart(caption), state);
+    }
+    update_duck(state);
+}
+
+#[inline]
+fn interrupt_current(state: &mut super::State) {
+    if let Some(current) = state.voice.current.take() {
+        if let Some(caption) = current.caption {
+            event::emit(AudioEvent::CaptionEnd(caption), state);
+        }
+        event::emit(AudioEvent::VoiceLineInterrupted(current.id), state);
+    }
+}
+
+#[inline]
+fn update_duck(state: &mut super::State) {
+    let ducking = state.voice.current.is_some();
+    if ducking != state.voice.ducking {
+        state.voice.ducking = ducking;
+        let duck = if ducking { state.voice.duck } else { 1. };
+        let global_volume = state.global_volume;
+        for name in &[bus::MUSIC, bus::EFFECT] {
+            bus::get_mut(name, state).unwrap().set_voice_duck(duck, global_volume);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! this module allow to detect when the audio device stops consuming samples
+//!
+//! the audio clock is driven by the samples consumed by the audio device, if it
+//! doesn't advance during `WatchdogSetting::timeout` of calls to `baal::update`
+//! `AudioEvent::OutputStalled` is emitted and the audio player is optionally
+//! rebuilt on the default endpoint like `baal::handle_route_change`
+//!
+//! only the rodio backend is watched, the clock doesn't advance in deterministic mode
+
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
+
+use super::Setting;
+use super::backend;
+use super::event;
+use super::event::AudioEvent;
+
+/// the setting of the watchdog, see `Setting::watchdog`
+#[derive(Clone,Copy,Debug,PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct WatchdogSetting {
+    /// the duration without samples consumed after which the output is stalled
+    pub timeout: Duration,
+
+    /// whether the audio player is rebuilt on the default endpoint when the output
+    /// is stalled
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub reinit: bool,
+}
+
+#[doc(hidden)]
+pub struct Watchdog {
+    setting: Option<WatchdogSetting>,
+    last_clock: usize,
+    /// the time elapsed since the clock last advanced
+    stalled: Duration,
+    /// whether the current stall has been reported
+    reported: bool,
+}
+
+impl Watchdog {
+    #[doc(hidden)]
+    pub fn init(setting: &Setting) -> Watchdog {
+        Watchdog {
+            setting: setting.watchdog,
+            last_clock: 0,
+            stalled: Duration::new(0, 0),
+            reported: false,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn reset(&mut self, setting: &Setting) {
+        self.setting = setting.watchdog;
+        self.stalled = Duration::new(0, 0);
+        self.reported = false;
+    }
+}
+
+/// watch the audio clock, return whether the audio player must be rebuilt
+#[doc(hidden)]
+pub fn update(dt: Duration, state: &mut super::State) -> bool {
+    let setting = match state.watchdog.setting {
+        Some(setting) => setting,
+        None => return false,
+    };
+    match state.backend {
+        backend::Backend::Rodio(_) if !state.virtual_clock => (),
+        _ => return false,
+    }
+
+    let clock = state.clock.load(Relaxed);
+    if clock != state.watchdog.last_clock {
+        state.watchdog.last_clock = clock;
+        state.watchdog.stalled = Duration::new(0, 0);
+        state.watchdog.reported = false;
+        return false;
+    }
+
+    state.watchdog.stalled += dt;
+    if state.watchdog.reported || state.watchdog.stalled < setting.timeout {
+        return false;
+    }
+    state.watchdog.reported = true;
+    let stalled = state.watchdog.stalled;
+    warn!("baal: no samples consumed by the audio device for {:?}", stalled);
+    event::emit(AudioEvent::OutputStalled(stalled), state);
+    setting.reinit
+}
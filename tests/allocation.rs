@@ -0,0 +1,57 @@
+extern crate baal;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// the system allocator counting the allocations
+struct Counter;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+/// the slot of an ended short effect, its sink and its controls, is reused by
+/// the next play so that baal doesn't allocate when playing an effect
+///
+/// only the null backend discards the sources without allocating, the rodio and
+/// render backends still box each appended source, so the play path as a whole
+/// isn't allocation free
+#[test]
+fn play_reuses_the_short_slots() {
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        short_effects: vec!("shoot.ogg".into()),
+        max_short_effects: Some(4),
+        .. Default::default()
+    };
+    baal::init_with_backend(&setting, baal::backend::Backend::Null).expect("init baal");
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+
+    // the first play allocates the slot reused by the next ones, the null
+    // backend drops the sources so the effect is ended at the next update
+    baal::effect::short::play(shoot, [0., 0., 0.]);
+    baal::update(Duration::from_millis(16));
+
+    for _ in 0..10 {
+        let before = ALLOCATIONS.load(Ordering::SeqCst);
+        baal::effect::short::play(shoot, [0., 0., 0.]);
+        let after = ALLOCATIONS.load(Ordering::SeqCst);
+        assert_eq!(after, before);
+        baal::update(Duration::from_millis(16));
+    }
+
+    baal::close();
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "persistent")]
+
+extern crate baal;
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn batch() {
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        music_dir: "assets/musics".into(),
+        root: None,
+
+        global_volume: 0.5,
+        music_volume: 0.5,
+        effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
+
+        distance_model: baal::effect::DistanceModel::Linear(1.,4.),
+        air_absorption: 0.,
+        near_field: 0.,
+
+        music_transition: baal::music::MusicTransition::Instant,
+
+        short_effects: vec!("shoot.ogg".into(),"hit.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
+        persistent_effects: vec!("electro_fly_from_xonotic_game.ogg".into()),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
+        musics: vec!(),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
+    };
+
+    baal::init(&setting).expect("init baal");
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+    let hit = setting.short_effect_id("hit.ogg").unwrap();
+    let fly = setting.persistent_effect_id("electro_fly_from_xonotic_game.ogg").unwrap();
+
+    baal::effect::short::play_many(&[(shoot,[0.,0.,0.]),(hit,[1.,0.,0.]),(shoot,[2.,0.,0.])]);
+
+    baal::effect::persistent::set_positions_for_all(vec!((fly,vec!([0.,0.,0.],[1.,1.,1.]))));
+    baal::effect::persistent::update_volume_for_all();
+    thread::sleep(Duration::from_secs(1));
+
+    baal::effect::persistent::set_positions(fly,vec!([2.,2.,2.]));
+    baal::effect::persistent::update_volume_for_all();
+    thread::sleep(Duration::from_secs(1));
+
+    baal::close();
+}
@@ -0,0 +1,85 @@
+extern crate baal;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn test() {
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        music_dir: "assets/musics".into(),
+        root: None,
+
+        global_volume: 0.5,
+        music_volume: 0.5,
+        effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
+
+        distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
+
+        music_transition: baal::music::MusicTransition::Instant,
+
+        short_effects: vec!("shoot.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
+        persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
+        musics: vec!("village.ogg".into()),
+        deterministic_seed: Some(42),
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
+    };
+
+    let backend = baal::backend::Backend::Render(baal::backend::RenderBackend::new());
+    baal::init_with_backend(&setting, backend).expect("init baal");
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+    assert_eq!(baal::clock(), Duration::new(0, 0));
+
+    // the clock only advances with advance
+    baal::update(Duration::new(1, 0));
+    assert_eq!(baal::clock(), Duration::new(0, 0));
+    baal::advance(Duration::new(1, 0));
+    assert_eq!(baal::clock(), Duration::new(1, 0));
+
+    // the delay is measured on the clock and not on the samples rendered
+    baal::effect::short::play_at(shoot, [0., 0., 0.], Duration::from_millis(1500));
+    let delayed = baal::render(Duration::new(1, 0));
+    assert!(delayed.iter().all(|&sample| sample == 0.));
+    baal::advance(Duration::from_millis(500));
+    let played = baal::render(Duration::from_millis(100));
+    assert!(played.iter().any(|&sample| sample != 0.));
+    baal::effect::short::stop_all();
+
+    // so is the fade of the stop
+    baal::effect::short::play_pcm(vec!(1.; 3 * 44_100), 1, 44_100, [0., 0., 0.]);
+    let full = baal::render(Duration::from_millis(100))[0];
+    assert!(full > 0.);
+    baal::effect::short::stop_all_with_fade(Duration::new(1, 0));
+    let start = baal::render(Duration::from_millis(100));
+    assert!(start.iter().all(|&sample| (sample - full).abs() < 1e-3));
+    baal::advance(Duration::from_millis(500));
+    let middle = baal::render(Duration::from_millis(100));
+    assert!(middle.iter().all(|&sample| (sample - full / 2.).abs() < 1e-3));
+    baal::advance(Duration::from_millis(500));
+    assert!(baal::render(Duration::from_millis(100)).iter().all(|&sample| sample == 0.));
+
+    // the parts of samples of each step add up
+    let before = baal::clock();
+    for _ in 0..40 {
+        baal::advance(Duration::from_millis(25));
+    }
+    assert_eq!(baal::clock(), before + Duration::new(1, 0));
+
+    baal::close();
+}
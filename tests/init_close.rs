@@ -1,32 +1,60 @@
+#![cfg(feature = "music")]
+
 extern crate baal;
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 #[test]
 fn test() {
     let setting = baal::Setting {
         effect_dir: "assets/effects".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.0,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
         distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
 
         music_transition: baal::music::MusicTransition::Instant,
 
         short_effects: vec!("shoot.ogg".into(),"hit.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!("village.ogg".into()),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+    let hit = setting.short_effect_id("hit.ogg").unwrap();
+    let village = setting.music_id("village.ogg").unwrap();
+
     for _ in 0..4 {
         baal::init(&setting).expect("fail to init baal");
-        baal::music::play(0);
+        baal::music::play(village);
 
         for i in 0..7 {
             let p = (i*20) as f32;
-            baal::effect::short::play(0,[p,0.,0.]);
-            baal::effect::short::play(1,[p,0.,0.]);
+            baal::effect::short::play(shoot,[p,0.,0.]);
+            baal::effect::short::play(hit,[p,0.,0.]);
         }
 
         baal::close();
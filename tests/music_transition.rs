@@ -1,5 +1,8 @@
+#![cfg(feature = "music")]
+
 extern crate baal;
 
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
@@ -8,36 +11,60 @@ fn test() {
     let setting = baal::Setting {
         effect_dir: "assets/effects".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.5,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
         distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
 
         music_transition: baal::music::MusicTransition::Instant,
 
         short_effects: vec!(),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!("first_call_kevin_macleod_incompetech.ogg".into()),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
     baal::init(&setting).expect("fail to init baal");
+    let call = setting.music_id("first_call_kevin_macleod_incompetech.ogg").unwrap();
 
     thread::sleep(Duration::from_secs(1));
 
-    baal::music::play(0);
+    baal::music::play(call);
     thread::sleep(Duration::from_secs(4));
 
-    baal::music::play(0);
+    baal::music::play(call);
     thread::sleep(Duration::from_secs(4));
 
     baal::music::set_transition(baal::music::MusicTransition::Smooth(Duration::from_secs(2)));
-    baal::music::play(0);
+    baal::music::play(call);
     thread::sleep(Duration::from_secs(4));
 
     baal::music::set_transition(baal::music::MusicTransition::Overlap(Duration::from_secs(2)));
-    baal::music::play(0);
+    baal::music::play(call);
     thread::sleep(Duration::from_secs(10));
+
+    baal::music::play_with_transition(call, baal::music::MusicTransition::Instant);
+    assert_eq!(baal::music::transition(), baal::music::MusicTransition::Overlap(Duration::from_secs(2)));
+    thread::sleep(Duration::from_secs(2));
     baal::close();
 }
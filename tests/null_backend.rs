@@ -0,0 +1,270 @@
+#![cfg(feature = "music")]
+
+extern crate baal;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn test() {
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        music_dir: "assets/musics".into(),
+        root: None,
+
+        global_volume: 0.5,
+        music_volume: 0.5,
+        effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
+
+        distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
+
+        music_transition: baal::music::MusicTransition::Instant,
+
+        short_effects: vec!("shoot.ogg".into(),"hit.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
+        persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
+        musics: vec!("village.ogg".into()),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
+    };
+
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+    let hit = setting.short_effect_id("hit.ogg").unwrap();
+    assert_eq!(hit.index(), 1);
+    let village = setting.music_id("village.ogg").unwrap();
+    assert_eq!(village.index(), 0);
+    assert_eq!(setting.persistent_effect_id("hit.ogg"), None);
+
+    baal::init_with_backend(&setting, baal::backend::Backend::Null).expect("init baal");
+    let memory = baal::memory_report();
+    assert_eq!(memory.assets.len(), 2);
+    assert!(memory.assets[0].bytes >= memory.assets[1].bytes);
+    assert_eq!(memory.total_bytes, memory.assets[0].bytes + memory.assets[1].bytes);
+    baal::music::play(village);
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    baal::effect::short::play(hit,[0.,0.,0.]);
+    baal::effect::short::play_detached(hit,[0.,0.,0.]);
+    assert_eq!(baal::stats().short_effects, 2);
+    let spatial_state = baal::debug::spatial_state();
+    assert_eq!(spatial_state.short_effects.len(), 2);
+    assert_eq!(spatial_state.short_effects[0].effect, Some(shoot));
+
+    // sources are discarded so short effects end at the next update
+    baal::update(Duration::new(0, 0));
+    assert_eq!(baal::stats().short_effects, 0);
+    assert_eq!(baal::music::status(), baal::music::MusicStatus::Play);
+
+    baal::enter_pause_preset();
+    baal::update(Duration::from_millis(baal::bus::PAUSE_FADE_MILLIS));
+    assert!(baal::is_pause_preset());
+    // the preset is applied on top of the mix, a change made while paused is kept
+    assert_eq!(baal::bus::volume(baal::bus::EFFECT), 0.5);
+    baal::bus::set_volume(baal::bus::EFFECT, 0.25);
+    baal::exit_pause_preset();
+    baal::update(Duration::from_millis(baal::bus::PAUSE_FADE_MILLIS));
+    assert!(!baal::is_pause_preset());
+    assert_eq!(baal::bus::low_pass(baal::bus::EFFECT), None);
+    assert_eq!(baal::bus::volume(baal::bus::EFFECT), 0.25);
+    baal::bus::set_volume(baal::bus::EFFECT, 0.5);
+
+    baal::pause_all_except(&[baal::bus::UI, baal::bus::VOICE]);
+    assert!(baal::bus::is_paused(baal::bus::EFFECT));
+    assert!(baal::music::is_paused());
+    assert!(!baal::bus::is_paused(baal::bus::UI));
+    assert!(!baal::voice::is_paused());
+    baal::resume_all();
+    assert!(!baal::effect::is_paused());
+
+    baal::on_focus_lost();
+    assert!(!baal::focus::has_focus());
+    assert_eq!(baal::music::status(), baal::music::MusicStatus::Play);
+    baal::on_focus_gained();
+    assert!(baal::focus::has_focus());
+    baal::handle_interruption_begin();
+    assert!(baal::focus::is_interrupted());
+    baal::handle_interruption_end();
+    assert!(!baal::focus::is_interrupted());
+    // nothing to rebuild with the null backend
+    baal::handle_route_change().expect("handle route change");
+
+    baal::effect::short::reload(shoot).expect("reload effect");
+    // the ogg decoder doesn't know the duration, the samples are counted
+    assert!(baal::music::duration(village).unwrap() > Duration::new(0, 0));
+    baal::music::reload(village).expect("reload music");
+    assert!(baal::music::duration(village).unwrap() > Duration::new(0, 0));
+    assert_eq!(baal::music::index(), Some(village));
+    baal::music::play_then(village, village);
+    baal::music::set_next(village);
+    // the end of the loop is never reached as sources are discarded
+    baal::music::stop_at_loop_end();
+    assert_eq!(baal::music::index(), Some(village));
+
+    // the music is restored at the position of the snapshot
+    let mut snapshot = baal::snapshot();
+    assert_eq!(snapshot.music_index, Some(village));
+    snapshot.music_position = Duration::new(1, 0);
+    snapshot.effect_volume = 0.25;
+    baal::music::stop();
+    baal::restore(&snapshot);
+    assert_eq!(baal::music::index(), Some(village));
+    assert_eq!(baal::music::position(), Some(Duration::new(1, 0)));
+    assert_eq!(baal::snapshot(), snapshot);
+    // a snapshot taken with more musics doesn't panic
+    baal::reset(&baal::Setting { musics: vec!(), .. setting.clone() }).expect("reset baal without musics");
+    baal::restore(&snapshot);
+    assert_eq!(baal::music::index(), None);
+
+    let setting = baal::Setting {
+        max_short_effects: Some(1),
+        .. setting
+    };
+    baal::reset(&setting).expect("reset baal");
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    baal::effect::short::play(hit,[0.,0.,0.]);
+    assert_eq!(baal::stats().short_effects, 1);
+
+    let silent = baal::Setting {
+        max_short_effects: Some(0),
+        .. setting.clone()
+    };
+    baal::reset(&silent).expect("reset baal");
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    baal::effect::ui::play(hit);
+    assert_eq!(baal::stats().short_effects, 0);
+
+    let mut sends = HashMap::new();
+    sends.insert("reverb".to_string(), 0.5);
+    let mut return_buses = HashMap::new();
+    return_buses.insert("reverb".to_string(), baal::bus::ReturnBus { volume: 0.5, decay: 0.8 });
+    let setting = baal::Setting {
+        short_effects: vec!(baal::effect::EffectSetting {
+            sends: sends,
+            .. baal::effect::EffectSetting::from("shoot.ogg")
+        }),
+        return_buses: return_buses,
+        .. setting
+    };
+    baal::reset(&setting).expect("reset baal with a return bus");
+    baal::effect::short::play(setting.short_effect_id("shoot.ogg").unwrap(),[0.,0.,0.]);
+    assert_eq!(baal::stats().short_effects, 1);
+
+    let setting = baal::Setting {
+        return_buses: HashMap::new(),
+        .. setting
+    };
+    match baal::reset(&setting) {
+        Err(baal::InitError::UnknownBus(name)) => assert_eq!(name, "reverb"),
+        _ => panic!("the returns of the effect must be validated"),
+    }
+
+    let setting = baal::Setting {
+        short_effects: vec!(baal::effect::EffectSetting {
+            bus: Some("weapons".into()),
+            .. baal::effect::EffectSetting::from("shoot.ogg")
+        }),
+        .. setting
+    };
+    match baal::reset(&setting) {
+        Err(baal::InitError::UnknownBus(name)) => assert_eq!(name, "weapons"),
+        _ => panic!("the bus of the effect must be validated"),
+    }
+
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        blend_containers: vec!(baal::effect::blend::BlendContainer {
+            parameter: "rpm".into(),
+            layers: vec!(baal::effect::blend::BlendLayer { effect: 0, range: [0., 0., 1., 1.] }),
+        }),
+        .. Default::default()
+    };
+    match baal::reset(&setting) {
+        Err(baal::InitError::UnknownPersistentEffect(effect)) => assert_eq!(effect, 0),
+        _ => panic!("the layers of the blend containers must be validated"),
+    }
+
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        parameter_bindings: vec!(baal::parameter::ParameterBinding {
+            parameter: "speed".into(),
+            target: baal::parameter::ParameterTarget::ShortPitch(0),
+            curve: vec!((0., 1.), (1., 2.)),
+        }),
+        .. Default::default()
+    };
+    match baal::reset(&setting) {
+        Err(baal::InitError::UnknownShortEffect(effect)) => assert_eq!(effect, 0),
+        _ => panic!("the effects of the parameter bindings must be validated"),
+    }
+
+    let mut events = HashMap::new();
+    events.insert("explosion".to_string(), vec!(baal::event::EventAction::PlayRandom(vec!(0, 3))));
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        short_effects: vec!("shoot.ogg".into()),
+        events: events,
+        .. Default::default()
+    };
+    match baal::reset(&setting) {
+        Err(baal::InitError::UnknownShortEffect(effect)) => assert_eq!(effect, 3),
+        _ => panic!("the effects of the events must be validated"),
+    }
+
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        music_dir: "assets/musics".into(),
+        .. Default::default()
+    };
+    baal::reset(&setting).expect("reset baal without effects");
+    let shoot = baal::effect::short::register("shoot.ogg".into()).expect("register a short effect");
+    assert_eq!(shoot.index(), 0);
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    assert_eq!(baal::stats().short_effects, 1);
+    let village = baal::music::register("village.ogg".into()).expect("register a music");
+    assert_eq!(baal::music::count(), 1);
+    baal::music::play(village);
+    assert!(baal::music::register("missing.ogg".into()).is_err());
+    assert_eq!(baal::music::count(), 1);
+
+    let preview = baal::preview::play_file("assets/effects/hit.ogg").expect("preview a file");
+    assert_eq!(preview.position(), Some(Duration::new(0, 0)));
+    preview.seek(Duration::from_millis(100)).expect("seek the preview");
+    assert_eq!(preview.position(), Some(Duration::from_millis(100)));
+    let other = baal::preview::play_file("assets/effects/shoot.ogg").expect("preview another file");
+    assert!(!preview.is_playing());
+    assert!(other.is_playing());
+    other.stop();
+    assert!(!other.is_playing());
+    assert!(baal::preview::play_file("assets/effects/missing.ogg").is_err());
+    match baal::preview::play_file("Cargo.toml") {
+        Err(error @ baal::InitError::DecodeError(..)) => assert!(std::error::Error::cause(&error).is_some()),
+        _ => panic!("a file that isn't audio must fail to decode"),
+    }
+
+    // the emitters are removed on close, the calls on them do nothing
+    let emitter = baal::Emitter::new([1., 0., 0.]);
+    baal::close();
+    baal::init_with_backend(&setting, baal::backend::Backend::Null).expect("init baal");
+    emitter.set_position([3., 0., 0.]);
+    emitter.play(shoot);
+    assert_eq!(emitter.position(), [0., 0., 0.]);
+    let other = baal::Emitter::new([2., 0., 0.]);
+    drop(emitter);
+    assert_eq!(other.position(), [2., 0., 0.]);
+
+    baal::close();
+}
@@ -1,5 +1,8 @@
+#![cfg(feature = "persistent")]
+
 extern crate baal;
 
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
@@ -8,27 +11,47 @@ fn persistent() {
     let setting = baal::Setting {
         effect_dir: "assets/effects".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.5,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
         distance_model: baal::effect::DistanceModel::Linear(1.,4.),
+        air_absorption: 0.,
+        near_field: 0.,
 
         music_transition: baal::music::MusicTransition::Instant,
 
         short_effects: vec!(),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!("electro_fly_from_xonotic_game.ogg".into()),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!(),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
     baal::init(&setting).expect("init baal");
+    let fly = setting.persistent_effect_id("electro_fly_from_xonotic_game.ogg").unwrap();
 
-    baal::effect::persistent::add_position(0,[0.0,0.0,0.0]);
+    baal::effect::persistent::add_position(fly,[0.0,0.0,0.0]);
 
-    baal::effect::persistent::update_volume(0);
-    baal::effect::persistent::clear_positions(0);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
+    baal::effect::persistent::update_volume(fly);
+    baal::effect::persistent::clear_positions(fly);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
 
     thread::sleep(Duration::from_secs(2));
 
@@ -37,12 +60,12 @@ fn persistent() {
 
     thread::sleep(Duration::from_secs(2));
 
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
-    baal::effect::persistent::add_position(0,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
+    baal::effect::persistent::add_position(fly,[1.2,1.2,1.2]);
 
     baal::effect::persistent::update_volume_for_all();
 
@@ -0,0 +1,139 @@
+extern crate baal;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn render() {
+    let setting = baal::Setting {
+        effect_dir: "assets/effects".into(),
+        music_dir: "assets/musics".into(),
+        root: None,
+
+        global_volume: 1.0,
+        music_volume: 1.0,
+        effect_volume: 1.0,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
+
+        distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
+
+        music_transition: baal::music::MusicTransition::Instant,
+
+        short_effects: vec!("shoot.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
+        persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
+        musics: vec!(),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
+    };
+
+    let backend = baal::backend::Backend::Render(baal::backend::RenderBackend::new());
+    baal::init_with_backend(&setting, backend).expect("init baal");
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
+
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    let near = baal::render(Duration::from_millis(100));
+    baal::effect::short::stop_all();
+
+    // the gain of the sound is clamped to the ceiling
+    baal::effect::short::set_effect_volume(shoot, 10.);
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    let loud = baal::render(Duration::from_millis(100));
+    baal::effect::short::stop_all();
+    baal::effect::short::set_effect_volume(shoot, 1.);
+    for (near, loud) in near.iter().zip(&loud) {
+        assert!((near - loud).abs() < 1e-3);
+    }
+
+    // the tracked effect is stopped with its handle
+    let handle = baal::effect::short::play(shoot,[0.,0.,0.]);
+    assert!(handle.is_playing());
+    handle.stop();
+    assert!(!handle.is_playing());
+    assert!(baal::render(Duration::from_millis(10)).iter().all(|&sample| sample == 0.));
+
+    // the detached effects are mixed at once in their shared sink
+    baal::effect::short::play_detached(shoot,[0.,0.,0.]);
+    baal::effect::short::play_detached(shoot,[0.,0.,0.]);
+    assert!(baal::effect::short::playing().is_empty());
+    let detached = baal::render(Duration::from_millis(100));
+    for (near, detached) in near.iter().zip(&detached) {
+        assert!((near * 2. - detached).abs() < 1e-3);
+    }
+
+    baal::effect::short::play(shoot,[60.,0.,0.]);
+    let far = baal::render(Duration::from_millis(100));
+    baal::effect::short::stop_all();
+
+    assert_eq!(near.len(), 4410);
+    assert!(near.iter().any(|&sample| sample != 0.));
+    for (near, far) in near.iter().zip(&far) {
+        assert!((near * 0.5 - far).abs() < 1e-3);
+    }
+
+    let params = baal::effect::short::PlayParams {
+        filter: Some(baal::effect::short::FilterPreset::Muffled),
+        ..Default::default()
+    };
+    baal::effect::short::play_with(shoot,[0.,0.,0.], params);
+    let muffled = baal::render(Duration::from_millis(100));
+    baal::effect::short::stop_all();
+    let energy = |samples: &[f32]| samples.iter().map(|sample| sample * sample).sum::<f32>();
+    assert!(energy(&muffled) < energy(&near));
+
+    baal::effect::short::play_sequence(&[(shoot, Duration::from_millis(100))], [0.,0.,0.]);
+    let gap = baal::render(Duration::from_millis(100));
+    let sequence = baal::render(Duration::from_millis(100));
+    baal::effect::short::stop_all();
+    assert!(gap[..4000].iter().all(|&sample| sample == 0.));
+    assert!(sequence.iter().any(|&sample| sample != 0.));
+
+    baal::effect::short::play_pcm(vec!(1.; 4410), 0, 44_100, [0.,0.,0.]);
+    baal::effect::short::play_pcm(vec!(1.; 4410), 1, 0, [0.,0.,0.]);
+    assert!(baal::effect::short::playing().is_empty());
+    baal::effect::short::play_pcm(vec!(1.; 4410), 1, 44_100, [0.,0.,0.]);
+    assert_eq!(baal::effect::short::playing()[0].effect, None);
+    let pcm = baal::render(Duration::from_millis(100));
+    assert!(pcm[0] > 0.);
+    assert!(pcm.iter().all(|&sample| (sample - pcm[0]).abs() < 1e-3));
+
+    baal::update(Duration::from_secs(1));
+    assert!(baal::stats().clipped_per_second.is_some());
+
+    // each sound is below full scale but not their sum
+    baal::effect::short::play_pcm(vec!(0.6; 4410), 1, 44_100, [0.,0.,0.]);
+    baal::effect::short::play_pcm(vec!(0.6; 4410), 1, 44_100, [0.,0.,0.]);
+    baal::render(Duration::from_millis(100));
+    baal::update(Duration::from_secs(1));
+    assert!(baal::stats().clipped_per_second.unwrap() > 0);
+
+    // the ui effects are not stopped
+    baal::effect::short::play(shoot,[0.,0.,0.]);
+    baal::effect::ui::play(shoot);
+    baal::effect::short::stop_all_with_fade(Duration::new(0, 0));
+    baal::render(Duration::from_millis(10));
+    baal::update(Duration::from_millis(10));
+    assert_eq!(baal::effect::short::playing().len(), 1);
+    baal::effect::short::stop_all();
+
+    baal::set_master_pre_gain_db(-20.);
+    baal::effect::short::play_pcm(vec!(1.; 4410), 1, 44_100, [0.,0.,0.]);
+    let attenuated = baal::render(Duration::from_millis(100));
+    assert!((attenuated[0] - pcm[0] / 10.).abs() < 1e-3);
+
+    baal::close();
+}
@@ -1,5 +1,6 @@
 extern crate baal;
 
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
@@ -8,25 +9,45 @@ fn test() {
     let setting = baal::Setting {
         effect_dir: "assets/effects".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.0,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
         distance_model: baal::effect::DistanceModel::Linear(10.,110.),
+        air_absorption: 0.,
+        near_field: 0.,
 
         music_transition: baal::music::MusicTransition::Instant,
 
         short_effects: vec!("shoot.ogg".into(),"hit.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!("village.ogg".into()),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
     baal::init(&setting).expect("fail to init baal");
+    let shoot = setting.short_effect_id("shoot.ogg").unwrap();
 
-    let child = std::thread::spawn(|| {
+    let child = std::thread::spawn(move || {
         for _ in 0..20 {
-            baal::effect::short::play(0,[0.,0.,0.]);
+            baal::effect::short::play(shoot,[0.,0.,0.]);
             thread::sleep(Duration::from_millis(1));
         }
     });
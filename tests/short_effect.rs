@@ -1,5 +1,6 @@
 extern crate baal;
 
+use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
 
@@ -8,31 +9,51 @@ fn persistent() {
     let setting = baal::Setting {
         effect_dir: "assets/musics".into(),
         music_dir: "assets/musics".into(),
+        root: None,
 
         global_volume: 0.5,
         music_volume: 0.5,
         effect_volume: 0.5,
+        volume_curve: Default::default(),
+        max_gain: 1.,
+        master_pre_gain_db: 0.,
 
         distance_model: baal::effect::DistanceModel::Linear(1.,4.),
+        air_absorption: 0.,
+        near_field: 0.,
 
         music_transition: baal::music::MusicTransition::Instant,
 
         short_effects: vec!("first_call_kevin_macleod_incompetech.ogg".into()),
+        max_short_effects: None,
+        auto_gain_db: 0.,
         persistent_effects: vec!(),
+        lod_tiers: vec!(),
+        persistent_fade: Duration::new(0, 0),
+        blend_containers: vec!(),
+        mixer_snapshots: HashMap::new(),
+        return_buses: HashMap::new(),
+        parameter_bindings: vec!(),
+        events: HashMap::new(),
         musics: vec!(),
+        deterministic_seed: None,
+        voice: Default::default(),
+        focus_policy: Default::default(),
+        watchdog: None,
     };
 
     baal::init(&setting).expect("init baal");
+    let call = setting.short_effect_id("first_call_kevin_macleod_incompetech.ogg").unwrap();
 
-    baal::effect::short::play(0,[0.0,0.0,0.0]);
+    baal::effect::short::play(call,[0.0,0.0,0.0]);
     thread::sleep(Duration::from_secs(1));
-    baal::effect::short::play(0,[0.0,0.0,0.0]);
+    baal::effect::short::play(call,[0.0,0.0,0.0]);
     thread::sleep(Duration::from_secs(1));
-    baal::effect::short::play(0,[0.0,0.0,0.0]);
+    baal::effect::short::play(call,[0.0,0.0,0.0]);
     thread::sleep(Duration::from_secs(1));
-    baal::effect::short::play(0,[0.0,0.0,0.0]);
+    baal::effect::short::play(call,[0.0,0.0,0.0]);
     thread::sleep(Duration::from_secs(1));
-    baal::effect::short::play(0,[0.0,0.0,0.0]);
+    baal::effect::short::play(call,[0.0,0.0,0.0]);
     thread::sleep(Duration::from_secs(1));
 
     baal::effect::short::stop_all();